@@ -10,10 +10,11 @@ use crate::{
     editor::{Editor, EditorContext, EditorFlow},
     editor_utils::MessageKind,
     events::{ClientEvent, ClientEventReceiver, ServerEvent, TargetClient},
+    mode::ModeKind,
     platform::{Key, Platform, PlatformEvent, PlatformRequest, ProcessTag},
     plugin::{PluginCollection, PluginDefinition},
     serialization::{DeserializeError, Serialize},
-    ui, Args, ResourceFile,
+    session_file, state_file, ui, Args, ResourceFile,
 };
 
 #[derive(Default, Clone, Copy)]
@@ -96,6 +97,18 @@ impl ServerApplication {
             }
         }
 
+        state_file::load(&mut ctx.editor);
+
+        if let Some(name) = &config.args.load_session {
+            let path = session_file::session_path(&ctx.editor, name);
+            if let Err(error) = session_file::load(&mut ctx, &path) {
+                ctx.editor
+                    .status_bar
+                    .write(MessageKind::Error)
+                    .fmt(format_args!("could not load session '{}': {}", name, error));
+            }
+        }
+
         Some(Self {
             ctx,
             client_event_receiver: ClientEventReceiver::default(),
@@ -116,8 +129,10 @@ impl ServerApplication {
                     self.ctx.clients.on_client_joined(handle)
                 }
                 PlatformEvent::ConnectionClose { handle } => {
+                    Editor::enter_mode(&mut self.ctx, handle, ModeKind::default());
                     self.ctx.clients.on_client_left(handle);
                     if self.ctx.clients.iter().next().is_none() {
+                        state_file::save(&self.ctx.editor);
                         self.ctx.platform.requests.enqueue(PlatformRequest::Quit);
                     }
                 }
@@ -144,6 +159,7 @@ impl ServerApplication {
                                 .requests
                                 .enqueue(PlatformRequest::CloseClient { handle }),
                             EditorFlow::QuitAll => {
+                                state_file::save(&self.ctx.editor);
                                 self.ctx.platform.requests.enqueue(PlatformRequest::Quit)
                             }
                         }
@@ -158,9 +174,10 @@ impl ServerApplication {
                             index,
                             handle,
                         ),
-                        ProcessTag::PickerEntries => self
+                        ProcessTag::PickerEntries(client_handle) => self
                             .ctx
-                            .editor
+                            .clients
+                            .get_mut(client_handle)
                             .picker_entries_process_buf
                             .on_process_spawned(),
                         ProcessTag::Plugin { plugin_handle, id } => {
@@ -184,15 +201,14 @@ impl ServerApplication {
                             bytes,
                             &mut self.ctx.editor.events,
                         ),
-                        ProcessTag::PickerEntries => self
-                            .ctx
-                            .editor
-                            .picker_entries_process_buf
-                            .on_process_output(
-                                &mut self.ctx.editor.picker,
-                                &self.ctx.editor.read_line,
+                        ProcessTag::PickerEntries(client_handle) => {
+                            let client = self.ctx.clients.get_mut(client_handle);
+                            client.picker_entries_process_buf.on_process_output(
+                                &mut client.picker,
+                                &client.read_line,
                                 bytes,
-                            ),
+                            )
+                        }
                         ProcessTag::Plugin { plugin_handle, id } => {
                             PluginCollection::on_process_output(
                                 &mut self.ctx,
@@ -213,11 +229,11 @@ impl ServerApplication {
                             index,
                             &mut self.ctx.editor.events,
                         ),
-                        ProcessTag::PickerEntries => {
-                            self.ctx.editor.picker_entries_process_buf.on_process_exit(
-                                &mut self.ctx.editor.picker,
-                                &self.ctx.editor.read_line,
-                            )
+                        ProcessTag::PickerEntries(client_handle) => {
+                            let client = self.ctx.clients.get_mut(client_handle);
+                            client
+                                .picker_entries_process_buf
+                                .on_process_exit(&mut client.picker, &client.read_line)
                         }
                         ProcessTag::Plugin { plugin_handle, id } => {
                             PluginCollection::on_process_exit(&mut self.ctx, plugin_handle, id)
@@ -385,4 +401,3 @@ where
         self.restore_screen();
     }
 }
-
@@ -2,6 +2,7 @@ use std::{
     fmt,
     fs::File,
     io,
+    num::NonZeroU8,
     ops::{Add, RangeBounds, Sub},
     path::{Component, Path, PathBuf},
     process::{Command, Stdio},
@@ -11,7 +12,8 @@ use std::{
 use crate::{
     buffer_history::{BufferHistory, Edit, EditKind},
     buffer_position::{BufferPosition, BufferPositionIndex, BufferRange},
-    editor_utils::ResidualStrBytes,
+    config::{Config, ConfigOverrides, ScopedConfigCollection, ScopedConfigMatches},
+    editor_utils::{leading_whitespace, ResidualStrBytes},
     events::{EditorEvent, EditorEventQueue},
     help,
     pattern::Pattern,
@@ -46,6 +48,49 @@ pub fn find_delimiter_pair_at(text: &str, index: usize, delimiter: char) -> Opti
     None
 }
 
+// parses a `<name ...>` or `</name>` tag starting at `index` and returns its name,
+// whether it's a closing tag and the index right after it (self closing tags are ignored)
+fn parse_tag_at(text: &str, index: usize) -> Option<(&str, bool, usize)> {
+    let rest = text[index..].strip_prefix('<')?;
+    let (rest, is_closing) = match rest.strip_prefix('/') {
+        Some(rest) => (rest, true),
+        None => (rest, false),
+    };
+    let name_len = rest
+        .find(|c: char| !c.is_alphanumeric() && !matches!(c, '-' | '_' | ':' | '.'))
+        .unwrap_or(rest.len());
+    if name_len == 0 {
+        return None;
+    }
+
+    let end = rest.find('>')?;
+    if rest[..end].ends_with('/') {
+        return None;
+    }
+
+    let end = text.len() - rest.len() + end + 1;
+    Some((&rest[..name_len], is_closing, end))
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SurroundPair {
+    Balanced(char, char),
+    Delimiter(char),
+    Tag,
+}
+impl SurroundPair {
+    pub fn from_key(key: char) -> Self {
+        match key {
+            '(' | ')' => Self::Balanced('(', ')'),
+            '[' | ']' => Self::Balanced('[', ']'),
+            '{' | '}' => Self::Balanced('{', '}'),
+            '<' | '>' => Self::Balanced('<', '>'),
+            't' => Self::Tag,
+            _ => Self::Delimiter(key),
+        }
+    }
+}
+
 pub fn parse_path_and_position(text: &str) -> (&str, Option<BufferPosition>) {
     let text = text.trim();
     match text.rfind(':') {
@@ -210,6 +255,97 @@ impl<'a> DoubleEndedIterator for CharDisplayDistances<'a> {
     }
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum IndentationStyle {
+    Tabs,
+    Spaces(u8),
+}
+impl IndentationStyle {
+    pub fn from_config(config: &Config) -> Self {
+        if config.indent_with_tabs {
+            Self::Tabs
+        } else {
+            Self::Spaces(config.tab_size.get())
+        }
+    }
+
+    pub fn detect<'a, I>(lines: I) -> Option<Self>
+    where
+        I: Iterator<Item = &'a str>,
+    {
+        const SAMPLE_LINE_COUNT: usize = 1024;
+        const MAX_WIDTH: usize = 8;
+
+        let mut tab_line_count = 0;
+        let mut space_line_count = 0;
+        let mut width_counts = [0; MAX_WIDTH];
+        let mut previous_spaces_len = Some(0);
+
+        for line in lines.take(SAMPLE_LINE_COUNT) {
+            let trimmed = line.trim_start_matches([' ', '\t']);
+            if trimmed.is_empty() {
+                continue;
+            }
+
+            let indentation = &line[..line.len() - trimmed.len()];
+            if indentation.starts_with('\t') {
+                tab_line_count += 1;
+                previous_spaces_len = None;
+                continue;
+            }
+            if indentation.contains('\t') {
+                previous_spaces_len = None;
+                continue;
+            }
+            if !indentation.is_empty() {
+                space_line_count += 1;
+            }
+
+            let spaces_len = indentation.len();
+            if let Some(previous_spaces_len) = previous_spaces_len {
+                let width = spaces_len.abs_diff(previous_spaces_len);
+                if (1..=MAX_WIDTH).contains(&width) {
+                    width_counts[width - 1] += 1;
+                }
+            }
+            previous_spaces_len = Some(spaces_len);
+        }
+
+        if tab_line_count == 0 && space_line_count == 0 {
+            None
+        } else if tab_line_count > space_line_count {
+            Some(Self::Tabs)
+        } else {
+            let mut best_width = 0;
+            for (i, &count) in width_counts.iter().enumerate() {
+                if count > 0 && (best_width == 0 || count > width_counts[best_width - 1]) {
+                    best_width = i + 1;
+                }
+            }
+            if best_width == 0 {
+                None
+            } else {
+                Some(Self::Spaces(best_width as _))
+            }
+        }
+    }
+
+    pub fn width(self, tab_size: u8) -> u8 {
+        match self {
+            Self::Tabs => tab_size,
+            Self::Spaces(width) => width,
+        }
+    }
+}
+impl fmt::Display for IndentationStyle {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Tabs => f.write_str("tabs"),
+            Self::Spaces(width) => write!(f, "spaces:{}", width),
+        }
+    }
+}
+
 pub struct WordRefWithIndex<'a> {
     pub kind: WordKind,
     pub text: &'a str,
@@ -598,12 +734,21 @@ impl BufferContent {
         Ok(())
     }
 
-    pub fn write<W>(&self, write: &mut W) -> io::Result<()>
+    pub fn write<W>(&self, write: &mut W, format: &BufferFormat) -> io::Result<()>
     where
         W: io::Write,
     {
-        for line in &self.lines {
-            write!(write, "{}\n", line.as_str())?;
+        if format.byte_order_mark {
+            write.write_all(b"\xef\xbb\xbf")?;
+        }
+
+        let line_ending = format.line_ending.as_str();
+        let last_line_index = self.lines.len() - 1;
+        for (i, line) in self.lines.iter().enumerate() {
+            write.write_all(line.as_str().as_bytes())?;
+            if i < last_line_index || format.insert_final_newline {
+                write.write_all(line_ending.as_bytes())?;
+            }
         }
         Ok(())
     }
@@ -913,6 +1058,266 @@ impl BufferContent {
 
         Some(BufferRange::between(left_position, right_position))
     }
+
+    // the ranges of the opening and closing tags of the innermost element that contains `position`
+    pub fn find_tag_pair_at(&self, position: BufferPosition) -> Option<(BufferRange, BufferRange)> {
+        let position = self.saturate_position(position);
+        let column_byte_index = position.column_byte_index as usize;
+
+        let mut unmatched_closing_tags = Vec::new();
+        for line_index in (0..=position.line_index).rev() {
+            let line = self.lines[line_index as usize].as_str();
+            for (i, _) in line.rmatch_indices('<') {
+                let is_cursor_line = line_index == position.line_index;
+                if is_cursor_line && i >= column_byte_index {
+                    continue;
+                }
+                let (name, is_closing, end) = match parse_tag_at(line, i) {
+                    Some(tag) => tag,
+                    None => continue,
+                };
+
+                if is_closing {
+                    if !is_cursor_line || end <= column_byte_index {
+                        unmatched_closing_tags.push(name);
+                    }
+                    continue;
+                }
+                if unmatched_closing_tags.last() == Some(&name) {
+                    unmatched_closing_tags.pop();
+                    continue;
+                }
+
+                let open = BufferRange::between(
+                    BufferPosition::line_col(line_index, i as _),
+                    BufferPosition::line_col(line_index, end as _),
+                );
+                match self.find_closing_tag(name, open.to) {
+                    Some(close) if close.to >= position => return Some((open, close)),
+                    _ => (),
+                }
+            }
+        }
+
+        None
+    }
+
+    fn find_closing_tag(&self, name: &str, from: BufferPosition) -> Option<BufferRange> {
+        let mut depth = 0;
+        for line_index in from.line_index as usize..self.lines.len() {
+            let line = self.lines[line_index].as_str();
+            for (i, _) in line.match_indices('<') {
+                if line_index == from.line_index as usize && i < from.column_byte_index as usize {
+                    continue;
+                }
+                match parse_tag_at(line, i) {
+                    Some((n, false, _)) if n == name => depth += 1,
+                    Some((n, true, _)) if n == name && depth > 0 => depth -= 1,
+                    Some((n, true, end)) if n == name => {
+                        return Some(BufferRange::between(
+                            BufferPosition::line_col(line_index as _, i as _),
+                            BufferPosition::line_col(line_index as _, end as _),
+                        ));
+                    }
+                    _ => (),
+                }
+            }
+        }
+        None
+    }
+
+    // the ranges of the left and right delimiters of the pair that surrounds `position`
+    pub fn find_surround_pair_at(
+        &self,
+        position: BufferPosition,
+        pair: SurroundPair,
+    ) -> Option<(BufferRange, BufferRange)> {
+        let (range, left, right) = match pair {
+            SurroundPair::Balanced(left, right) => (
+                self.find_balanced_chars_at(position, left, right)?,
+                left,
+                right,
+            ),
+            SurroundPair::Delimiter(delimiter) => (
+                self.find_delimiter_pair_at(position, delimiter)?,
+                delimiter,
+                delimiter,
+            ),
+            SurroundPair::Tag => return self.find_tag_pair_at(position),
+        };
+
+        let left_from = range.from.column_byte_index - left.len_utf8() as BufferPositionIndex;
+        let right_to = range.to.column_byte_index + right.len_utf8() as BufferPositionIndex;
+        Some((
+            BufferRange::between(
+                BufferPosition::line_col(range.from.line_index, left_from),
+                range.from,
+            ),
+            BufferRange::between(
+                range.to,
+                BufferPosition::line_col(range.to.line_index, right_to),
+            ),
+        ))
+    }
+
+    // the run of non blank lines (or of blank lines) that contains `line_index`
+    pub fn find_paragraph_at(
+        &self,
+        line_index: BufferPositionIndex,
+    ) -> (BufferPositionIndex, BufferPositionIndex) {
+        let is_blank = |i: usize| self.lines[i].as_str().trim().is_empty();
+        let line_index = (line_index as usize).min(self.lines.len() - 1);
+        let blank = is_blank(line_index);
+
+        let mut from = line_index;
+        while from > 0 && is_blank(from - 1) == blank {
+            from -= 1;
+        }
+        let mut to = line_index;
+        while to + 1 < self.lines.len() && is_blank(to + 1) == blank {
+            to += 1;
+        }
+
+        (from as _, to as _)
+    }
+
+    // the lines around `line_index` that are indented at least as much as it
+    // (or as the next non blank line when it's blank)
+    pub fn find_indentation_block_at(
+        &self,
+        line_index: BufferPositionIndex,
+    ) -> Option<(BufferPositionIndex, BufferPositionIndex)> {
+        let has_text = |i: usize| !self.lines[i].as_str().trim().is_empty();
+        let indentation_len = |i: usize| leading_whitespace(self.lines[i].as_str()).len();
+        let is_inside = |i: usize, len: usize| !has_text(i) || indentation_len(i) >= len;
+
+        let line_index = (line_index as usize..self.lines.len()).find(|&i| has_text(i))?;
+        let len = indentation_len(line_index);
+
+        let mut from = line_index;
+        while from > 0 && is_inside(from - 1, len) {
+            from -= 1;
+        }
+        while !has_text(from) {
+            from += 1;
+        }
+        let mut to = line_index;
+        while to + 1 < self.lines.len() && is_inside(to + 1, len) {
+            to += 1;
+        }
+        while !has_text(to) {
+            to -= 1;
+        }
+
+        Some((from as _, to as _))
+    }
+
+    // the comma separated argument at `position` inside the innermost brackets
+    // `around` also selects its separator (and the whitespace after it)
+    pub fn find_argument_at(&self, position: BufferPosition, around: bool) -> Option<BufferRange> {
+        let position = self.saturate_position(position);
+
+        let mut depth = 0;
+        let mut before = None;
+        'before: for line_index in (0..=position.line_index).rev() {
+            let mut line = self.lines[line_index as usize].as_str();
+            if line_index == position.line_index {
+                line = &line[..position.column_byte_index as usize];
+            }
+            for (i, c) in line.char_indices().rev() {
+                let is_comma = match c {
+                    ')' | ']' | '}' => {
+                        depth += 1;
+                        continue;
+                    }
+                    '(' | '[' | '{' if depth > 0 => {
+                        depth -= 1;
+                        continue;
+                    }
+                    '(' | '[' | '{' => false,
+                    ',' if depth == 0 => true,
+                    _ => continue,
+                };
+                let position = BufferPosition::line_col(line_index, (i + 1) as _);
+                before = Some((position, is_comma));
+                break 'before;
+            }
+        }
+        let (from, has_comma_before) = before?;
+
+        depth = 0;
+        let mut after = None;
+        'after: for line_index in position.line_index as usize..self.lines.len() {
+            let mut line = self.lines[line_index].as_str();
+            let mut offset = 0;
+            if line_index == position.line_index as usize {
+                offset = position.column_byte_index as usize;
+                line = &line[offset..];
+            }
+            for (i, c) in line.char_indices() {
+                let is_comma = match c {
+                    '(' | '[' | '{' => {
+                        depth += 1;
+                        continue;
+                    }
+                    ')' | ']' | '}' if depth > 0 => {
+                        depth -= 1;
+                        continue;
+                    }
+                    ')' | ']' | '}' => false,
+                    ',' if depth == 0 => true,
+                    _ => continue,
+                };
+                let position = BufferPosition::line_col(line_index as _, (offset + i) as _);
+                after = Some((position, is_comma));
+                break 'after;
+            }
+        }
+        let (to, has_comma_after) = after?;
+
+        let inner_from = self.next_non_whitespace(from).min(to);
+        let inner_to = self.previous_non_whitespace_end(to).max(inner_from);
+
+        let range = if !around {
+            BufferRange::between(inner_from, inner_to)
+        } else if has_comma_after {
+            let after_comma = BufferPosition::line_col(to.line_index, to.column_byte_index + 1);
+            BufferRange::between(inner_from, self.next_non_whitespace(after_comma))
+        } else if has_comma_before {
+            let comma = BufferPosition::line_col(from.line_index, from.column_byte_index - 1);
+            BufferRange::between(comma, inner_to)
+        } else {
+            BufferRange::between(from, to)
+        };
+        Some(range)
+    }
+
+    fn next_non_whitespace(&self, position: BufferPosition) -> BufferPosition {
+        let mut line_index = position.line_index as usize;
+        let mut column_byte_index = position.column_byte_index as usize;
+        loop {
+            let line = self.lines[line_index].as_str();
+            let rest = line[column_byte_index..].trim_start();
+            if !rest.is_empty() || line_index + 1 == self.lines.len() {
+                return BufferPosition::line_col(line_index as _, (line.len() - rest.len()) as _);
+            }
+            line_index += 1;
+            column_byte_index = 0;
+        }
+    }
+
+    fn previous_non_whitespace_end(&self, position: BufferPosition) -> BufferPosition {
+        let mut line_index = position.line_index as usize;
+        let mut column_byte_index = position.column_byte_index as usize;
+        loop {
+            let line = self.lines[line_index].as_str()[..column_byte_index].trim_end();
+            if !line.is_empty() || line_index == 0 {
+                return BufferPosition::line_col(line_index as _, line.len() as _);
+            }
+            line_index -= 1;
+            column_byte_index = self.lines[line_index].as_str().len();
+        }
+    }
 }
 
 impl fmt::Display for BufferContent {
@@ -968,7 +1373,7 @@ impl From<io::Error> for BufferWriteError {
     }
 }
 
-#[derive(Default)]
+#[derive(Default, Clone, Copy)]
 pub struct BufferProperties {
     pub history_enabled: bool,
     pub saving_enabled: bool,
@@ -995,6 +1400,41 @@ impl BufferProperties {
     }
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LineEnding {
+    Lf,
+    CrLf,
+}
+impl LineEnding {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Lf => "\n",
+            Self::CrLf => "\r\n",
+        }
+    }
+}
+
+pub struct BufferFormat {
+    pub indentation_style: Option<IndentationStyle>,
+    pub tab_size: Option<NonZeroU8>,
+    pub line_ending: LineEnding,
+    pub byte_order_mark: bool,
+    pub trim_trailing_whitespace: bool,
+    pub insert_final_newline: bool,
+}
+impl Default for BufferFormat {
+    fn default() -> Self {
+        Self {
+            indentation_style: None,
+            tab_size: None,
+            line_ending: LineEnding::Lf,
+            byte_order_mark: false,
+            trim_trailing_whitespace: false,
+            insert_final_newline: true,
+        }
+    }
+}
+
 pub struct Buffer {
     alive: bool,
     handle: BufferHandle,
@@ -1006,7 +1446,11 @@ pub struct Buffer {
     pub lints: BufferLintCollection,
     search_ranges: Vec<BufferRange>,
     needs_save: bool,
+    indentation_style: Option<IndentationStyle>,
     pub properties: BufferProperties,
+    pub format: BufferFormat,
+    pub local_config: ConfigOverrides,
+    config_scope_matches: ScopedConfigMatches,
 }
 
 impl Buffer {
@@ -1022,7 +1466,11 @@ impl Buffer {
             lints: BufferLintCollection::default(),
             search_ranges: Vec::new(),
             needs_save: false,
+            indentation_style: None,
             properties: BufferProperties::default(),
+            format: BufferFormat::default(),
+            local_config: ConfigOverrides::default(),
+            config_scope_matches: ScopedConfigMatches::default(),
         }
     }
 
@@ -1037,7 +1485,11 @@ impl Buffer {
         self.history.clear();
         self.search_ranges.clear();
         self.needs_save = false;
+        self.indentation_style = None;
         self.properties = BufferProperties::default();
+        self.format = BufferFormat::default();
+        self.local_config = ConfigOverrides::default();
+        self.config_scope_matches.clear();
     }
 
     fn remove_all_words_from_database(&mut self, word_database: &mut WordDatabase) {
@@ -1054,7 +1506,57 @@ impl Buffer {
         self.handle
     }
 
+    pub fn config(&self, config: &Config, scoped_configs: &ScopedConfigCollection) -> Config {
+        let mut config = config.clone();
+        if let Some(tab_size) = self.format.tab_size {
+            config.tab_size = tab_size;
+        }
+        if let Some(path) = self.path.to_str() {
+            scoped_configs.apply_to(&self.config_scope_matches, path, &mut config);
+        }
+        self.local_config.apply_to(&mut config);
+        config
+    }
+
+    // `config` is the buffer's config. Indentation configs explicitly set for this buffer
+    // (through scoped or local configs) take precedence over `.editorconfig` and the detected style
+    pub fn indentation_style(
+        &self,
+        config: &Config,
+        scoped_configs: &ScopedConfigCollection,
+    ) -> IndentationStyle {
+        let mut explicit_tabs = self.local_config.indent_with_tabs.is_some();
+        let mut explicit_tab_size = self.local_config.tab_size.is_some();
+        if let Some(path) = self.path.to_str() {
+            for overrides in scoped_configs.matching(&self.config_scope_matches, path) {
+                explicit_tabs |= overrides.indent_with_tabs.is_some();
+                explicit_tab_size |= overrides.tab_size.is_some();
+            }
+        }
+
+        let style = self.format.indentation_style.or(self.indentation_style);
+        let with_tabs = match style {
+            Some(style) if !explicit_tabs => style == IndentationStyle::Tabs,
+            _ => config.indent_with_tabs,
+        };
+        match style {
+            _ if with_tabs => IndentationStyle::Tabs,
+            Some(IndentationStyle::Spaces(width)) if !explicit_tab_size => {
+                IndentationStyle::Spaces(width)
+            }
+            _ => IndentationStyle::Spaces(config.tab_size.get()),
+        }
+    }
+
+    // caches which scoped configs apply to this buffer's path
+    pub fn refresh_config_scopes(&mut self, scoped_configs: &ScopedConfigCollection) {
+        if let Some(path) = self.path.to_str() {
+            scoped_configs.update_matches(path, &mut self.config_scope_matches);
+        }
+    }
+
     pub fn set_path(&mut self, path: &Path) {
+        self.config_scope_matches.clear();
         self.path.clear();
         let mut components = path.components();
         match components.next() {
@@ -1068,6 +1570,10 @@ impl Buffer {
         &self.highlighted
     }
 
+    pub fn syntax_handle(&self) -> SyntaxHandle {
+        self.syntax_handle
+    }
+
     pub fn update_highlighting(&mut self, syntaxes: &SyntaxCollection) -> HighlightResult {
         self.highlighted
             .highlight_dirty_lines(syntaxes.get(self.syntax_handle), &self.content)
@@ -1369,6 +1875,7 @@ impl Buffer {
         self.needs_save = false;
         self.history.clear();
         self.search_ranges.clear();
+        self.indentation_style = None;
 
         events.enqueue(EditorEvent::BufferRead {
             handle: self.handle,
@@ -1392,6 +1899,9 @@ impl Buffer {
             self.content.read(&mut reader)?;
         }
 
+        self.indentation_style =
+            IndentationStyle::detect(self.content.lines.iter().map(BufferLine::as_str));
+
         self.highlighted.insert_range(BufferRange::between(
             BufferPosition::zero(),
             BufferPosition::line_col((self.content.lines.len() - 1) as _, 0),
@@ -1408,8 +1918,28 @@ impl Buffer {
         Ok(())
     }
 
+    fn trim_trailing_whitespace(
+        &mut self,
+        word_database: &mut WordDatabase,
+        events: &mut EditorEventQueue,
+    ) {
+        for line_index in 0..self.content.lines.len() {
+            let line = self.content.lines[line_index].as_str();
+            let trimmed_len = line.trim_end().len();
+            if trimmed_len < line.len() {
+                let range = BufferRange::between(
+                    BufferPosition::line_col(line_index as _, trimmed_len as _),
+                    BufferPosition::line_col(line_index as _, line.len() as _),
+                );
+                self.delete_range(word_database, range, events);
+            }
+        }
+        self.commit_edits();
+    }
+
     pub fn write_to_file(
         &mut self,
+        word_database: &mut WordDatabase,
         new_path: Option<&Path>,
         events: &mut EditorEventQueue,
     ) -> Result<(), BufferWriteError> {
@@ -1427,9 +1957,14 @@ impl Buffer {
             return Err(BufferWriteError::SavingDisabled);
         }
 
+        if self.format.trim_trailing_whitespace {
+            self.trim_trailing_whitespace(word_database, events);
+        }
+
         if self.properties.is_file {
             let file = File::create(&self.path)?;
-            self.content.write(&mut io::BufWriter::new(file))?;
+            self.content
+                .write(&mut io::BufWriter::new(file), &self.format)?;
         }
 
         self.needs_save = false;
@@ -1887,6 +2422,81 @@ mod tests {
         );
     }
 
+    #[test]
+    fn indentation_style_detection() {
+        fn detect(text: &str) -> Option<IndentationStyle> {
+            IndentationStyle::detect(text.lines())
+        }
+
+        assert_eq!(None, detect(""));
+        assert_eq!(None, detect("a\n\nb\n"));
+        assert_eq!(
+            Some(IndentationStyle::Tabs),
+            detect("fn f() {\n\tif x {\n\t\ty();\n\t}\n}\n")
+        );
+        assert_eq!(
+            Some(IndentationStyle::Spaces(2)),
+            detect("fn f() {\n  if x {\n    y();\n\n    z();\n  }\n}\n")
+        );
+        assert_eq!(
+            Some(IndentationStyle::Spaces(4)),
+            detect("a:\n    b:\n        c\n    d\n        e\n   f\n")
+        );
+    }
+
+    #[test]
+    fn indentation_style_precedence() {
+        fn style(
+            buffer: &Buffer,
+            config: &Config,
+            scoped_configs: &ScopedConfigCollection,
+        ) -> IndentationStyle {
+            let config = buffer.config(config, scoped_configs);
+            buffer.indentation_style(&config, scoped_configs)
+        }
+
+        let config = Config::default();
+        let mut scoped_configs = ScopedConfigCollection::default();
+        let mut buffers = BufferCollection::default();
+        let buffer = buffers.add_new();
+        buffer.set_path(Path::new("src/main.rs"));
+        buffer.indentation_style = Some(IndentationStyle::Spaces(2));
+
+        assert_eq!(
+            IndentationStyle::Spaces(2),
+            style(buffer, &config, &scoped_configs)
+        );
+
+        buffer.format.indentation_style = Some(IndentationStyle::Tabs);
+        assert_eq!(
+            IndentationStyle::Tabs,
+            style(buffer, &config, &scoped_configs)
+        );
+
+        buffer.format.indentation_style = None;
+        let overrides = scoped_configs.get_or_add("**/*.rs").unwrap();
+        assert!(overrides.parse_config("indent_with_tabs", "true").is_ok());
+        assert_eq!(
+            IndentationStyle::Tabs,
+            style(buffer, &config, &scoped_configs)
+        );
+
+        assert!(buffer
+            .local_config
+            .parse_config("indent_with_tabs", "false")
+            .is_ok());
+        assert_eq!(
+            IndentationStyle::Spaces(2),
+            style(buffer, &config, &scoped_configs)
+        );
+
+        assert!(buffer.local_config.parse_config("tab_size", "3").is_ok());
+        assert_eq!(
+            IndentationStyle::Spaces(3),
+            style(buffer, &config, &scoped_configs)
+        );
+    }
+
     #[test]
     fn display_distance() {
         fn display_len(text: &str) -> usize {
@@ -2181,6 +2791,30 @@ mod tests {
         buffer.undo(&mut word_database, &mut events);
     }
 
+    #[test]
+    fn buffer_content_write() {
+        fn write(buffer: &BufferContent, format: &BufferFormat) -> Vec<u8> {
+            let mut bytes = Vec::new();
+            buffer.write(&mut bytes, format).unwrap();
+            bytes
+        }
+
+        let buffer = buffer_from_str("first\nsecond");
+        let mut format = BufferFormat::default();
+        assert_eq!(b"first\nsecond\n", &write(&buffer, &format)[..]);
+
+        format.line_ending = LineEnding::CrLf;
+        format.insert_final_newline = false;
+        assert_eq!(b"first\r\nsecond", &write(&buffer, &format)[..]);
+
+        format.byte_order_mark = true;
+        format.insert_final_newline = true;
+        assert_eq!(
+            b"\xef\xbb\xbffirst\r\nsecond\r\n",
+            &write(&buffer, &format)[..]
+        );
+    }
+
     #[test]
     fn buffer_content_text_range() {
         let buffer = buffer_from_str("abc\ndef\nghi");
@@ -2310,6 +2944,138 @@ mod tests {
         );
     }
 
+    #[test]
+    fn buffer_find_tag_pair() {
+        fn range(line_index: usize, from: usize, to: usize) -> BufferRange {
+            BufferRange::between(
+                BufferPosition::line_col(line_index as _, from as _),
+                BufferPosition::line_col(line_index as _, to as _),
+            )
+        }
+
+        let buffer = buffer_from_str("<div a='1'>\n  <p>x<br/>y</p><br>\n  z\n</div>");
+        let div = Some((range(0, 0, 11), range(3, 0, 6)));
+        let p = Some((range(1, 2, 5), range(1, 12, 16)));
+
+        assert_eq!(p, buffer.find_tag_pair_at(BufferPosition::line_col(1, 5)));
+        assert_eq!(p, buffer.find_tag_pair_at(BufferPosition::line_col(1, 9)));
+        assert_eq!(p, buffer.find_tag_pair_at(BufferPosition::line_col(1, 13)));
+        assert_eq!(div, buffer.find_tag_pair_at(BufferPosition::line_col(1, 1)));
+        assert_eq!(
+            div,
+            buffer.find_tag_pair_at(BufferPosition::line_col(1, 17))
+        );
+        assert_eq!(div, buffer.find_tag_pair_at(BufferPosition::line_col(2, 2)));
+        assert_eq!(div, buffer.find_tag_pair_at(BufferPosition::line_col(0, 3)));
+        assert_eq!(
+            None,
+            buffer_from_str("<p>x").find_tag_pair_at(BufferPosition::zero())
+        );
+    }
+
+    #[test]
+    fn buffer_find_surround_pair() {
+        fn range(from: usize, to: usize) -> BufferRange {
+            BufferRange::between(
+                BufferPosition::line_col(0, from as _),
+                BufferPosition::line_col(0, to as _),
+            )
+        }
+
+        let buffer = buffer_from_str("f(\"ab\")");
+        let position = BufferPosition::line_col(0, 4);
+        assert_eq!(
+            Some((range(1, 2), range(6, 7))),
+            buffer.find_surround_pair_at(position, SurroundPair::from_key(')'))
+        );
+        assert_eq!(
+            Some((range(2, 3), range(5, 6))),
+            buffer.find_surround_pair_at(position, SurroundPair::from_key('"'))
+        );
+        assert_eq!(
+            None,
+            buffer.find_surround_pair_at(position, SurroundPair::from_key('['))
+        );
+    }
+
+    #[test]
+    fn buffer_find_paragraph() {
+        let buffer = buffer_from_str("a\nb\n\n  \nc\n");
+        assert_eq!((0, 1), buffer.find_paragraph_at(0));
+        assert_eq!((0, 1), buffer.find_paragraph_at(1));
+        assert_eq!((2, 3), buffer.find_paragraph_at(3));
+        assert_eq!((4, 4), buffer.find_paragraph_at(4));
+        assert_eq!((5, 5), buffer.find_paragraph_at(9));
+    }
+
+    #[test]
+    fn buffer_find_indentation_block() {
+        let buffer = buffer_from_str("if a:\n    b\n\n    if c:\n        d\n    e\nf");
+        assert_eq!(Some((1, 5)), buffer.find_indentation_block_at(1));
+        assert_eq!(Some((1, 5)), buffer.find_indentation_block_at(2));
+        assert_eq!(Some((4, 4)), buffer.find_indentation_block_at(4));
+        assert_eq!(Some((0, 6)), buffer.find_indentation_block_at(6));
+        assert_eq!(None, buffer_from_str("a\n\n").find_indentation_block_at(1));
+    }
+
+    #[test]
+    fn buffer_find_argument() {
+        fn range(from: (usize, usize), to: (usize, usize)) -> Option<BufferRange> {
+            Some(BufferRange::between(
+                BufferPosition::line_col(from.0 as _, from.1 as _),
+                BufferPosition::line_col(to.0 as _, to.1 as _),
+            ))
+        }
+
+        let buffer = buffer_from_str("f(a, g(b, c), [d])\nh(\n    e,\n    i,\n)");
+        let col = |column_byte_index| BufferPosition::line_col(0, column_byte_index);
+
+        assert_eq!(
+            range((0, 2), (0, 3)),
+            buffer.find_argument_at(col(2), false)
+        );
+        assert_eq!(range((0, 2), (0, 5)), buffer.find_argument_at(col(2), true));
+        assert_eq!(
+            range((0, 5), (0, 12)),
+            buffer.find_argument_at(col(5), false)
+        );
+        assert_eq!(
+            range((0, 7), (0, 8)),
+            buffer.find_argument_at(col(7), false)
+        );
+        assert_eq!(
+            range((0, 10), (0, 11)),
+            buffer.find_argument_at(col(10), false)
+        );
+        assert_eq!(
+            range((0, 8), (0, 11)),
+            buffer.find_argument_at(col(10), true)
+        );
+        assert_eq!(
+            range((0, 14), (0, 17)),
+            buffer.find_argument_at(col(13), false)
+        );
+        assert_eq!(
+            range((0, 12), (0, 17)),
+            buffer.find_argument_at(col(13), true)
+        );
+        assert_eq!(
+            range((0, 15), (0, 16)),
+            buffer.find_argument_at(col(15), false)
+        );
+        assert_eq!(None, buffer.find_argument_at(col(0), false));
+
+        let position = BufferPosition::line_col(2, 4);
+        assert_eq!(
+            range((2, 4), (2, 5)),
+            buffer.find_argument_at(position, false)
+        );
+        assert_eq!(
+            range((2, 4), (3, 4)),
+            buffer.find_argument_at(position, true)
+        );
+    }
+
     #[test]
     fn buffer_display_len() {
         fn len(buffer: &BufferContent, line: usize) -> usize {
@@ -1,9 +1,13 @@
 use crate::{
-    buffer::{Buffer, BufferCollection, BufferHandle, CharDisplayDistances},
+    buffer::{
+        Buffer, BufferCollection, BufferHandle, CharDisplayDistances, DisplayLen, IndentationStyle,
+        SurroundPair,
+    },
     buffer_history::EditKind,
     buffer_position::{BufferPosition, BufferPositionIndex, BufferRange},
     client::ClientHandle,
     cursor::{Cursor, CursorCollection},
+    editor_utils::{convert_case, increment_number, LineOperation, TextCase},
     events::EditorEventQueue,
     word_database::{WordDatabase, WordIter, WordKind},
 };
@@ -371,6 +375,89 @@ impl BufferView {
         }
     }
 
+    // one cursor per line from `anchor` to the main cursor spanning the same display columns
+    // lines too short to reach the block are skipped
+    pub fn select_block(
+        &mut self,
+        buffers: &BufferCollection,
+        anchor: BufferPosition,
+        tab_size: u8,
+    ) {
+        fn display_column(line: &str, column_byte_index: usize, tab_size: u8) -> u32 {
+            DisplayLen::from(&line[..column_byte_index]).total_len(tab_size) as _
+        }
+
+        // a char that is only partially covered by the block (like a tab) is included in it
+        fn column_byte_index(line: &str, column: u32, tab_size: u8, is_end: bool) -> Option<usize> {
+            if column == 0 {
+                return Some(0);
+            }
+            let mut distances = CharDisplayDistances::new(line, tab_size);
+            if is_end {
+                distances
+                    .find(|d| d.distance >= column)
+                    .map(|d| d.char_index as usize + d.char.len_utf8())
+            } else {
+                distances
+                    .find(|d| d.distance > column)
+                    .map(|d| d.char_index as usize)
+                    .or_else(|| {
+                        let len = display_column(line, line.len(), tab_size);
+                        if column <= len {
+                            Some(line.len())
+                        } else {
+                            None
+                        }
+                    })
+            }
+        }
+
+        let buffer = buffers.get(self.buffer_handle).content();
+        let anchor = buffer.saturate_position(anchor);
+        let mut cursors = self.cursors.mut_guard();
+        let position = cursors.main_cursor().position;
+
+        let lines = buffer.lines();
+        let anchor_column = display_column(
+            lines[anchor.line_index as usize].as_str(),
+            anchor.column_byte_index as _,
+            tab_size,
+        );
+        let position_column = display_column(
+            lines[position.line_index as usize].as_str(),
+            position.column_byte_index as _,
+            tab_size,
+        );
+        let left_column = anchor_column.min(position_column);
+        let right_column = anchor_column.max(position_column);
+
+        let from_line_index = anchor.line_index.min(position.line_index);
+        let to_line_index = anchor.line_index.max(position.line_index);
+
+        cursors.clear();
+        for line_index in from_line_index..=to_line_index {
+            let line = lines[line_index as usize].as_str();
+            let left = match column_byte_index(line, left_column, tab_size, false) {
+                Some(index) => index,
+                None => continue,
+            };
+            let right = column_byte_index(line, right_column, tab_size, true)
+                .unwrap_or(line.len())
+                .max(left);
+
+            let left = BufferPosition::line_col(line_index, left as _);
+            let right = BufferPosition::line_col(line_index, right as _);
+            let (anchor, position) = if anchor_column <= position_column {
+                (left, right)
+            } else {
+                (right, left)
+            };
+            cursors.add(Cursor { anchor, position });
+        }
+
+        cursors.set_main_cursor_near_position(position);
+    }
+
     pub fn append_selection_text_and_ranges(
         &self,
         buffers: &BufferCollection,
@@ -428,6 +515,295 @@ impl BufferView {
         }
     }
 
+    // the new text is inserted before the old one is deleted so that selections end up selecting it
+    pub fn convert_case(
+        &self,
+        buffers: &mut BufferCollection,
+        word_database: &mut WordDatabase,
+        case: TextCase,
+        buf: &mut String,
+        events: &mut EditorEventQueue,
+    ) {
+        let buffer = buffers.get_mut(self.buffer_handle);
+        for cursor in self.cursors[..].iter().rev() {
+            let range = cursor.to_range();
+            buf.clear();
+            let mut len = 0;
+            let mut unchanged = true;
+            for text in buffer.content().text_range(range) {
+                convert_case(text, case, buf);
+                unchanged = unchanged && buf[len..] == *text;
+                len = buf.len();
+            }
+            if unchanged {
+                continue;
+            }
+
+            buffer.insert_text(word_database, range.to, buf, events);
+            buffer.delete_range(word_database, range, events);
+        }
+    }
+
+    pub fn edit_lines(
+        &self,
+        buffers: &mut BufferCollection,
+        word_database: &mut WordDatabase,
+        operation: &mut LineOperation,
+        buf: &mut String,
+        events: &mut EditorEventQueue,
+    ) {
+        let buffer = buffers.get_mut(self.buffer_handle);
+
+        let mut line_ranges = Vec::new();
+        for cursor in &self.cursors[..] {
+            let range = cursor.to_range();
+            match line_ranges.last_mut() {
+                Some((_, to)) if range.from.line_index <= *to => {
+                    *to = range.to.line_index.max(*to);
+                }
+                _ => line_ranges.push((range.from.line_index, range.to.line_index)),
+            }
+        }
+
+        for &(from, to) in line_ranges.iter().rev() {
+            let content = buffer.content();
+            let original_lines = &content.lines()[from as usize..=to as usize];
+            let mut lines: Vec<_> = original_lines.iter().map(|l| l.as_str()).collect();
+            operation.apply(&mut lines);
+
+            let unchanged = lines.len() == original_lines.len()
+                && lines
+                    .iter()
+                    .zip(original_lines.iter())
+                    .all(|(a, b)| *a == b.as_str());
+            if unchanged {
+                continue;
+            }
+
+            buf.clear();
+            for line in &lines {
+                buf.push_str(line);
+                buf.push('\n');
+            }
+            buf.pop();
+            let is_empty = lines.is_empty();
+
+            let line_count = content.lines().len() as BufferPositionIndex;
+            let last_line_len = content.lines()[to as usize].as_str().len();
+            let from = BufferPosition::line_col(from, 0);
+            let to = BufferPosition::line_col(to, last_line_len as _);
+
+            if is_empty {
+                // remove the lines themselves instead of leaving an empty one behind
+                let range = if to.line_index + 1 < line_count {
+                    BufferRange::between(from, BufferPosition::line_col(to.line_index + 1, 0))
+                } else if from.line_index > 0 {
+                    let previous_line_index = from.line_index - 1;
+                    let previous_line_len =
+                        content.lines()[previous_line_index as usize].as_str().len();
+                    BufferRange::between(
+                        BufferPosition::line_col(previous_line_index, previous_line_len as _),
+                        to,
+                    )
+                } else {
+                    BufferRange::between(from, to)
+                };
+                buffer.delete_range(word_database, range, events);
+            } else {
+                buffer.insert_text(word_database, to, buf, events);
+                buffer.delete_range(word_database, BufferRange::between(from, to), events);
+            }
+        }
+    }
+
+    // pads before cursors so that the nth cursor of each line ends up at the same display column
+    pub fn align_cursors(
+        &self,
+        buffers: &mut BufferCollection,
+        word_database: &mut WordDatabase,
+        indentation_style: IndentationStyle,
+        tab_size: u8,
+        buf: &mut String,
+        events: &mut EditorEventQueue,
+    ) {
+        struct Alignment {
+            index_in_line: usize,
+            insert_position: BufferPosition,
+            position: BufferPosition,
+        }
+
+        let buffer = buffers.get_mut(self.buffer_handle);
+
+        let mut alignments: Vec<Alignment> = Vec::with_capacity(self.cursors[..].len());
+        let mut max_index_in_line = 0;
+        for cursor in &self.cursors[..] {
+            let index_in_line = match alignments.last() {
+                Some(last) if last.position.line_index == cursor.position.line_index => {
+                    last.index_in_line + 1
+                }
+                _ => 0,
+            };
+            max_index_in_line = max_index_in_line.max(index_in_line);
+
+            let from = cursor.to_range().from;
+            let insert_position = if from.line_index == cursor.position.line_index {
+                from
+            } else {
+                cursor.position
+            };
+
+            alignments.push(Alignment {
+                index_in_line,
+                insert_position,
+                position: cursor.position,
+            });
+        }
+
+        let display_column = |buffer: &Buffer, position: BufferPosition| {
+            let line = buffer.content().lines()[position.line_index as usize].as_str();
+            DisplayLen::from(&line[..position.column_byte_index as usize]).total_len(tab_size)
+        };
+
+        for index_in_line in 0..=max_index_in_line {
+            let target_column = alignments
+                .iter()
+                .filter(|a| a.index_in_line == index_in_line)
+                .map(|a| display_column(buffer, a.position))
+                .max()
+                .unwrap_or(0);
+
+            for i in 0..alignments.len() {
+                let alignment = &alignments[i];
+                if alignment.index_in_line != index_in_line {
+                    continue;
+                }
+
+                let width = target_column - display_column(buffer, alignment.position);
+                if width == 0 {
+                    continue;
+                }
+
+                buf.clear();
+                let space_count = match indentation_style {
+                    IndentationStyle::Tabs => {
+                        let tab_size = tab_size as usize;
+                        for _ in 0..width / tab_size {
+                            buf.push('\t');
+                        }
+                        width % tab_size
+                    }
+                    IndentationStyle::Spaces(_) => width,
+                };
+                for _ in 0..space_count {
+                    buf.push(' ');
+                }
+
+                let insert_position = alignment.insert_position;
+                buffer.insert_text(word_database, insert_position, buf, events);
+
+                for alignment in &mut alignments[i..] {
+                    if alignment.position.line_index != insert_position.line_index {
+                        break;
+                    }
+                    if alignment.insert_position >= insert_position {
+                        alignment.insert_position.column_byte_index +=
+                            buf.len() as BufferPositionIndex;
+                    }
+                    if alignment.position >= insert_position {
+                        alignment.position.column_byte_index += buf.len() as BufferPositionIndex;
+                    }
+                }
+            }
+        }
+    }
+
+    // when `sequential`, each cursor adds `delta` one more time than the cursor before it
+    pub fn increment_numbers(
+        &self,
+        buffers: &mut BufferCollection,
+        word_database: &mut WordDatabase,
+        delta: i64,
+        sequential: bool,
+        buf: &mut String,
+        events: &mut EditorEventQueue,
+    ) {
+        let buffer = buffers.get_mut(self.buffer_handle);
+        let mut last_number_position = None;
+        for (i, cursor) in self.cursors[..].iter().enumerate().rev() {
+            let delta = if sequential {
+                delta.saturating_mul(i as i64 + 1)
+            } else {
+                delta
+            };
+
+            let position = cursor.to_range().from;
+            let line = buffer.content().lines()[position.line_index as usize].as_str();
+            buf.clear();
+            let range = match increment_number(line, position.column_byte_index as _, delta, buf) {
+                Some(range) => range,
+                None => continue,
+            };
+
+            let from = BufferPosition::line_col(position.line_index, range.start as _);
+            if last_number_position == Some(from) {
+                continue;
+            }
+            last_number_position = Some(from);
+
+            let to = BufferPosition::line_col(position.line_index, range.end as _);
+            buffer.insert_text(word_database, to, buf, events);
+            buffer.delete_range(word_database, BufferRange::between(from, to), events);
+        }
+    }
+
+    pub fn add_surround(
+        &self,
+        buffers: &mut BufferCollection,
+        word_database: &mut WordDatabase,
+        (left, right): (&str, &str),
+        events: &mut EditorEventQueue,
+    ) {
+        let buffer = buffers.get_mut(self.buffer_handle);
+        for cursor in self.cursors[..].iter().rev() {
+            let range = cursor.to_range();
+            buffer.insert_text(word_database, range.to, right, events);
+            buffer.insert_text(word_database, range.from, left, events);
+        }
+    }
+
+    // replaces the pair surrounding each cursor or deletes it if `replacement` is `None`
+    pub fn change_surround(
+        &self,
+        buffers: &mut BufferCollection,
+        word_database: &mut WordDatabase,
+        pair: SurroundPair,
+        replacement: Option<(&str, &str)>,
+        events: &mut EditorEventQueue,
+    ) {
+        let buffer = buffers.get_mut(self.buffer_handle);
+
+        let mut delimiters = Vec::new();
+        for cursor in &self.cursors[..] {
+            if let Some((left, right)) = buffer
+                .content()
+                .find_surround_pair_at(cursor.position, pair)
+            {
+                delimiters.push((left, false));
+                delimiters.push((right, true));
+            }
+        }
+        delimiters.sort_unstable_by_key(|(range, _)| range.from);
+        delimiters.dedup_by_key(|(range, _)| range.from);
+
+        for &(range, is_right) in delimiters.iter().rev() {
+            buffer.delete_range(word_database, range, events);
+            if let Some((left, right)) = replacement {
+                let text = if is_right { right } else { left };
+                buffer.insert_text(word_database, range.from, text, events);
+            }
+        }
+    }
+
     pub fn find_completion_positions(
         &self,
         buffers: &mut BufferCollection,
@@ -657,7 +1033,10 @@ mod tests {
 
     use std::ops::Range;
 
-    use crate::{buffer::BufferProperties, buffer_position::BufferPosition};
+    use crate::{
+        buffer::BufferProperties, buffer_position::BufferPosition, editor_utils::LineSortKind,
+        pattern::Pattern,
+    };
 
     struct TestContext {
         pub buffers: BufferCollection,
@@ -776,4 +1155,302 @@ mod tests {
         assert_movement(&mut ctx, 1..2, 1..0, CursorMovement::WordsBackward(1));
         assert_movement(&mut ctx, 2..0, 1..9, CursorMovement::WordsBackward(1));
     }
+
+    #[test]
+    fn buffer_view_convert_case() {
+        let mut events = EditorEventQueue::default();
+        let mut word_database = WordDatabase::new();
+        let mut buf = String::new();
+
+        let mut ctx = TestContext::with_buffer("fooBar bazQux");
+        let buffer_view = ctx.buffer_views.get_mut(ctx.buffer_view_handle);
+        let mut cursors = buffer_view.cursors.mut_guard();
+        cursors.clear();
+        cursors.add(Cursor {
+            anchor: BufferPosition::line_col(0, 0),
+            position: BufferPosition::line_col(0, 6),
+        });
+        cursors.add(Cursor {
+            anchor: BufferPosition::line_col(0, 13),
+            position: BufferPosition::line_col(0, 7),
+        });
+        drop(cursors);
+
+        ctx.buffer_views.get(ctx.buffer_view_handle).convert_case(
+            &mut ctx.buffers,
+            &mut word_database,
+            TextCase::Snake,
+            &mut buf,
+            &mut events,
+        );
+
+        let buffer_view = ctx.buffer_views.get(ctx.buffer_view_handle);
+        let buffer = ctx.buffers.get(buffer_view.buffer_handle);
+        assert_eq!("foo_bar baz_qux", buffer.content().to_string());
+    }
+
+    #[test]
+    fn buffer_view_edit_lines() {
+        let mut events = EditorEventQueue::default();
+        let mut word_database = WordDatabase::new();
+        let mut buf = String::new();
+
+        let mut ctx = TestContext::with_buffer("c\nb\na\n-\nz\ny\nx");
+        let buffer_view = ctx.buffer_views.get_mut(ctx.buffer_view_handle);
+        let mut cursors = buffer_view.cursors.mut_guard();
+        cursors.clear();
+        cursors.add(Cursor {
+            anchor: BufferPosition::line_col(0, 0),
+            position: BufferPosition::line_col(1, 0),
+        });
+        cursors.add(Cursor {
+            anchor: BufferPosition::line_col(1, 0),
+            position: BufferPosition::line_col(2, 0),
+        });
+        cursors.add(Cursor {
+            anchor: BufferPosition::line_col(4, 0),
+            position: BufferPosition::line_col(6, 1),
+        });
+        drop(cursors);
+
+        ctx.buffer_views.get(ctx.buffer_view_handle).edit_lines(
+            &mut ctx.buffers,
+            &mut word_database,
+            &mut LineOperation::Sort(LineSortKind::Lexical),
+            &mut buf,
+            &mut events,
+        );
+
+        let buffer_view = ctx.buffer_views.get(ctx.buffer_view_handle);
+        let buffer = ctx.buffers.get(buffer_view.buffer_handle);
+        assert_eq!("a\nb\nc\n-\nx\ny\nz", buffer.content().to_string());
+
+        let mut pattern = Pattern::new();
+        pattern.compile_searcher("-").unwrap();
+        let mut ctx = TestContext::with_buffer("a\n-\n-");
+        let buffer_view = ctx.buffer_views.get_mut(ctx.buffer_view_handle);
+        let mut cursors = buffer_view.cursors.mut_guard();
+        cursors.clear();
+        cursors.add(Cursor {
+            anchor: BufferPosition::line_col(1, 0),
+            position: BufferPosition::line_col(2, 0),
+        });
+        drop(cursors);
+
+        ctx.buffer_views.get(ctx.buffer_view_handle).edit_lines(
+            &mut ctx.buffers,
+            &mut word_database,
+            &mut LineOperation::Drop(&pattern),
+            &mut buf,
+            &mut events,
+        );
+
+        let buffer_view = ctx.buffer_views.get(ctx.buffer_view_handle);
+        let buffer = ctx.buffers.get(buffer_view.buffer_handle);
+        assert_eq!("a", buffer.content().to_string());
+    }
+
+    #[test]
+    fn buffer_view_select_block() {
+        fn cursor(anchor: (usize, usize), position: (usize, usize)) -> Cursor {
+            Cursor {
+                anchor: BufferPosition::line_col(anchor.0 as _, anchor.1 as _),
+                position: BufferPosition::line_col(position.0 as _, position.1 as _),
+            }
+        }
+
+        let mut ctx = TestContext::with_buffer("abcdef\n\tx\n\nab\nabcdefgh");
+        let buffer_view = ctx.buffer_views.get_mut(ctx.buffer_view_handle);
+        let mut cursors = buffer_view.cursors.mut_guard();
+        cursors.clear();
+        cursors.add(cursor((4, 4), (4, 4)));
+        drop(cursors);
+
+        buffer_view.select_block(&ctx.buffers, BufferPosition::line_col(0, 1), 4);
+        assert_eq!(
+            &[
+                cursor((0, 1), (0, 4)),
+                cursor((1, 0), (1, 1)),
+                cursor((3, 1), (3, 2)),
+                cursor((4, 1), (4, 4)),
+            ],
+            &buffer_view.cursors[..]
+        );
+        assert_eq!(cursor((4, 1), (4, 4)), *buffer_view.cursors.main_cursor());
+
+        let mut cursors = buffer_view.cursors.mut_guard();
+        cursors.clear();
+        cursors.add(cursor((0, 0), (0, 0)));
+        drop(cursors);
+
+        buffer_view.select_block(&ctx.buffers, BufferPosition::line_col(4, 5), 4);
+        assert_eq!(
+            &[
+                cursor((0, 5), (0, 0)),
+                cursor((1, 2), (1, 0)),
+                cursor((2, 0), (2, 0)),
+                cursor((3, 2), (3, 0)),
+                cursor((4, 5), (4, 0)),
+            ],
+            &buffer_view.cursors[..]
+        );
+        assert_eq!(cursor((0, 5), (0, 0)), *buffer_view.cursors.main_cursor());
+    }
+
+    #[test]
+    fn buffer_view_align_cursors() {
+        let mut events = EditorEventQueue::default();
+        let mut word_database = WordDatabase::new();
+        let mut buf = String::new();
+
+        let mut ctx = TestContext::with_buffer("a = 1, b\nabc = 2, c\n\tx = 3,  d");
+        let buffer_view = ctx.buffer_views.get_mut(ctx.buffer_view_handle);
+        let mut cursors = buffer_view.cursors.mut_guard();
+        cursors.clear();
+        for &(line_index, column_byte_index) in &[(0, 2), (0, 7), (1, 4), (1, 9), (2, 3), (2, 9)] {
+            let position = BufferPosition::line_col(line_index, column_byte_index);
+            cursors.add(Cursor {
+                anchor: position,
+                position,
+            });
+        }
+        drop(cursors);
+
+        ctx.buffer_views.get(ctx.buffer_view_handle).align_cursors(
+            &mut ctx.buffers,
+            &mut word_database,
+            IndentationStyle::Spaces(4),
+            4,
+            &mut buf,
+            &mut events,
+        );
+
+        let buffer_view = ctx.buffer_views.get(ctx.buffer_view_handle);
+        let buffer = ctx.buffers.get(buffer_view.buffer_handle);
+        assert_eq!(
+            "a     = 1,  b\nabc   = 2,  c\n\tx = 3,  d",
+            buffer.content().to_string()
+        );
+
+        let mut ctx = TestContext::with_buffer("a = 1\nabcdefghij = 2");
+        let buffer_view = ctx.buffer_views.get_mut(ctx.buffer_view_handle);
+        let mut cursors = buffer_view.cursors.mut_guard();
+        cursors.clear();
+        for &(line_index, column_byte_index) in &[(0, 2), (1, 11)] {
+            let position = BufferPosition::line_col(line_index, column_byte_index);
+            cursors.add(Cursor {
+                anchor: position,
+                position,
+            });
+        }
+        drop(cursors);
+
+        ctx.buffer_views.get(ctx.buffer_view_handle).align_cursors(
+            &mut ctx.buffers,
+            &mut word_database,
+            IndentationStyle::Tabs,
+            4,
+            &mut buf,
+            &mut events,
+        );
+
+        let buffer_view = ctx.buffer_views.get(ctx.buffer_view_handle);
+        let buffer = ctx.buffers.get(buffer_view.buffer_handle);
+        assert_eq!("a \t\t = 1\nabcdefghij = 2", buffer.content().to_string());
+    }
+
+    #[test]
+    fn buffer_view_increment_numbers() {
+        let mut events = EditorEventQueue::default();
+        let mut word_database = WordDatabase::new();
+        let mut buf = String::new();
+
+        let mut ctx = TestContext::with_buffer("0 0 a0\n0");
+        let buffer_view = ctx.buffer_views.get_mut(ctx.buffer_view_handle);
+        let mut cursors = buffer_view.cursors.mut_guard();
+        cursors.clear();
+        for &(line_index, column_byte_index) in &[(0, 0), (0, 1), (0, 4), (1, 0)] {
+            let position = BufferPosition::line_col(line_index, column_byte_index);
+            cursors.add(Cursor {
+                anchor: position,
+                position,
+            });
+        }
+        drop(cursors);
+
+        ctx.buffer_views
+            .get(ctx.buffer_view_handle)
+            .increment_numbers(
+                &mut ctx.buffers,
+                &mut word_database,
+                2,
+                true,
+                &mut buf,
+                &mut events,
+            );
+
+        let buffer_view = ctx.buffer_views.get(ctx.buffer_view_handle);
+        let buffer = ctx.buffers.get(buffer_view.buffer_handle);
+        assert_eq!("2 4 a6\n8", buffer.content().to_string());
+    }
+
+    #[test]
+    fn buffer_view_surround() {
+        fn set_cursors(ctx: &mut TestContext, ranges: &[(usize, usize)]) {
+            let buffer_view = ctx.buffer_views.get_mut(ctx.buffer_view_handle);
+            let mut cursors = buffer_view.cursors.mut_guard();
+            cursors.clear();
+            for &(from, to) in ranges {
+                cursors.add(Cursor {
+                    anchor: BufferPosition::line_col(0, from as _),
+                    position: BufferPosition::line_col(0, to as _),
+                });
+            }
+        }
+
+        fn text(ctx: &TestContext) -> String {
+            let buffer_view = ctx.buffer_views.get(ctx.buffer_view_handle);
+            let buffer = ctx.buffers.get(buffer_view.buffer_handle);
+            buffer.content().to_string()
+        }
+
+        let mut events = EditorEventQueue::default();
+        let mut word_database = WordDatabase::new();
+
+        let mut ctx = TestContext::with_buffer("ab cd");
+        set_cursors(&mut ctx, &[(0, 2), (3, 5)]);
+        ctx.buffer_views.get(ctx.buffer_view_handle).add_surround(
+            &mut ctx.buffers,
+            &mut word_database,
+            ("(", ")"),
+            &mut events,
+        );
+        assert_eq!("(ab) (cd)", text(&ctx));
+
+        let mut ctx = TestContext::with_buffer("f((a), \"b\")");
+        set_cursors(&mut ctx, &[(3, 3), (4, 4)]);
+        ctx.buffer_views
+            .get(ctx.buffer_view_handle)
+            .change_surround(
+                &mut ctx.buffers,
+                &mut word_database,
+                SurroundPair::from_key('('),
+                Some(("[", "]")),
+                &mut events,
+            );
+        assert_eq!("f([a], \"b\")", text(&ctx));
+
+        let mut ctx = TestContext::with_buffer("<p>x</p>");
+        set_cursors(&mut ctx, &[(3, 3)]);
+        ctx.buffer_views
+            .get(ctx.buffer_view_handle)
+            .change_surround(
+                &mut ctx.buffers,
+                &mut word_database,
+                SurroundPair::Tag,
+                None,
+                &mut events,
+            );
+        assert_eq!("x", text(&ctx));
+    }
 }
@@ -4,9 +4,11 @@ use crate::{
     buffer::{BufferHandle, BufferProperties, CharDisplayDistances},
     buffer_position::BufferPositionIndex,
     buffer_view::{BufferViewCollection, BufferViewHandle},
-    editor::Editor,
-    editor_utils::ResidualStrBytes,
+    editor::{BufferedKeys, Editor},
+    editor_utils::{PickerEntriesProcessBuf, ReadLine, ResidualStrBytes},
+    mode::Mode,
     navigation_history::{NavigationHistory, NavigationMovement},
+    picker::{Picker, PickerPreview},
     serialization::{DeserializeError, Deserializer, Serialize, Serializer},
 };
 
@@ -43,6 +45,13 @@ pub struct Client {
     pub(crate) scroll: BufferPositionIndex,
 
     pub(crate) navigation_history: NavigationHistory,
+    pub mode: Mode,
+    pub(crate) buffered_keys: BufferedKeys,
+    pub read_line: ReadLine,
+    pub picker: Picker,
+    pub picker_preview: PickerPreview,
+    pub(crate) picker_entries_process_buf: PickerEntriesProcessBuf,
+    following: Option<ClientHandle>,
 
     buffer_view_handle: Option<BufferViewHandle>,
     stdin_buffer_handle: Option<BufferHandle>,
@@ -59,6 +68,13 @@ impl Client {
             scroll: 0,
 
             navigation_history: NavigationHistory::default(),
+            mode: Mode::default(),
+            buffered_keys: BufferedKeys::default(),
+            read_line: ReadLine::default(),
+            picker: Picker::default(),
+            picker_preview: PickerPreview::default(),
+            picker_entries_process_buf: PickerEntriesProcessBuf::default(),
+            following: None,
 
             buffer_view_handle: None,
             stdin_buffer_handle: None,
@@ -73,6 +89,13 @@ impl Client {
         self.scroll = 0;
 
         self.navigation_history.clear();
+        self.mode = Mode::default();
+        self.buffered_keys = BufferedKeys::default();
+        self.read_line = ReadLine::default();
+        self.picker = Picker::default();
+        self.picker_preview = PickerPreview::default();
+        self.picker_entries_process_buf = PickerEntriesProcessBuf::default();
+        self.following = None;
 
         self.buffer_view_handle = None;
         self.stdin_buffer_handle = None;
@@ -87,6 +110,14 @@ impl Client {
         self.buffer_view_handle
     }
 
+    pub fn following(&self) -> Option<ClientHandle> {
+        self.following
+    }
+
+    pub(crate) fn follow(&mut self, leader: Option<ClientHandle>) {
+        self.following = leader;
+    }
+
     pub fn stdin_buffer_handle(&self) -> Option<BufferHandle> {
         self.stdin_buffer_handle
     }
@@ -211,11 +242,15 @@ impl Client {
             None => return 0,
         };
 
-        let tab_size = editor.config.tab_size.get();
         let width = self.viewport_size.0 as usize;
 
         let buffer_view = editor.buffer_views.get(buffer_view_handle);
-        let buffer = editor.buffers.get(buffer_view.buffer_handle).content();
+        let buffer = editor.buffers.get(buffer_view.buffer_handle);
+        let tab_size = buffer
+            .config(&editor.config, &editor.scoped_configs)
+            .tab_size
+            .get();
+        let buffer = buffer.content();
         let position = buffer_view.cursors.main_cursor().position;
 
         let mut height = position.line_index as usize;
@@ -279,6 +314,72 @@ impl ClientManager {
         self.clients.iter_mut().filter(|c| c.active)
     }
 
+    pub fn is_active(&self, handle: ClientHandle) -> bool {
+        match self.clients.get(handle.0 as usize) {
+            Some(client) => client.active,
+            None => false,
+        }
+    }
+
+    // followers switch to the buffer their leader is viewing and mirror its main cursor and scroll
+    pub(crate) fn update_followers(&mut self, buffer_views: &mut BufferViewCollection) {
+        for i in 0..self.clients.len() {
+            let follower = &self.clients[i];
+            let leader_handle = match follower.following {
+                Some(handle) if follower.active => handle,
+                _ => continue,
+            };
+            if !self.is_active(leader_handle) || leader_handle == follower.handle {
+                self.clients[i].following = None;
+                continue;
+            }
+
+            let leader = &self.clients[leader_handle.0 as usize];
+            let leader_scroll = leader.scroll;
+            let leader_buffer_view = match leader.buffer_view_handle {
+                Some(handle) => buffer_views.get(handle),
+                None => continue,
+            };
+            let buffer_handle = leader_buffer_view.buffer_handle;
+            let main_cursor = *leader_buffer_view.cursors.main_cursor();
+
+            let follower = &mut self.clients[i];
+            let handle =
+                buffer_views.buffer_view_handle_from_buffer_handle(follower.handle, buffer_handle);
+            if follower.buffer_view_handle != Some(handle) {
+                follower.set_buffer_view_handle(Some(handle), buffer_views);
+            }
+
+            let mut cursors = buffer_views.get_mut(handle).cursors.mut_guard();
+            cursors.clear();
+            cursors.add(main_cursor);
+            follower.scroll = leader_scroll;
+        }
+    }
+
+    // clients that have not joined yet get the view once they connect with that handle
+    pub(crate) fn restore_client_view(
+        &mut self,
+        handle: ClientHandle,
+        buffer_view_handle: BufferViewHandle,
+        scroll: BufferPositionIndex,
+        buffer_views: &BufferViewCollection,
+    ) {
+        let min_len = handle.0 as usize + 1;
+        if min_len > self.clients.len() {
+            self.clients.resize_with(min_len, Client::new);
+        }
+
+        let client = &mut self.clients[handle.0 as usize];
+        client.handle = handle;
+        if client.active {
+            client.set_buffer_view_handle(Some(buffer_view_handle), buffer_views);
+        } else {
+            client.set_buffer_view_handle_no_history(Some(buffer_view_handle));
+        }
+        client.scroll = scroll;
+    }
+
     pub(crate) fn on_client_joined(&mut self, handle: ClientHandle) {
         let min_len = handle.0 as usize + 1;
         if min_len > self.clients.len() {
@@ -1,4 +1,4 @@
-use std::{collections::VecDeque, fmt};
+use std::fmt;
 
 use crate::{
     buffer::{Buffer, BufferHandle, BufferReadError, BufferWriteError},
@@ -6,17 +6,18 @@ use crate::{
     client::ClientHandle,
     config::ParseConfigError,
     editor::{EditorContext, EditorFlow},
-    editor_utils::{MessageKind, ParseKeyMapError},
+    editor_utils::{History, MessageKind, ParseKeyMapError},
     events::KeyParseAllError,
     glob::InvalidGlobError,
     pattern::PatternError,
     plugin::PluginHandle,
+    session_file::SessionError,
 };
 
 mod builtins;
 mod expansions;
 
-const HISTORY_CAPACITY: usize = 10;
+pub const HISTORY_CAPACITY: usize = 10;
 
 pub enum CommandError {
     InvalidMacroName,
@@ -25,6 +26,7 @@ pub enum CommandError {
     TooManyArguments,
     TooFewArguments,
     NoTargetClient,
+    NoSuchClient,
     NoBufferOpened,
     UnsavedChanges,
     BufferReadError(BufferReadError),
@@ -37,10 +39,18 @@ pub enum CommandError {
     KeyMapError(ParseKeyMapError),
     KeyParseError(KeyParseAllError),
     InvalidRegisterKey,
+    NoSuchMarker,
+    InvalidNavigationEntry,
     InvalidEnvironmentVariable,
     InvalidTokenKind,
+    InvalidAutoPairs,
+    InvalidCommentDelimiters,
+    InvalidTextCase,
+    InvalidLineSortKind,
+    InvalidSelectionScope,
     PatternError(PatternError),
     InvalidGlob(InvalidGlobError),
+    SessionError(SessionError),
     OtherStatic(&'static str),
     OtherOwned(String),
 }
@@ -53,6 +63,7 @@ impl fmt::Display for CommandError {
             Self::TooManyArguments => f.write_str("too many arguments"),
             Self::TooFewArguments => f.write_str("too few arguments"),
             Self::NoTargetClient => f.write_str("no target client"),
+            Self::NoSuchClient => f.write_str("no such client"),
             Self::NoBufferOpened => f.write_str("no buffer opened"),
             Self::UnsavedChanges => f.write_str("unsaved changes"),
             Self::BufferReadError(error) => write!(f, "buffer read error: {}", error),
@@ -65,10 +76,18 @@ impl fmt::Display for CommandError {
             Self::KeyMapError(error) => write!(f, "key map error: {}", error),
             Self::KeyParseError(error) => write!(f, "key parse error: {}", error),
             Self::InvalidRegisterKey => f.write_str("invalid register key"),
+            Self::NoSuchMarker => f.write_str("no such marker"),
+            Self::InvalidNavigationEntry => f.write_str("invalid navigation history entry"),
             Self::InvalidEnvironmentVariable => f.write_str("invalid environment variable"),
             Self::InvalidTokenKind => f.write_str("invalid token kind"),
+            Self::InvalidAutoPairs => f.write_str("invalid auto pairs"),
+            Self::InvalidCommentDelimiters => f.write_str("invalid comment delimiters"),
+            Self::InvalidTextCase => f.write_str("invalid text case"),
+            Self::InvalidLineSortKind => f.write_str("invalid line sort kind"),
+            Self::InvalidSelectionScope => f.write_str("invalid selection scope"),
             Self::PatternError(error) => write!(f, "pattern error: {}", error),
             Self::InvalidGlob(error) => write!(f, "glob error: {}", error),
+            Self::SessionError(error) => write!(f, "session error: {}", error),
             Self::OtherStatic(error) => f.write_str(error),
             Self::OtherOwned(error) => f.write_str(&error),
         }
@@ -440,7 +459,7 @@ impl MacroCollection {
 pub struct CommandManager {
     commands: Vec<Command>,
     pub macros: MacroCollection,
-    history: VecDeque<String>,
+    pub history: History,
 }
 
 impl CommandManager {
@@ -448,7 +467,7 @@ impl CommandManager {
         let mut this = Self {
             commands: Vec::new(),
             macros: MacroCollection::default(),
-            history: VecDeque::with_capacity(HISTORY_CAPACITY),
+            history: History::with_capacity(HISTORY_CAPACITY),
         };
         builtins::register_commands(&mut this);
         this
@@ -498,36 +517,11 @@ impl CommandManager {
         &self.commands
     }
 
-    pub fn history_len(&self) -> usize {
-        self.history.len()
-    }
-
-    pub fn history_entry(&self, index: usize) -> &str {
-        match self.history.get(index) {
-            Some(e) => &e[..],
-            None => "",
-        }
-    }
-
     pub fn add_to_history(&mut self, entry: &str) {
-        if entry.is_empty() || entry.starts_with(|c: char| c.is_ascii_whitespace()) {
+        if entry.starts_with(|c: char| c.is_ascii_whitespace()) {
             return;
         }
-        if let Some(back) = self.history.back() {
-            if back == entry {
-                return;
-            }
-        }
-
-        let mut s = if self.history.len() == self.history.capacity() {
-            self.history.pop_front().unwrap()
-        } else {
-            String::new()
-        };
-
-        s.clear();
-        s.push_str(entry);
-        self.history.push_back(s);
+        self.history.add(entry);
     }
 
     pub fn unwrap_eval_result(
@@ -1,17 +1,23 @@
-use std::{path::Path, process::Stdio, env};
+use std::{env, path::Path, process::Stdio};
 
 use crate::{
     buffer::{parse_path_and_position, BufferProperties, BufferWriteError},
     buffer_position::BufferPosition,
-    client::ViewAnchor,
-    command::{CommandError, CommandManager, CompletionSource},
+    client::{ClientHandle, ViewAnchor},
+    command::{CommandError, CommandIO, CommandManager, CompletionSource},
     config::{ParseConfigError, CONFIG_NAMES},
     cursor::Cursor,
-    editor::EditorFlow,
-    editor_utils::{parse_process_command, MessageKind, RegisterKey},
+    editor::{EditorContext, EditorFlow},
+    editor_utils::{
+        parse_marker, parse_process_command, LineOperation, LineSortKind, MessageKind, RegisterKey,
+        TextCase,
+    },
+    editorconfig::load_buffer_format,
     help,
-    mode::{picker, read_line, ModeKind},
+    mode::{normal, picker, read_line, ModeKind},
+    pattern::Pattern,
     platform::{PlatformRequest, ProcessTag},
+    session_file,
     syntax::TokenKind,
     theme::{Color, THEME_COLOR_NAMES},
     word_database::WordIndicesIter,
@@ -93,6 +99,34 @@ pub fn register_commands(commands: &mut CommandManager) {
         Ok(())
     });
 
+    r("follow", &[], |ctx, io| {
+        let leader = io.args.try_next();
+        io.args.assert_empty()?;
+
+        let client_handle = io.client_handle()?;
+        let leader_handle = match leader {
+            Some(leader) => match leader.parse() {
+                Ok(handle) => ClientHandle(handle),
+                Err(_) => return Err(CommandError::NoSuchClient),
+            },
+            None => match ctx.clients.previous_focused_client() {
+                Some(handle) if handle != client_handle => handle,
+                _ => match ctx.clients.focused_client() {
+                    Some(handle) => handle,
+                    None => return Err(CommandError::NoSuchClient),
+                },
+            },
+        };
+        if leader_handle == client_handle || !ctx.clients.is_active(leader_handle) {
+            return Err(CommandError::NoSuchClient);
+        }
+
+        ctx.clients
+            .get_mut(client_handle)
+            .follow(Some(leader_handle));
+        Ok(())
+    });
+
     r("open", &[CompletionSource::Files], |ctx, io| {
         let mut path = io.args.next()?;
 
@@ -152,8 +186,14 @@ pub fn register_commands(commands: &mut CommandManager) {
         let buffer_handle = io.current_buffer_handle(ctx)?;
         let buffer = ctx.editor.buffers.get_mut(buffer_handle);
 
+        if let Some(path) = path {
+            buffer.set_path(path);
+            buffer.properties.is_file = true;
+            load_buffer_format(&ctx.editor.current_directory, buffer);
+        }
+
         buffer
-            .write_to_file(path, &mut ctx.editor.events)
+            .write_to_file(&mut ctx.editor.word_database, path, &mut ctx.editor.events)
             .map_err(CommandError::BufferWriteError)?;
 
         ctx.editor
@@ -168,7 +208,8 @@ pub fn register_commands(commands: &mut CommandManager) {
 
         let mut count = 0;
         for buffer in ctx.editor.buffers.iter_mut() {
-            match buffer.write_to_file(None, &mut ctx.editor.events) {
+            match buffer.write_to_file(&mut ctx.editor.word_database, None, &mut ctx.editor.events)
+            {
                 Ok(()) => count += 1,
                 Err(BufferWriteError::SavingDisabled) => (),
                 Err(error) => return Err(CommandError::BufferWriteError(error)),
@@ -255,6 +296,35 @@ pub fn register_commands(commands: &mut CommandManager) {
         Ok(())
     });
 
+    r("session-save", &[CompletionSource::Files], |ctx, io| {
+        let name = io.args.next()?;
+        io.args.assert_empty()?;
+
+        let path = session_file::session_path(&ctx.editor, name);
+        session_file::save(ctx, &path).map_err(CommandError::SessionError)?;
+
+        ctx.editor
+            .status_bar
+            .write(MessageKind::Info)
+            .fmt(format_args!("session saved to {:?}", &path));
+        Ok(())
+    });
+
+    r("session-load", &[CompletionSource::Files], |ctx, io| {
+        let name = io.args.next()?;
+        io.args.assert_empty()?;
+
+        io.assert_can_discard_all_buffers(ctx)?;
+        let path = session_file::session_path(&ctx.editor, name);
+        session_file::load(ctx, &path).map_err(CommandError::SessionError)?;
+
+        ctx.editor
+            .status_bar
+            .write(MessageKind::Info)
+            .fmt(format_args!("session loaded from {:?}", &path));
+        Ok(())
+    });
+
     static CONFIG_COMPLETIONS: &[CompletionSource] = &[CompletionSource::Custom(CONFIG_NAMES)];
     r("config", CONFIG_COMPLETIONS, |ctx, io| {
         let key = io.args.next()?;
@@ -279,6 +349,54 @@ pub fn register_commands(commands: &mut CommandManager) {
         }
     });
 
+    static CONFIG_LOCAL_COMPLETIONS: &[CompletionSource] = &[
+        CompletionSource::Custom(&["buffer"]),
+        CompletionSource::Custom(CONFIG_NAMES),
+    ];
+    r("config-local", CONFIG_LOCAL_COMPLETIONS, |ctx, io| {
+        let scope = io.args.next()?;
+        let key = io.args.next()?;
+        let value = io.args.try_next();
+        io.args.assert_empty()?;
+
+        let mut config = ctx.editor.config.clone();
+        if scope == "buffer" {
+            let buffer_handle = io.current_buffer_handle(ctx)?;
+            let buffer = ctx.editor.buffers.get_mut(buffer_handle);
+            if let Some(value) = value {
+                return buffer
+                    .local_config
+                    .parse_config(key, value)
+                    .map_err(CommandError::ConfigError);
+            }
+            config = buffer.config(&ctx.editor.config, &ctx.editor.scoped_configs);
+        } else if let Some(value) = value {
+            let result = match ctx.editor.scoped_configs.get_or_add(scope) {
+                Ok(overrides) => overrides
+                    .parse_config(key, value)
+                    .map_err(CommandError::ConfigError),
+                Err(error) => Err(CommandError::InvalidGlob(error)),
+            };
+            for buffer in ctx.editor.buffers.iter_mut() {
+                buffer.refresh_config_scopes(&ctx.editor.scoped_configs);
+            }
+            return result;
+        } else if let Some(overrides) = ctx.editor.scoped_configs.find(scope) {
+            overrides.apply_to(&mut config);
+        }
+
+        match config.display_config(key) {
+            Some(display) => {
+                ctx.editor
+                    .status_bar
+                    .write(MessageKind::Info)
+                    .fmt(format_args!("{}", display));
+                Ok(())
+            }
+            None => Err(CommandError::ConfigError(ParseConfigError::NoSuchConfig)),
+        }
+    });
+
     static COLOR_COMPLETIONS: &[CompletionSource] = &[CompletionSource::Custom(THEME_COLOR_NAMES)];
     r("color", COLOR_COMPLETIONS, |ctx, io| {
         let key = io.args.next()?;
@@ -332,7 +450,19 @@ pub fn register_commands(commands: &mut CommandManager) {
     });
 
     static SYNTAX_COMPLETIONS: &[CompletionSource] = &[CompletionSource::Custom(&[
-        "keywords", "types", "symbols", "literals", "strings", "comments", "texts",
+        "keywords",
+        "types",
+        "symbols",
+        "literals",
+        "strings",
+        "comments",
+        "texts",
+        "pairs",
+        "indent",
+        "dedent",
+        "function",
+        "line-comment",
+        "block-comment",
     ])];
     r("syntax", SYNTAX_COMPLETIONS, |ctx, io| {
         let arg = io.args.next()?;
@@ -347,6 +477,38 @@ pub fn register_commands(commands: &mut CommandManager) {
             },
         };
 
+        if arg == "pairs" {
+            if !ctx.editor.syntaxes.get_current().set_auto_pairs(pattern) {
+                return Err(CommandError::InvalidAutoPairs);
+            }
+            return Ok(());
+        }
+
+        let syntax = ctx.editor.syntaxes.get_current();
+        match arg {
+            "line-comment" => {
+                syntax.set_line_comment(pattern);
+                return Ok(());
+            }
+            "block-comment" => {
+                if !syntax.set_block_comment(pattern) {
+                    return Err(CommandError::InvalidCommentDelimiters);
+                }
+                return Ok(());
+            }
+            _ => (),
+        }
+
+        let result = match arg {
+            "indent" => Some(syntax.set_indent_rule(pattern)),
+            "dedent" => Some(syntax.set_dedent_rule(pattern)),
+            "function" => Some(syntax.set_function_rule(pattern)),
+            _ => None,
+        };
+        if let Some(result) = result {
+            return result.map_err(CommandError::PatternError);
+        }
+
         let token_kind = match arg {
             "keywords" => TokenKind::Keyword,
             "types" => TokenKind::Type,
@@ -369,6 +531,18 @@ pub fn register_commands(commands: &mut CommandManager) {
         }
     });
 
+    r("snippet", &[], |ctx, io| {
+        let glob = io.args.next()?;
+        let trigger = io.args.next()?;
+        let body = io.args.next()?;
+        io.args.assert_empty()?;
+
+        match ctx.editor.snippets.add(glob, trigger, body) {
+            Ok(()) => Ok(()),
+            Err(error) => Err(CommandError::InvalidGlob(error)),
+        }
+    });
+
     r("copy-command", &[], |ctx, io| {
         let command = io.args.next()?;
         io.args.assert_empty()?;
@@ -408,6 +582,48 @@ pub fn register_commands(commands: &mut CommandManager) {
         Ok(())
     });
 
+    r("clear-navigation-history", &[], |ctx, io| {
+        io.args.assert_empty()?;
+
+        let client_handle = io.client_handle()?;
+        ctx.clients
+            .get_mut(client_handle)
+            .navigation_history
+            .clear();
+        Ok(())
+    });
+
+    r("delete-navigation-entry", &[], |ctx, io| {
+        let index = io.args.next()?;
+        io.args.assert_empty()?;
+
+        let client_handle = io.client_handle()?;
+        let navigation_history = &mut ctx.clients.get_mut(client_handle).navigation_history;
+        match index.parse() {
+            Ok(index) if navigation_history.remove_snapshot(index) => Ok(()),
+            _ => Err(CommandError::InvalidNavigationEntry),
+        }
+    });
+
+    r("clear-markers", &[], |ctx, io| {
+        io.args.assert_empty()?;
+        ctx.editor.registers.clear_markers();
+        Ok(())
+    });
+
+    r("delete-marker", &[], |ctx, io| {
+        let key = io.args.next()?;
+        io.args.assert_empty()?;
+
+        let key = RegisterKey::from_str(key).ok_or(CommandError::InvalidRegisterKey)?;
+        let register = ctx.editor.registers.get_mut(key);
+        if parse_marker(register).is_none() {
+            return Err(CommandError::NoSuchMarker);
+        }
+        register.clear();
+        Ok(())
+    });
+
     r("set-env", &[], |_, io| {
         let key = io.args.next()?;
         let value = io.args.next()?;
@@ -428,7 +644,8 @@ pub fn register_commands(commands: &mut CommandManager) {
             None => ("readline:", arg),
         };
         io.args.assert_empty()?;
-        read_line::custom::enter_mode(ctx, continuation, prompt);
+        let client_handle = io.client_handle()?;
+        read_line::custom::enter_mode(ctx, client_handle, continuation, prompt);
         Ok(())
     });
 
@@ -439,18 +656,20 @@ pub fn register_commands(commands: &mut CommandManager) {
             None => ("pick:", arg),
         };
         io.args.assert_empty()?;
-        picker::custom::enter_mode(ctx, continuation, prompt);
+        let client_handle = io.client_handle()?;
+        picker::custom::enter_mode(ctx, client_handle, continuation, prompt);
         Ok(())
     });
 
     r("picker-entries", &[], |ctx, io| {
-        ctx.editor.picker.clear();
+        let client = ctx.clients.get_mut(io.client_handle()?);
+        client.picker.clear();
         while let Some(arg) = io.args.try_next() {
-            ctx.editor.picker.add_custom_entry(arg);
+            client.picker.add_custom_entry(arg);
         }
-        ctx.editor
+        client
             .picker
-            .filter(WordIndicesIter::empty(), ctx.editor.read_line.input());
+            .filter(WordIndicesIter::empty(), client.read_line.input());
         Ok(())
     });
 
@@ -458,7 +677,8 @@ pub fn register_commands(commands: &mut CommandManager) {
         let command = io.args.next()?;
         io.args.assert_empty()?;
 
-        ctx.editor.picker.clear();
+        let client_handle = io.client_handle()?;
+        ctx.clients.get_mut(client_handle).picker.clear();
 
         match parse_process_command(command) {
             Some(mut command) => {
@@ -469,7 +689,7 @@ pub fn register_commands(commands: &mut CommandManager) {
                 ctx.platform
                     .requests
                     .enqueue(PlatformRequest::SpawnProcess {
-                        tag: ProcessTag::PickerEntries,
+                        tag: ProcessTag::PickerEntries(client_handle),
                         command,
                         buf_len: 4 * 1024,
                     });
@@ -552,6 +772,128 @@ pub fn register_commands(commands: &mut CommandManager) {
         Ok(())
     });
 
+    static CONVERT_CASE_COMPLETIONS: &[CompletionSource] =
+        &[CompletionSource::Custom(TextCase::NAMES)];
+    r("convert-case", CONVERT_CASE_COMPLETIONS, |ctx, io| {
+        let case = io.args.next()?;
+        io.args.assert_empty()?;
+
+        let case = TextCase::from_name(case).ok_or(CommandError::InvalidTextCase)?;
+        let buffer_view_handle = io.current_buffer_view_handle(ctx)?;
+        let buffer_view = ctx.editor.buffer_views.get(buffer_view_handle);
+
+        let mut buf = ctx.editor.string_pool.acquire();
+        buffer_view.convert_case(
+            &mut ctx.editor.buffers,
+            &mut ctx.editor.word_database,
+            case,
+            &mut buf,
+            &mut ctx.editor.events,
+        );
+        ctx.editor.string_pool.release(buf);
+
+        ctx.editor
+            .buffers
+            .get_mut(buffer_view.buffer_handle)
+            .commit_edits();
+        Ok(())
+    });
+
+    static SELECT_OCCURRENCES_COMPLETIONS: &[CompletionSource] =
+        &[CompletionSource::Custom(&["in-selections"])];
+    r(
+        "select-occurrences",
+        SELECT_OCCURRENCES_COMPLETIONS,
+        |ctx, io| {
+            let scope = io.args.try_next();
+            io.args.assert_empty()?;
+
+            let within_selections = match scope {
+                Some("in-selections") => true,
+                Some(_) => return Err(CommandError::InvalidSelectionScope),
+                None => false,
+            };
+            let client_handle = io.client_handle()?;
+            normal::select_all_occurrences(ctx, client_handle, within_selections);
+            Ok(())
+        },
+    );
+
+    fn edit_lines(
+        ctx: &mut EditorContext,
+        io: &mut CommandIO,
+        mut operation: LineOperation,
+    ) -> Result<(), CommandError> {
+        let buffer_view_handle = io.current_buffer_view_handle(ctx)?;
+        let buffer_view = ctx.editor.buffer_views.get(buffer_view_handle);
+
+        let mut buf = ctx.editor.string_pool.acquire();
+        buffer_view.edit_lines(
+            &mut ctx.editor.buffers,
+            &mut ctx.editor.word_database,
+            &mut operation,
+            &mut buf,
+            &mut ctx.editor.events,
+        );
+        ctx.editor.string_pool.release(buf);
+
+        ctx.editor
+            .buffers
+            .get_mut(buffer_view.buffer_handle)
+            .commit_edits();
+        Ok(())
+    }
+
+    static SORT_LINES_COMPLETIONS: &[CompletionSource] =
+        &[CompletionSource::Custom(LineSortKind::NAMES)];
+    r("sort-lines", SORT_LINES_COMPLETIONS, |ctx, io| {
+        let kind = io.args.try_next();
+        io.args.assert_empty()?;
+
+        let kind = match kind {
+            Some(kind) => LineSortKind::from_name(kind).ok_or(CommandError::InvalidLineSortKind)?,
+            None => LineSortKind::Lexical,
+        };
+        edit_lines(ctx, io, LineOperation::Sort(kind))
+    });
+
+    r("unique-lines", &[], |ctx, io| {
+        io.args.assert_empty()?;
+        edit_lines(ctx, io, LineOperation::Unique)
+    });
+
+    r("reverse-lines", &[], |ctx, io| {
+        io.args.assert_empty()?;
+        edit_lines(ctx, io, LineOperation::Reverse)
+    });
+
+    r("shuffle-lines", &[], |ctx, io| {
+        io.args.assert_empty()?;
+        edit_lines(ctx, io, LineOperation::shuffle())
+    });
+
+    r("keep-lines", &[], |ctx, io| {
+        let pattern = io.args.next()?;
+        io.args.assert_empty()?;
+
+        let mut line_pattern = Pattern::new();
+        line_pattern
+            .compile_searcher(pattern)
+            .map_err(CommandError::PatternError)?;
+        edit_lines(ctx, io, LineOperation::Keep(&line_pattern))
+    });
+
+    r("drop-lines", &[], |ctx, io| {
+        let pattern = io.args.next()?;
+        io.args.assert_empty()?;
+
+        let mut line_pattern = Pattern::new();
+        line_pattern
+            .compile_searcher(pattern)
+            .map_err(CommandError::PatternError)?;
+        edit_lines(ctx, io, LineOperation::Drop(&line_pattern))
+    });
+
     r("command", &[], |ctx, io| {
         let name = io.args.next()?;
         let source = io.args.next()?;
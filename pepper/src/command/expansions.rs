@@ -17,6 +17,7 @@ pub enum ExpansionError {
     InvalidBufferId,
     InvalidCursorIndex,
     InvalidRegisterKey,
+    NoSuchConfig,
 }
 impl fmt::Display for ExpansionError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
@@ -27,6 +28,7 @@ impl fmt::Display for ExpansionError {
             Self::InvalidBufferId => f.write_str("invalid buffer id"),
             Self::InvalidCursorIndex => f.write_str("invalid cursor index"),
             Self::InvalidRegisterKey => f.write_str("invalid register key"),
+            Self::NoSuchConfig => f.write_str("no such config"),
         }
     }
 }
@@ -128,6 +130,22 @@ pub fn write_variable_expansion<'ctx>(
                 }
             }
         }
+        "buffer-indentation" => {
+            let buffer = if args.is_empty() {
+                current_buffer(ctx, client_handle)
+            } else {
+                let id = args.parse().map_err(|_| ExpansionError::InvalidBufferId)?;
+                ctx.editor.buffers.try_get(BufferHandle(id))
+            };
+            if let Some(buffer) = buffer {
+                let config = buffer.config(&ctx.editor.config, &ctx.editor.scoped_configs);
+                let _ = write!(
+                    output,
+                    "{}",
+                    buffer.indentation_style(&config, &ctx.editor.scoped_configs)
+                );
+            }
+        }
         "cursor-anchor-column" => {
             if let Some(cursor) = cursor(ctx, client_handle, args)? {
                 let _ = write!(output, "{}", cursor.anchor.column_byte_index);
@@ -160,15 +178,28 @@ pub fn write_variable_expansion<'ctx>(
         }
         "readline-input" => {
             assert_empty_args(args)?;
-            output.push_str(ctx.editor.read_line.input());
+            if let Some(client_handle) = client_handle {
+                output.push_str(ctx.clients.get(client_handle).read_line.input());
+            }
         }
         "picker-entry" => {
             assert_empty_args(args)?;
-            let entry = match ctx.editor.picker.current_entry(&ctx.editor.word_database) {
-                Some(entry) => entry.1,
-                None => "",
+            if let Some(client_handle) = client_handle {
+                let picker = &ctx.clients.get(client_handle).picker;
+                if let Some((_, entry)) = picker.current_entry(&ctx.editor.word_database) {
+                    output.push_str(entry);
+                }
+            }
+        }
+        "config" => {
+            let config = match current_buffer(ctx, client_handle) {
+                Some(buffer) => buffer.config(&ctx.editor.config, &ctx.editor.scoped_configs),
+                None => ctx.editor.config.clone(),
             };
-            output.push_str(entry);
+            let display = config
+                .display_config(args)
+                .ok_or(ExpansionError::NoSuchConfig)?;
+            let _ = write!(output, "{}", display);
         }
         "register" => {
             let key = RegisterKey::from_str(args).ok_or(ExpansionError::InvalidRegisterKey)?;
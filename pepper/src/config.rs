@@ -1,4 +1,9 @@
-use std::{fmt, num::NonZeroU8};
+use std::{fmt, num::NonZeroU8, rc::Rc, str::FromStr};
+
+use crate::{
+    editor_utils::hash_bytes,
+    glob::{Glob, InvalidGlobError},
+};
 
 pub enum ParseConfigError {
     NoSuchConfig,
@@ -13,10 +18,120 @@ impl fmt::Display for ParseConfigError {
     }
 }
 
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum CompletionScope {
+    All,
+    Buffer,
+    Syntax,
+}
+impl FromStr for CompletionScope {
+    type Err = ();
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "all" => Ok(Self::All),
+            "buffer" => Ok(Self::Buffer),
+            "syntax" => Ok(Self::Syntax),
+            _ => Err(()),
+        }
+    }
+}
+impl fmt::Display for CompletionScope {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::All => f.write_str("all"),
+            Self::Buffer => f.write_str("buffer"),
+            Self::Syntax => f.write_str("syntax"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct PersistedState(u8);
+impl PersistedState {
+    pub const NONE: Self = Self(0);
+    pub const REGISTERS: Self = Self(1 << 0);
+    pub const COMMAND_HISTORY: Self = Self(1 << 1);
+    pub const SEARCH_HISTORY: Self = Self(1 << 2);
+    pub const RECENT_FILES: Self = Self(1 << 3);
+    pub const ALL: Self = Self(
+        Self::REGISTERS.0 | Self::COMMAND_HISTORY.0 | Self::SEARCH_HISTORY.0 | Self::RECENT_FILES.0,
+    );
+
+    const NAMES: &'static [(&'static str, Self)] = &[
+        ("registers", Self::REGISTERS),
+        ("command-history", Self::COMMAND_HISTORY),
+        ("search-history", Self::SEARCH_HISTORY),
+        ("recent-files", Self::RECENT_FILES),
+    ];
+
+    pub fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+impl FromStr for PersistedState {
+    type Err = ();
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "none" => return Ok(Self::NONE),
+            "all" => return Ok(Self::ALL),
+            _ => (),
+        }
+        let mut parts = Self::NONE;
+        for name in s.split(',') {
+            match Self::NAMES.iter().find(|(n, _)| *n == name) {
+                Some((_, part)) => parts.0 |= part.0,
+                None => return Err(()),
+            }
+        }
+        Ok(parts)
+    }
+}
+impl fmt::Display for PersistedState {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if *self == Self::NONE {
+            return f.write_str("none");
+        }
+        let mut names = Self::NAMES.iter().filter(|(_, part)| self.contains(*part));
+        if let Some((name, _)) = names.next() {
+            f.write_str(name)?;
+        }
+        for (name, _) in names {
+            f.write_str(",")?;
+            f.write_str(name)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, PartialEq, Eq)]
+pub struct ConfigPath(Rc<str>);
+impl ConfigPath {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+impl Default for ConfigPath {
+    fn default() -> Self {
+        Self("".into())
+    }
+}
+impl FromStr for ConfigPath {
+    type Err = ();
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(Self(s.into()))
+    }
+}
+impl fmt::Display for ConfigPath {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
 macro_rules! config_values {
     ($($name:ident: $type:ty = $default:expr,)*) => {
         pub static CONFIG_NAMES: &[&str] = &[$(stringify!($name),)*];
 
+        #[derive(Clone)]
         pub struct Config {
             $(pub $name: $type,)*
         }
@@ -52,6 +167,30 @@ macro_rules! config_values {
             }
         }
 
+        #[derive(Default)]
+        pub struct ConfigOverrides {
+            $(pub $name: Option<$type>,)*
+        }
+
+        impl ConfigOverrides {
+            pub fn parse_config(&mut self, key: &str, value: &str) -> Result<(), ParseConfigError> {
+                match key {
+                    $(stringify!($name) => match value.parse() {
+                        Ok(value) => self.$name = Some(value),
+                        Err(_) => return Err(ParseConfigError::InvalidValue),
+                    },)*
+                    _ => return Err(ParseConfigError::NoSuchConfig),
+                }
+                Ok(())
+            }
+
+            pub fn apply_to(&self, config: &mut Config) {
+                $(if let Some(value) = &self.$name {
+                    config.$name = value.clone();
+                })*
+            }
+        }
+
         pub struct DisplayConfig<'a> {
             config: &'a Config,
             writter: fn(&Config, &mut fmt::Formatter) -> fmt::Result
@@ -65,6 +204,90 @@ macro_rules! config_values {
     }
 }
 
+struct ScopedConfig {
+    glob_hash: u64,
+    glob: Glob,
+    overrides: ConfigOverrides,
+}
+
+// indices of the scopes whose glob matches a path among the first `checked_len` scopes
+#[derive(Default)]
+pub struct ScopedConfigMatches {
+    indices: Vec<u32>,
+    checked_len: u32,
+}
+
+impl ScopedConfigMatches {
+    pub fn clear(&mut self) {
+        self.indices.clear();
+        self.checked_len = 0;
+    }
+}
+
+#[derive(Default)]
+pub struct ScopedConfigCollection {
+    scopes: Vec<ScopedConfig>,
+}
+
+impl ScopedConfigCollection {
+    pub fn get_or_add(&mut self, glob: &str) -> Result<&mut ConfigOverrides, InvalidGlobError> {
+        let glob_hash = hash_bytes(glob.as_bytes());
+        let index = match self.scopes.iter().position(|s| s.glob_hash == glob_hash) {
+            Some(index) => index,
+            None => {
+                let mut scope = ScopedConfig {
+                    glob_hash,
+                    glob: Glob::default(),
+                    overrides: ConfigOverrides::default(),
+                };
+                scope.glob.compile(glob)?;
+                self.scopes.push(scope);
+                self.scopes.len() - 1
+            }
+        };
+        Ok(&mut self.scopes[index].overrides)
+    }
+
+    pub fn find(&self, glob: &str) -> Option<&ConfigOverrides> {
+        let glob_hash = hash_bytes(glob.as_bytes());
+        self.scopes
+            .iter()
+            .find(|s| s.glob_hash == glob_hash)
+            .map(|s| &s.overrides)
+    }
+
+    // scopes are never removed and their globs never change, so only the scopes added
+    // since the last update need to be matched against `path`
+    pub fn update_matches(&self, path: &str, matches: &mut ScopedConfigMatches) {
+        let checked_len = matches.checked_len as usize;
+        for (i, scope) in self.scopes.iter().enumerate().skip(checked_len) {
+            if scope.glob.matches(path) {
+                matches.indices.push(i as _);
+            }
+        }
+        matches.checked_len = self.scopes.len() as _;
+    }
+
+    pub fn apply_to(&self, matches: &ScopedConfigMatches, path: &str, config: &mut Config) {
+        for overrides in self.matching(matches, path) {
+            overrides.apply_to(config);
+        }
+    }
+
+    pub fn matching<'a>(
+        &'a self,
+        matches: &'a ScopedConfigMatches,
+        path: &'a str,
+    ) -> impl 'a + Iterator<Item = &'a ConfigOverrides> {
+        let checked_len = (matches.checked_len as usize).min(self.scopes.len());
+        let checked = matches.indices.iter().map(|&i| &self.scopes[i as usize]);
+        let unchecked = self.scopes[checked_len..]
+            .iter()
+            .filter(move |s| s.glob.matches(path));
+        checked.chain(unchecked).map(|s| &s.overrides)
+    }
+}
+
 config_values! {
     tab_size: NonZeroU8 = NonZeroU8::new(4).unwrap(),
     indent_with_tabs: bool = true,
@@ -75,6 +298,108 @@ config_values! {
     visual_tab_repeat: char = ' ',
 
     completion_min_len: u8 = 3,
+    completion_scope: CompletionScope = CompletionScope::All,
     picker_max_height: u8 = 8,
+    picker_preview_height: u8 = 0,
     status_bar_max_height: NonZeroU8 = NonZeroU8::new(8).unwrap(),
+    status_bar_show_viewers: bool = false,
+
+    state_file: ConfigPath = ConfigPath::default(),
+    state_persist: PersistedState = PersistedState::ALL,
+    session_directory: ConfigPath = ConfigPath::default(),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scoped_config() {
+        let mut scoped_configs = ScopedConfigCollection::default();
+        let overrides = scoped_configs.get_or_add("**/*.yaml").unwrap();
+        assert!(overrides.parse_config("tab_size", "2").is_ok());
+        assert!(overrides.parse_config("tab_size", "0").is_err());
+        assert!(overrides.parse_config("no_such_config", "0").is_err());
+        assert!(scoped_configs.get_or_add("a**/c").is_err());
+
+        let mut local_config = ConfigOverrides::default();
+        assert!(local_config
+            .parse_config("indent_with_tabs", "false")
+            .is_ok());
+
+        let matches = ScopedConfigMatches::default();
+        let mut config = Config::default();
+        scoped_configs.apply_to(&matches, "src/main.rs", &mut config);
+        assert_eq!(4, config.tab_size.get());
+
+        scoped_configs.apply_to(&matches, "docs/config.yaml", &mut config);
+        local_config.apply_to(&mut config);
+        assert_eq!(2, config.tab_size.get());
+        assert!(!config.indent_with_tabs);
+    }
+
+    #[test]
+    fn scoped_config_matches() {
+        let mut scoped_configs = ScopedConfigCollection::default();
+        let overrides = scoped_configs.get_or_add("**/*.yaml").unwrap();
+        assert!(overrides.parse_config("tab_size", "2").is_ok());
+
+        let path = "docs/config.yaml";
+        let mut matches = ScopedConfigMatches::default();
+        scoped_configs.update_matches(path, &mut matches);
+        assert_eq!(&[0], &matches.indices[..]);
+        assert_eq!(1, matches.checked_len);
+
+        let overrides = scoped_configs.get_or_add("src/*").unwrap();
+        assert!(overrides.parse_config("tab_size", "3").is_ok());
+        let overrides = scoped_configs.get_or_add("docs/*").unwrap();
+        assert!(overrides.parse_config("tab_size", "8").is_ok());
+
+        let mut config = Config::default();
+        scoped_configs.apply_to(&matches, path, &mut config);
+        assert_eq!(8, config.tab_size.get());
+
+        scoped_configs.update_matches(path, &mut matches);
+        assert_eq!(&[0, 2], &matches.indices[..]);
+        assert_eq!(3, matches.checked_len);
+
+        let overrides = scoped_configs.get_or_add("**/*.yaml").unwrap();
+        assert!(overrides.parse_config("indent_with_tabs", "false").is_ok());
+        let mut config = Config::default();
+        scoped_configs.apply_to(&matches, path, &mut config);
+        assert_eq!(8, config.tab_size.get());
+        assert!(!config.indent_with_tabs);
+
+        matches.clear();
+        assert_eq!(2, scoped_configs.matching(&matches, path).count());
+    }
+
+    #[test]
+    fn persisted_state() {
+        let mut config = Config::default();
+        assert!(config.parse_config("state_persist", "none").is_ok());
+        assert!(!config.state_persist.contains(PersistedState::REGISTERS));
+
+        assert!(config
+            .parse_config("state_persist", "registers,recent-files")
+            .is_ok());
+        assert!(config.state_persist.contains(PersistedState::REGISTERS));
+        assert!(config.state_persist.contains(PersistedState::RECENT_FILES));
+        assert!(!config
+            .state_persist
+            .contains(PersistedState::COMMAND_HISTORY));
+        assert_eq!(
+            "registers,recent-files",
+            config.display_config("state_persist").unwrap().to_string()
+        );
+
+        assert!(config.parse_config("state_persist", "registers,").is_err());
+        assert!(config.parse_config("state_persist", "all").is_ok());
+        assert!(config.state_persist.contains(PersistedState::ALL));
+
+        assert!(config
+            .parse_config("state_file", "/home/user/.pepper_state")
+            .is_ok());
+        assert_eq!("/home/user/.pepper_state", config.state_file.as_str());
+    }
 }
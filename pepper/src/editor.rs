@@ -8,27 +8,31 @@ use crate::{
     buffer_position::{BufferPosition, BufferRange},
     buffer_view::{BufferViewCollection, BufferViewHandle},
     client::{ClientHandle, ClientManager},
-    command::CommandManager,
-    config::Config,
+    command::{CommandManager, HISTORY_CAPACITY},
+    config::{Config, ScopedConfigCollection},
     editor_utils::{
-        KeyMapCollection, MatchResult, PickerEntriesProcessBuf, ReadLine, RegisterCollection,
-        RegisterKey, StatusBar, StatusBarDisplay, StringPool,
+        History, KeyMapCollection, MatchResult, RegisterCollection, RegisterKey, StatusBar,
+        StatusBarDisplay, StringPool,
     },
+    editorconfig::load_buffer_format,
     events::{
         ClientEvent, EditorEvent, EditorEventIter, EditorEventQueue, KeyParseAllError, KeyParser,
         ServerEvent, TargetClient,
     },
     mode::{Mode, ModeKind},
     pattern::Pattern,
-    picker::Picker,
+    picker::{PickerPreview, PickerPreviewSource},
     platform::{Key, KeyCode, Platform, PlatformRequest},
     plugin::{PluginCollection, PluginHandle},
+    snippet::SnippetCollection,
     syntax::{HighlightResult, SyntaxCollection},
     theme::Theme,
     ui,
     word_database::WordDatabase,
 };
 
+const RECENT_FILES_CAPACITY: usize = 32;
+
 #[derive(Clone, Copy)]
 pub enum EditorFlow {
     Continue,
@@ -93,10 +97,23 @@ pub struct EditorContext {
 }
 impl EditorContext {
     pub(crate) fn render(&mut self) {
-        let picker_height = self
-            .editor
-            .picker
-            .update_scroll(self.editor.config.picker_max_height as _);
+        self.clients.update_followers(&mut self.editor.buffer_views);
+
+        let mut client_handles = [ClientHandle(0); u8::MAX as usize + 1];
+        let mut client_count = 0;
+        let mut client_buffer_view_handles = [None; u8::MAX as usize + 1];
+        for c in self.clients.iter() {
+            if c.has_ui() {
+                client_handles[client_count] = c.handle();
+                client_count += 1;
+                client_buffer_view_handles[c.handle().0 as usize] = c.buffer_view_handle();
+            }
+        }
+
+        for &handle in &client_handles[..client_count] {
+            PickerPreview::update(self, handle);
+        }
+
         self.editor.status_bar.on_before_render();
         let focused_client = self.clients.focused_client();
 
@@ -117,6 +134,20 @@ impl EditorContext {
                 }
             }
 
+            if let PickerPreviewSource::Buffer(handle) = c.picker_preview.source() {
+                let buffer = self.editor.buffers.get_mut(handle);
+                if let HighlightResult::Pending = buffer.update_highlighting(&self.editor.syntaxes)
+                {
+                    needs_redraw = true;
+                }
+            }
+
+            let picker_height = c
+                .picker
+                .update_scroll(self.editor.config.picker_max_height as _)
+                + c.picker_preview
+                    .height(self.editor.config.picker_preview_height);
+
             let has_focus = focused_client == Some(c.handle());
 
             let (status_bar_display, margin_bottom) = if has_focus {
@@ -134,7 +165,7 @@ impl EditorContext {
                 let margin_bottom = status_bar_height.saturating_sub(1).max(picker_height);
                 (status_bar_display, margin_bottom)
             } else {
-                (StatusBarDisplay::default(), 0)
+                (StatusBarDisplay::default(), picker_height)
             };
 
             c.scroll_to_main_cursor(&self.editor, margin_bottom);
@@ -144,9 +175,9 @@ impl EditorContext {
             let ctx = ui::RenderContext {
                 editor: &self.editor,
                 status_bar_display: &status_bar_display,
-                viewport_size: c.viewport_size,
-                scroll: c.scroll,
+                client: c,
                 has_focus,
+                client_buffer_view_handles: &client_buffer_view_handles,
             };
             ui::draw(&ctx, c.buffer_view_handle(), write);
             ServerEvent::Display(&[]).serialize_bytes_variant_header(write);
@@ -179,6 +210,7 @@ impl EditorContext {
                     EditorEvent::BufferRead { handle } => {
                         let buffer = self.editor.buffers.get_mut(handle);
                         buffer.refresh_syntax(&self.editor.syntaxes);
+                        buffer.refresh_config_scopes(&self.editor.scoped_configs);
                         self.editor.buffer_views.on_buffer_read(buffer);
                     }
                     EditorEvent::BufferInsertText { handle, range, .. } => {
@@ -186,25 +218,30 @@ impl EditorContext {
                         self.editor
                             .buffer_views
                             .on_buffer_insert_text(handle, range);
-                        self.editor
-                            .mode
-                            .insert_state
-                            .on_buffer_insert_text(handle, range);
+                        for client in self.clients.iter_mut() {
+                            client
+                                .mode
+                                .insert_state
+                                .on_buffer_insert_text(handle, range);
+                        }
                     }
                     EditorEvent::BufferDeleteText { handle, range } => {
                         self.editor.buffers.on_buffer_delete_text(handle, range);
                         self.editor
                             .buffer_views
                             .on_buffer_delete_text(handle, range);
-                        self.editor
-                            .mode
-                            .insert_state
-                            .on_buffer_delete_text(handle, range);
+                        for client in self.clients.iter_mut() {
+                            client
+                                .mode
+                                .insert_state
+                                .on_buffer_delete_text(handle, range);
+                        }
                     }
                     EditorEvent::BufferWrite { handle, new_path } => {
                         let buffer = self.editor.buffers.get_mut(handle);
                         if new_path {
                             buffer.refresh_syntax(&self.editor.syntaxes);
+                            buffer.refresh_config_scopes(&self.editor.scoped_configs);
                         }
 
                         for client in self.clients.iter() {
@@ -261,11 +298,12 @@ impl EditorContext {
 pub struct Editor {
     pub current_directory: PathBuf,
     pub config: Config,
+    pub scoped_configs: ScopedConfigCollection,
     pub theme: Theme,
     pub syntaxes: SyntaxCollection,
+    pub snippets: SnippetCollection,
     pub keymaps: KeyMapCollection,
 
-    pub mode: Mode,
     pub buffers: BufferCollection,
     pub buffer_views: BufferViewCollection,
     pub word_database: WordDatabase,
@@ -273,8 +311,8 @@ pub struct Editor {
     pub buffered_keys: BufferedKeys,
     pub recording_macro: Option<RegisterKey>,
     pub registers: RegisterCollection,
-    pub read_line: ReadLine,
-    pub picker: Picker,
+    pub search_history: History,
+    pub recent_files: History,
     pub string_pool: StringPool,
 
     pub status_bar: StatusBar,
@@ -282,20 +320,18 @@ pub struct Editor {
 
     pub commands: CommandManager,
     pub events: EditorEventQueue,
-
-    pub(crate) picker_entries_process_buf: PickerEntriesProcessBuf,
 }
 impl Editor {
     pub fn new(current_directory: PathBuf) -> Self {
         Self {
             current_directory,
             config: Config::default(),
+            scoped_configs: ScopedConfigCollection::default(),
             theme: Theme::default(),
             syntaxes: SyntaxCollection::new(),
+            snippets: SnippetCollection::default(),
             keymaps: KeyMapCollection::default(),
 
-            mode: Mode::default(),
-
             buffers: BufferCollection::default(),
             buffer_views: BufferViewCollection::default(),
             word_database: WordDatabase::new(),
@@ -303,8 +339,8 @@ impl Editor {
             buffered_keys: BufferedKeys::default(),
             recording_macro: None,
             registers: RegisterCollection::new(),
-            read_line: ReadLine::default(),
-            picker: Picker::default(),
+            search_history: History::with_capacity(HISTORY_CAPACITY),
+            recent_files: History::with_capacity(RECENT_FILES_CAPACITY),
             string_pool: StringPool::default(),
 
             status_bar: StatusBar::new(),
@@ -312,8 +348,6 @@ impl Editor {
 
             commands: CommandManager::new(),
             events: EditorEventQueue::default(),
-
-            picker_entries_process_buf: PickerEntriesProcessBuf::default(),
         }
     }
 
@@ -325,6 +359,9 @@ impl Editor {
         create_if_not_found: bool,
     ) -> Result<BufferViewHandle, BufferReadError> {
         if let Some(buffer_handle) = self.buffers.find_with_path(&self.current_directory, path) {
+            if self.buffers.get(buffer_handle).properties.saving_enabled {
+                self.add_recent_file(path);
+            }
             let handle = self
                 .buffer_views
                 .buffer_view_handle_from_buffer_handle(client_handle, buffer_handle);
@@ -333,11 +370,16 @@ impl Editor {
             let path = path.strip_prefix(&self.current_directory).unwrap_or(path);
             let buffer = self.buffers.add_new();
             buffer.set_path(path);
+            let saving_enabled = properties.saving_enabled;
             buffer.properties = properties;
+            load_buffer_format(&self.current_directory, buffer);
 
             match buffer.read_from_file(&mut self.word_database, &mut self.events) {
                 Ok(()) => {
                     let handle = self.buffer_views.add_new(client_handle, buffer.handle());
+                    if saving_enabled {
+                        self.add_recent_file(path);
+                    }
                     Ok(handle)
                 }
                 Err(BufferReadError::FileNotFound) if create_if_not_found => {
@@ -353,13 +395,25 @@ impl Editor {
         }
     }
 
-    pub fn enter_mode(&mut self, next: ModeKind) {
-        Mode::change_to(self, next);
+    fn add_recent_file(&mut self, path: &Path) {
+        let path = self.current_directory.join(path);
+        if let Some(path) = path.to_str() {
+            self.recent_files.remove(path);
+            self.recent_files.add(path);
+        }
     }
 
-    pub fn enter_plugin_mode(&mut self, plugin_handle: PluginHandle) {
-        Mode::change_to(self, ModeKind::Plugin);
-        self.mode.plugin_handle = Some(plugin_handle);
+    pub fn enter_mode(ctx: &mut EditorContext, client_handle: ClientHandle, next: ModeKind) {
+        Mode::change_to(ctx, client_handle, next);
+    }
+
+    pub fn enter_plugin_mode(
+        ctx: &mut EditorContext,
+        client_handle: ClientHandle,
+        plugin_handle: PluginHandle,
+    ) {
+        Mode::change_to(ctx, client_handle, ModeKind::Plugin);
+        ctx.clients.get_mut(client_handle).mode.plugin_handle = Some(plugin_handle);
     }
 
     pub fn execute_keys(
@@ -370,7 +424,7 @@ impl Editor {
         let start_index = keys.index;
 
         match ctx.editor.keymaps.matches(
-            ctx.editor.mode.kind(),
+            ctx.clients.get(client_handle).mode.kind(),
             &ctx.editor.buffered_keys.0[start_index..],
         ) {
             MatchResult::None => (),
@@ -391,7 +445,7 @@ impl Editor {
                 None => return EditorFlow::Continue,
                 Some(EditorFlow::Continue) => (),
                 Some(flow) => {
-                    ctx.editor.enter_mode(ModeKind::default());
+                    Self::enter_mode(ctx, client_handle, ModeKind::default());
                     ctx.editor.buffered_keys.0.truncate(start_index);
                     return flow;
                 }
@@ -413,6 +467,16 @@ impl Editor {
         EditorFlow::Continue
     }
 
+    // keys that are still waiting for more input (like an incomplete binding) are moved back
+    // into the client so they are only ever completed by that same client's next keys
+    fn take_pending_keys(ctx: &mut EditorContext, client_handle: ClientHandle) {
+        let client = ctx.clients.get_mut(client_handle);
+        client
+            .buffered_keys
+            .0
+            .append(&mut ctx.editor.buffered_keys.0);
+    }
+
     pub(crate) fn on_client_event(
         ctx: &mut EditorContext,
         client_handle: ClientHandle,
@@ -430,15 +494,25 @@ impl Editor {
 
                 if ctx.clients.focus_client(client_handle) {
                     ctx.editor.recording_macro = None;
-                    ctx.editor.buffered_keys.0.clear();
-                    ctx.editor.enter_mode(ModeKind::default());
                 }
 
                 if key.code != KeyCode::None {
+                    let client = ctx.clients.get_mut(client_handle);
+                    if client.following().is_some() {
+                        client.follow(None);
+                        return EditorFlow::Continue;
+                    }
                     ctx.editor.status_bar.clear();
                 }
+                let client = ctx.clients.get_mut(client_handle);
+                ctx.editor
+                    .buffered_keys
+                    .0
+                    .splice(0..0, client.buffered_keys.0.drain(..));
                 ctx.editor.buffered_keys.0.push(key);
-                Self::execute_keys(ctx, client_handle, KeysIterator { index: 0 })
+                let flow = Self::execute_keys(ctx, client_handle, KeysIterator { index: 0 });
+                Self::take_pending_keys(ctx, client_handle);
+                flow
             }
             ClientEvent::Resize(width, height) => {
                 let client = ctx.clients.get_mut(client_handle);
@@ -455,6 +529,7 @@ impl Editor {
                 };
 
                 let result = CommandManager::eval(ctx, Some(client_handle), commands);
+                Self::take_pending_keys(ctx, client_handle);
                 CommandManager::unwrap_eval_result(ctx, result, commands, Some("client-commands"))
             }
             ClientEvent::StdinInput(target, bytes) => {
@@ -1,11 +1,20 @@
-use std::{fmt, process::Command};
+use std::{
+    cmp::Ordering,
+    collections::{hash_map::RandomState, HashSet, VecDeque},
+    fmt,
+    hash::{BuildHasher, Hasher},
+    ops::Range,
+    process::Command,
+};
 
 use crate::{
-    buffer::char_display_len,
+    buffer::{char_display_len, parse_path_and_position, IndentationStyle},
+    buffer_position::BufferPosition,
     command::CommandTokenizer,
     editor::{BufferedKeys, KeysIterator},
     events::{KeyParseAllError, KeyParser},
     mode::ModeKind,
+    pattern::Pattern,
     picker::Picker,
     platform::{Key, KeyCode, Platform},
     word_database::{WordIter, WordKind},
@@ -434,10 +443,23 @@ impl RegisterKey {
     }
 }
 
+// markers are registers holding a `path:line,col` (see `m<char>` in normal mode)
+pub fn parse_marker(text: &str) -> Option<(&str, BufferPosition)> {
+    if text.contains('\n') {
+        return None;
+    }
+    match parse_path_and_position(text) {
+        (path, Some(position)) if !path.is_empty() => Some((path, position)),
+        _ => None,
+    }
+}
+
 const REGISTERS_LEN: usize = (b'z' - b'a' + 1) as _;
 
 pub struct RegisterCollection {
     registers: [String; REGISTERS_LEN],
+    // one bit for each register whose content was saved as a marker
+    marker_bits: u32,
 }
 
 impl RegisterCollection {
@@ -445,6 +467,7 @@ impl RegisterCollection {
         const DEFAULT_STRING: String = String::new();
         Self {
             registers: [DEFAULT_STRING; REGISTERS_LEN],
+            marker_bits: 0,
         }
     }
 
@@ -453,8 +476,90 @@ impl RegisterCollection {
     }
 
     pub fn get_mut(&mut self, key: RegisterKey) -> &mut String {
+        self.marker_bits &= !(1 << key.0);
+        &mut self.registers[key.0 as usize]
+    }
+
+    // same as `get_mut` but flags the register as holding a marker
+    pub fn get_marker_mut(&mut self, key: RegisterKey) -> &mut String {
+        self.marker_bits |= 1 << key.0;
         &mut self.registers[key.0 as usize]
     }
+
+    pub fn is_marker(&self, key: RegisterKey) -> bool {
+        self.marker_bits & (1 << key.0) != 0
+    }
+
+    pub fn clear_markers(&mut self) {
+        for (i, register) in self.registers.iter_mut().enumerate() {
+            if self.marker_bits & (1 << i) != 0 {
+                register.clear();
+            }
+        }
+        self.marker_bits = 0;
+    }
+}
+
+pub struct History {
+    entries: VecDeque<String>,
+    capacity: usize,
+}
+
+impl History {
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            entries: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn entry(&self, index: usize) -> &str {
+        match self.entries.get(index) {
+            Some(e) => &e[..],
+            None => "",
+        }
+    }
+
+    pub fn entries(&self) -> impl DoubleEndedIterator<Item = &str> {
+        self.entries.iter().map(|e| &e[..])
+    }
+
+    pub fn add(&mut self, entry: &str) {
+        if entry.is_empty() || self.capacity == 0 {
+            return;
+        }
+        if let Some(back) = self.entries.back() {
+            if back == entry {
+                return;
+            }
+        }
+
+        let mut s = if self.entries.len() == self.capacity {
+            self.entries.pop_front().unwrap()
+        } else {
+            String::new()
+        };
+
+        s.clear();
+        s.push_str(entry);
+        self.entries.push_back(s);
+    }
+
+    pub fn remove(&mut self, entry: &str) {
+        self.entries.retain(|e| e != entry);
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
 }
 
 #[derive(Default)]
@@ -525,6 +630,254 @@ impl PickerEntriesProcessBuf {
     }
 }
 
+pub fn indentation_unit(style: IndentationStyle) -> &'static str {
+    static SPACES_BUF: &[u8; u8::MAX as usize] = &[b' '; u8::MAX as usize];
+    match style {
+        IndentationStyle::Tabs => "\t",
+        IndentationStyle::Spaces(len) => unsafe {
+            std::str::from_utf8_unchecked(&SPACES_BUF[..len as usize])
+        },
+    }
+}
+
+pub fn leading_whitespace(line: &str) -> &str {
+    let len = line.len() - line.trim_start_matches([' ', '\t']).len();
+    &line[..len]
+}
+
+// how many bytes to remove from the end of `indentation` in order to dedent it once
+pub fn dedent_len(indentation: &str, tab_size: u8) -> usize {
+    match indentation.as_bytes().last() {
+        Some(b'\t') => 1,
+        Some(b' ') => indentation
+            .bytes()
+            .rev()
+            .take(tab_size as usize)
+            .take_while(|&b| b == b' ')
+            .count(),
+        _ => 0,
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TextCase {
+    Lower,
+    Upper,
+    Swap,
+    Snake,
+    ScreamingSnake,
+    Kebab,
+    Camel,
+    Pascal,
+}
+impl TextCase {
+    pub const NAMES: &'static [&'static str] = &[
+        "lower",
+        "upper",
+        "swap",
+        "snake",
+        "screaming-snake",
+        "kebab",
+        "camel",
+        "pascal",
+    ];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "lower" => Some(Self::Lower),
+            "upper" => Some(Self::Upper),
+            "swap" => Some(Self::Swap),
+            "snake" => Some(Self::Snake),
+            "screaming-snake" => Some(Self::ScreamingSnake),
+            "kebab" => Some(Self::Kebab),
+            "camel" => Some(Self::Camel),
+            "pascal" => Some(Self::Pascal),
+            _ => None,
+        }
+    }
+}
+
+// splits identifiers on `_` and on case changes (`fooBar`, `HTTPServer`)
+fn for_each_identifier_part<F>(identifier: &str, mut f: F)
+where
+    F: FnMut(&str),
+{
+    for chunk in identifier.split('_').filter(|c| !c.is_empty()) {
+        let mut start = 0;
+        let mut previous: Option<char> = None;
+        let mut chars = chunk.char_indices().peekable();
+        while let Some((i, c)) = chars.next() {
+            if let Some(p) = previous {
+                let next_is_lowercase = matches!(chars.peek(), Some((_, n)) if n.is_lowercase());
+                let is_boundary = c.is_uppercase()
+                    && (p.is_lowercase()
+                        || p.is_numeric()
+                        || (p.is_uppercase() && next_is_lowercase));
+                if is_boundary {
+                    f(&chunk[start..i]);
+                    start = i;
+                }
+            }
+            previous = Some(c);
+        }
+        f(&chunk[start..]);
+    }
+}
+
+// identifier words joined by a single `-` (as in `kebab-case`) are converted as a single identifier
+pub fn convert_case(text: &str, case: TextCase, output: &mut String) {
+    fn push_lowercase(output: &mut String, text: &str) {
+        output.extend(text.chars().flat_map(char::to_lowercase));
+    }
+    fn push_uppercase(output: &mut String, text: &str) {
+        output.extend(text.chars().flat_map(char::to_uppercase));
+    }
+    fn push_capitalized(output: &mut String, text: &str) {
+        let mut chars = text.chars();
+        if let Some(c) = chars.next() {
+            output.extend(c.to_uppercase());
+            push_lowercase(output, chars.as_str());
+        }
+    }
+
+    match case {
+        TextCase::Lower => return push_lowercase(output, text),
+        TextCase::Upper => return push_uppercase(output, text),
+        TextCase::Swap => {
+            for c in text.chars() {
+                if c.is_uppercase() {
+                    output.extend(c.to_lowercase());
+                } else {
+                    output.extend(c.to_uppercase());
+                }
+            }
+            return;
+        }
+        _ => (),
+    }
+
+    let mut part_count = 0;
+    let mut words = WordIter(text).peekable();
+    while let Some(word) = words.next() {
+        match word.kind {
+            WordKind::Identifier => {
+                let trimmed = word.text.trim_start_matches('_');
+                let prefix = &word.text[..word.text.len() - trimmed.len()];
+                let core = trimmed.trim_end_matches('_');
+                let suffix = &trimmed[core.len()..];
+
+                output.push_str(prefix);
+                for_each_identifier_part(core, |part| {
+                    match case {
+                        TextCase::Snake => {
+                            if part_count > 0 {
+                                output.push('_');
+                            }
+                            push_lowercase(output, part);
+                        }
+                        TextCase::ScreamingSnake => {
+                            if part_count > 0 {
+                                output.push('_');
+                            }
+                            push_uppercase(output, part);
+                        }
+                        TextCase::Kebab => {
+                            if part_count > 0 {
+                                output.push('-');
+                            }
+                            push_lowercase(output, part);
+                        }
+                        TextCase::Camel if part_count == 0 => push_lowercase(output, part),
+                        _ => push_capitalized(output, part),
+                    }
+                    part_count += 1;
+                });
+                output.push_str(suffix);
+            }
+            WordKind::Symbol
+                if word.text == "-"
+                    && part_count > 0
+                    && matches!(words.peek(), Some(w) if w.kind == WordKind::Identifier) => {}
+            _ => {
+                output.push_str(word.text);
+                part_count = 0;
+            }
+        }
+    }
+}
+
+// finds the number (decimal, `0x` hex or `0b` binary) that contains or comes after `index` in `line`,
+// writes it with `delta` added to `output` and returns its range in `line`
+// the digit count of hex, binary and zero padded numbers and the hex digit case are preserved
+pub fn increment_number(
+    line: &str,
+    index: usize,
+    delta: i64,
+    output: &mut String,
+) -> Option<Range<usize>> {
+    use fmt::Write;
+
+    let bytes = line.as_bytes();
+    let mut i = 0;
+    while i < bytes.len() {
+        if !bytes[i].is_ascii_digit() {
+            i += 1;
+            continue;
+        }
+
+        let radix = match bytes.get(i..i + 3) {
+            Some([b'0', b'x' | b'X', d]) if d.is_ascii_hexdigit() => 16,
+            Some([b'0', b'b' | b'B', b'0' | b'1']) => 2,
+            _ => 10,
+        };
+        let digits_start = if radix == 10 { i } else { i + 2 };
+        let digits_len = bytes[digits_start..]
+            .iter()
+            .take_while(|&&b| (b as char).is_digit(radix))
+            .count();
+        let end = digits_start + digits_len;
+        if end <= index {
+            i = end;
+            continue;
+        }
+
+        let digits = &line[digits_start..end];
+        let width = if radix != 10 || digits.starts_with('0') {
+            digits.len()
+        } else {
+            1
+        };
+        output.push_str(&line[i..digits_start]);
+
+        if radix == 10 {
+            let is_negative = i > 0
+                && bytes[i - 1] == b'-'
+                && !matches!(bytes.get(i.wrapping_sub(2)), Some(b) if b.is_ascii_alphanumeric() || *b == b'_');
+            let start = if is_negative { i - 1 } else { i };
+            let value: i64 = line[start..end].parse().ok()?;
+            let value = value.checked_add(delta)?;
+            if value < 0 {
+                output.push('-');
+            }
+            let _ = write!(output, "{:01$}", value.unsigned_abs(), width);
+            return Some(start..end);
+        }
+
+        let value = u64::from_str_radix(digits, radix).ok()?;
+        let value = value.wrapping_add(delta as u64);
+        let _ = match radix {
+            2 => write!(output, "{:01$b}", value, width),
+            _ if digits.bytes().any(|b| b.is_ascii_uppercase()) => {
+                write!(output, "{:01$X}", value, width)
+            }
+            _ => write!(output, "{:01$x}", value, width),
+        };
+        return Some(i..end);
+    }
+
+    None
+}
+
 // FNV-1a : https://en.wikipedia.org/wiki/Fowler–Noll–Vo_hash_function
 pub const fn hash_bytes(mut bytes: &[u8]) -> u64 {
     let mut hash: u64 = 0xcbf29ce484222325;
@@ -613,6 +966,103 @@ pub fn parse_process_command(command: &str) -> Option<Command> {
     Some(command)
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LineSortKind {
+    Lexical,
+    Numeric,
+    CaseInsensitive,
+}
+impl LineSortKind {
+    pub const NAMES: &'static [&'static str] = &["lexical", "numeric", "case-insensitive"];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "lexical" => Some(Self::Lexical),
+            "numeric" => Some(Self::Numeric),
+            "case-insensitive" => Some(Self::CaseInsensitive),
+            _ => None,
+        }
+    }
+}
+
+pub enum LineOperation<'a> {
+    Sort(LineSortKind),
+    Unique,
+    Reverse,
+    Shuffle { seed: u64 },
+    Keep(&'a Pattern),
+    Drop(&'a Pattern),
+}
+impl<'a> LineOperation<'a> {
+    pub fn shuffle() -> Self {
+        let seed = RandomState::new().build_hasher().finish();
+        Self::Shuffle { seed: seed | 1 }
+    }
+
+    pub fn apply(&mut self, lines: &mut Vec<&str>) {
+        // lines without a leading number sort before the ones with it
+        fn leading_number(line: &str) -> Option<f64> {
+            let line = line.trim_start();
+            let mut len = 0;
+            let mut has_digits = false;
+            let mut has_point = false;
+            for (i, c) in line.char_indices() {
+                match c {
+                    '-' | '+' if i == 0 => (),
+                    '.' if !has_point => has_point = true,
+                    '0'..='9' => has_digits = true,
+                    _ => break,
+                }
+                len = i + 1;
+            }
+            if has_digits {
+                line[..len].trim_end_matches('.').parse().ok()
+            } else {
+                None
+            }
+        }
+
+        fn contains(line: &str, pattern: &Pattern) -> bool {
+            pattern
+                .match_indices(line, pattern.search_anchor())
+                .next()
+                .is_some()
+        }
+
+        match self {
+            Self::Sort(LineSortKind::Lexical) => lines.sort(),
+            Self::Sort(LineSortKind::Numeric) => {
+                lines.sort_by(|a, b| match (leading_number(a), leading_number(b)) {
+                    (Some(a), Some(b)) => a.partial_cmp(&b).unwrap_or(Ordering::Equal),
+                    (a, b) => a.is_some().cmp(&b.is_some()),
+                })
+            }
+            Self::Sort(LineSortKind::CaseInsensitive) => lines.sort_by(|a, b| {
+                let a = a.chars().flat_map(char::to_lowercase);
+                let b = b.chars().flat_map(char::to_lowercase);
+                a.cmp(b)
+            }),
+            Self::Unique => {
+                let mut seen = HashSet::new();
+                lines.retain(|l| seen.insert(*l));
+            }
+            Self::Reverse => lines.reverse(),
+            Self::Shuffle { seed } => {
+                for i in (1..lines.len()).rev() {
+                    // xorshift64
+                    *seed ^= *seed << 13;
+                    *seed ^= *seed >> 7;
+                    *seed ^= *seed << 17;
+                    let j = (*seed % (i as u64 + 1)) as usize;
+                    lines.swap(i, j);
+                }
+            }
+            Self::Keep(pattern) => lines.retain(|l| contains(l, pattern)),
+            Self::Drop(pattern) => lines.retain(|l| !contains(l, pattern)),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -627,6 +1077,126 @@ mod tests {
         assert!(!is_char_boundary(bytes[3]));
     }
 
+    #[test]
+    fn indentation_helpers() {
+        assert_eq!("\t  ", leading_whitespace("\t  text  "));
+        assert_eq!("", leading_whitespace("text"));
+
+        assert_eq!("\t", indentation_unit(IndentationStyle::Tabs));
+        assert_eq!("  ", indentation_unit(IndentationStyle::Spaces(2)));
+
+        assert_eq!(1, dedent_len("  \t", 4));
+        assert_eq!(4, dedent_len("\t      ", 4));
+        assert_eq!(2, dedent_len("\t  ", 4));
+        assert_eq!(0, dedent_len("", 4));
+    }
+
+    #[test]
+    fn text_case_conversion() {
+        fn convert(text: &str, case: TextCase) -> String {
+            let mut output = String::new();
+            convert_case(text, case, &mut output);
+            output
+        }
+
+        assert_eq!("abc déf", convert("aBc DéF", TextCase::Lower));
+        assert_eq!("ABC DÉF", convert("aBc DéF", TextCase::Upper));
+        assert_eq!("AbC dÉf", convert("aBc DéF", TextCase::Swap));
+
+        let text = "fooBar HTTPServer some_value-x kebab-case __init__ v2Name";
+        assert_eq!(
+            "foo_bar http_server some_value_x kebab_case __init__ v2_name",
+            convert(text, TextCase::Snake)
+        );
+        assert_eq!(
+            "FOO_BAR HTTP_SERVER SOME_VALUE_X KEBAB_CASE __INIT__ V2_NAME",
+            convert(text, TextCase::ScreamingSnake)
+        );
+        assert_eq!(
+            "foo-bar http-server some-value-x kebab-case __init__ v2-name",
+            convert(text, TextCase::Kebab)
+        );
+        assert_eq!(
+            "fooBar httpServer someValueX kebabCase __init__ v2Name",
+            convert(text, TextCase::Camel)
+        );
+        assert_eq!(
+            "FooBar HttpServer SomeValueX KebabCase __Init__ V2Name",
+            convert(text, TextCase::Pascal)
+        );
+
+        assert_eq!("a_b - c(d_e)", convert("a-b - c(dE)", TextCase::Snake));
+    }
+
+    #[test]
+    fn number_increment() {
+        fn increment(line: &str, index: usize, delta: i64) -> Option<String> {
+            let mut output = String::new();
+            let range = increment_number(line, index, delta, &mut output)?;
+            let mut result = String::new();
+            result.push_str(&line[..range.start]);
+            result.push_str(&output);
+            result.push_str(&line[range.end..]);
+            Some(result)
+        }
+
+        assert_eq!(Some("x = 10;".into()), increment("x = 9;", 0, 1));
+        assert_eq!(Some("x = 9;".into()), increment("x = 9;", 4, 0));
+        assert_eq!(None, increment("x = 9;", 5, 1));
+        assert_eq!(Some("v1.3.0".into()), increment("v1.2.0", 2, 1));
+        assert_eq!(Some("v1.2.1".into()), increment("v1.2.0", 4, 1));
+        assert_eq!(Some("a -2".into()), increment("a -3", 0, 1));
+        assert_eq!(Some("a 1".into()), increment("a -3", 0, 4));
+        assert_eq!(Some("a-4".into()), increment("a-3", 0, 1));
+        assert_eq!(Some("007 1".into()), increment("010 1", 0, -3));
+        assert_eq!(Some("0x0a".into()), increment("0x09", 0, 1));
+        assert_eq!(Some("0xFF".into()), increment("0xFE", 3, 1));
+        assert_eq!(Some("0b0110".into()), increment("0b0101", 0, 1));
+        assert_eq!(Some("0x0f".into()), increment("0x10", 0, -1));
+    }
+
+    #[test]
+    fn marker_parsing() {
+        assert_eq!(
+            Some(("src/main.rs", BufferPosition::line_col(2, 4))),
+            parse_marker("src/main.rs:3,5")
+        );
+        assert_eq!(
+            Some(("file.txt", BufferPosition::line_col(9, 0))),
+            parse_marker("file.txt:10")
+        );
+        assert_eq!(None, parse_marker("file.txt"));
+        assert_eq!(None, parse_marker(":3,5"));
+        assert_eq!(None, parse_marker("macro\nkeys:3"));
+        assert_eq!(None, parse_marker(""));
+    }
+
+    #[test]
+    fn clear_marker_registers() {
+        let marker_key = RegisterKey::from_char('m').unwrap();
+        let copied_key = RegisterKey::from_char('c').unwrap();
+        let overwritten_key = RegisterKey::from_char('o').unwrap();
+
+        let mut registers = RegisterCollection::new();
+        registers
+            .get_marker_mut(marker_key)
+            .push_str("src/main.rs:3,5");
+        registers.get_mut(copied_key).push_str("src/lib.rs:10");
+        registers
+            .get_marker_mut(overwritten_key)
+            .push_str("file.txt:1");
+        registers.get_mut(overwritten_key).push('0');
+        assert!(registers.is_marker(marker_key));
+        assert!(!registers.is_marker(copied_key));
+        assert!(!registers.is_marker(overwritten_key));
+
+        registers.clear_markers();
+        assert_eq!("", registers.get(marker_key));
+        assert_eq!("src/lib.rs:10", registers.get(copied_key));
+        assert_eq!("file.txt:10", registers.get(overwritten_key));
+        assert!(!registers.is_marker(marker_key));
+    }
+
     #[test]
     fn residual_str_bytes() {
         let message = "abcdef".as_bytes();
@@ -696,4 +1266,42 @@ mod tests {
             residue.receive_bytes(&mut Default::default(), &[])
         );
     }
+
+    #[test]
+    fn line_operations() {
+        fn apply(mut operation: LineOperation, lines: &[&str]) -> String {
+            let mut lines = lines.to_vec();
+            operation.apply(&mut lines);
+            lines.join("|")
+        }
+
+        let lines = ["b", "10 x", "a", "B", "2", "-1.5", "a"];
+        assert_eq!(
+            "-1.5|10 x|2|B|a|a|b",
+            apply(LineOperation::Sort(LineSortKind::Lexical), &lines)
+        );
+        assert_eq!(
+            "b|a|B|a|-1.5|2|10 x",
+            apply(LineOperation::Sort(LineSortKind::Numeric), &lines)
+        );
+        assert_eq!(
+            "-1.5|10 x|2|a|a|b|B",
+            apply(LineOperation::Sort(LineSortKind::CaseInsensitive), &lines)
+        );
+        assert_eq!("b|10 x|a|B|2|-1.5", apply(LineOperation::Unique, &lines));
+        assert_eq!("a|-1.5|2|B|a|10 x|b", apply(LineOperation::Reverse, &lines));
+
+        let mut shuffled = lines.to_vec();
+        LineOperation::shuffle().apply(&mut shuffled);
+        shuffled.sort();
+        assert_eq!(
+            apply(LineOperation::Sort(LineSortKind::Lexical), &lines),
+            shuffled.join("|")
+        );
+
+        let mut pattern = Pattern::new();
+        pattern.compile_searcher("p/%d").unwrap();
+        assert_eq!("10 x|2|-1.5", apply(LineOperation::Keep(&pattern), &lines));
+        assert_eq!("b|a|B|a", apply(LineOperation::Drop(&pattern), &lines));
+    }
 }
@@ -0,0 +1,347 @@
+use std::{
+    fs,
+    num::NonZeroU8,
+    path::{Component, Path, PathBuf},
+};
+
+use crate::{
+    buffer::{Buffer, BufferFormat, IndentationStyle, LineEnding},
+    glob::Glob,
+};
+
+const EDITORCONFIG_FILE_NAME: &str = ".editorconfig";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum IndentStyle {
+    Tab,
+    Space,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum IndentSize {
+    Tab,
+    Columns(u8),
+}
+
+#[derive(Default)]
+pub struct EditorConfigProperties {
+    indent_style: Option<IndentStyle>,
+    indent_size: Option<IndentSize>,
+    tab_width: Option<u8>,
+    end_of_line: Option<LineEnding>,
+    byte_order_mark: Option<bool>,
+    trim_trailing_whitespace: Option<bool>,
+    insert_final_newline: Option<bool>,
+}
+
+impl EditorConfigProperties {
+    // `path` should be absolute so that all `.editorconfig` files up the directory tree are found
+    pub fn load(&mut self, path: &Path) {
+        let path = &normalize_path(path);
+        let mut files = Vec::new();
+        for directory in path.ancestors().skip(1) {
+            if let Ok(text) = fs::read_to_string(directory.join(EDITORCONFIG_FILE_NAME)) {
+                let is_root = is_root(&text);
+                files.push((directory, text));
+                if is_root {
+                    break;
+                }
+            }
+        }
+
+        let mut glob = Glob::default();
+        for (directory, text) in files.iter().rev() {
+            if let Some(relative_path) = path.strip_prefix(directory).ok().and_then(Path::to_str) {
+                self.parse(text, relative_path, &mut glob);
+            }
+        }
+    }
+
+    pub fn parse(&mut self, text: &str, relative_path: &str, glob: &mut Glob) {
+        let mut section_matches = false;
+        for line in text.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+                continue;
+            }
+
+            if let Some(section) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                section_matches =
+                    compile_section_glob(glob, section) && glob.matches(relative_path);
+                continue;
+            }
+
+            if section_matches {
+                if let Some((key, value)) = line.split_once('=') {
+                    self.set(key.trim(), value.trim());
+                }
+            }
+        }
+    }
+
+    fn set(&mut self, key: &str, value: &str) {
+        fn parse_bool(value: &str) -> Option<bool> {
+            if value.eq_ignore_ascii_case("true") {
+                Some(true)
+            } else if value.eq_ignore_ascii_case("false") {
+                Some(false)
+            } else {
+                None
+            }
+        }
+
+        let key = key.to_ascii_lowercase();
+        let value = value.to_ascii_lowercase();
+        match &key[..] {
+            "indent_style" => {
+                self.indent_style = match &value[..] {
+                    "tab" => Some(IndentStyle::Tab),
+                    "space" => Some(IndentStyle::Space),
+                    _ => None,
+                }
+            }
+            "indent_size" => {
+                self.indent_size = match &value[..] {
+                    "tab" => Some(IndentSize::Tab),
+                    _ => value
+                        .parse()
+                        .ok()
+                        .filter(|&n| n > 0)
+                        .map(IndentSize::Columns),
+                }
+            }
+            "tab_width" => self.tab_width = value.parse().ok().filter(|&n| n > 0),
+            "end_of_line" => {
+                self.end_of_line = match &value[..] {
+                    "lf" => Some(LineEnding::Lf),
+                    "crlf" => Some(LineEnding::CrLf),
+                    _ => None,
+                }
+            }
+            // only utf-8 is supported so other charsets are left as if they were unset
+            "charset" => {
+                self.byte_order_mark = match &value[..] {
+                    "utf-8" => Some(false),
+                    "utf-8-bom" => Some(true),
+                    _ => None,
+                }
+            }
+            "trim_trailing_whitespace" => self.trim_trailing_whitespace = parse_bool(&value),
+            "insert_final_newline" => self.insert_final_newline = parse_bool(&value),
+            _ => (),
+        }
+    }
+
+    pub fn apply_to(&self, format: &mut BufferFormat) {
+        let indent_width = match self.indent_size {
+            Some(IndentSize::Columns(width)) => Some(width),
+            Some(IndentSize::Tab) | None => self.tab_width,
+        };
+        match (self.indent_style, indent_width) {
+            (Some(IndentStyle::Tab), _) => format.indentation_style = Some(IndentationStyle::Tabs),
+            (Some(IndentStyle::Space), Some(width)) => {
+                format.indentation_style = Some(IndentationStyle::Spaces(width))
+            }
+            _ => (),
+        }
+
+        let tab_width = match self.indent_size {
+            Some(IndentSize::Columns(width)) => self.tab_width.or(Some(width)),
+            Some(IndentSize::Tab) | None => self.tab_width,
+        };
+        if let Some(tab_width) = tab_width.and_then(NonZeroU8::new) {
+            format.tab_size = Some(tab_width);
+        }
+
+        if let Some(line_ending) = self.end_of_line {
+            format.line_ending = line_ending;
+        }
+        if let Some(byte_order_mark) = self.byte_order_mark {
+            format.byte_order_mark = byte_order_mark;
+        }
+        if let Some(trim_trailing_whitespace) = self.trim_trailing_whitespace {
+            format.trim_trailing_whitespace = trim_trailing_whitespace;
+        }
+        if let Some(insert_final_newline) = self.insert_final_newline {
+            format.insert_final_newline = insert_final_newline;
+        }
+    }
+}
+
+fn is_root(text: &str) -> bool {
+    for line in text.lines() {
+        let line = line.trim();
+        if line.starts_with('[') {
+            break;
+        }
+        if let Some((key, value)) = line.split_once('=') {
+            if key.trim().eq_ignore_ascii_case("root") && value.trim().eq_ignore_ascii_case("true")
+            {
+                return true;
+            }
+        }
+    }
+    false
+}
+
+// sections without a `/` match files in any subdirectory
+fn compile_section_glob(glob: &mut Glob, section: &str) -> bool {
+    let result = if section.contains('/') {
+        glob.compile(section.strip_prefix('/').unwrap_or(section))
+    } else {
+        let mut pattern = String::with_capacity(section.len() + 3);
+        pattern.push_str("**/");
+        pattern.push_str(section);
+        glob.compile(&pattern)
+    };
+    result.is_ok()
+}
+
+// resolves `.` and `..` components without touching the file system
+fn normalize_path(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => (),
+            Component::ParentDir => {
+                if !normalized.pop() {
+                    normalized.push(component);
+                }
+            }
+            _ => normalized.push(component),
+        }
+    }
+    normalized
+}
+
+pub fn load_buffer_format(current_directory: &Path, buffer: &mut Buffer) {
+    if !buffer.properties.is_file {
+        return;
+    }
+
+    buffer.format = BufferFormat::default();
+    let mut properties = EditorConfigProperties::default();
+    properties.load(&current_directory.join(&buffer.path));
+    properties.apply_to(&mut buffer.format);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(text: &str, relative_path: &str) -> BufferFormat {
+        let mut properties = EditorConfigProperties::default();
+        properties.parse(text, relative_path, &mut Glob::default());
+        let mut format = BufferFormat::default();
+        properties.apply_to(&mut format);
+        format
+    }
+
+    #[test]
+    fn root_detection() {
+        assert!(is_root("root = true\n[*]\nindent_style = tab\n"));
+        assert!(is_root("# comment\nROOT=True\n"));
+        assert!(!is_root("[*]\nroot = true\n"));
+        assert!(!is_root("root = false\n"));
+    }
+
+    #[test]
+    fn section_matching() {
+        let text = "
+            root = true
+
+            [*]
+            end_of_line = crlf
+            insert_final_newline = true
+
+            [*.{rs,toml}]
+            indent_style = space
+            indent_size = 4
+
+            ; comment
+            [src/*.md]
+            indent_style = tab
+            trim_trailing_whitespace = true
+            charset = utf-8-bom
+            insert_final_newline = false
+        ";
+
+        let format = parse(text, "src/main.rs");
+        assert_eq!(Some(IndentationStyle::Spaces(4)), format.indentation_style);
+        assert_eq!(LineEnding::CrLf, format.line_ending);
+        assert!(!format.trim_trailing_whitespace);
+        assert!(format.insert_final_newline);
+
+        let format = parse(text, "src/notes.md");
+        assert_eq!(Some(IndentationStyle::Tabs), format.indentation_style);
+        assert!(format.trim_trailing_whitespace);
+        assert!(format.byte_order_mark);
+        assert!(!format.insert_final_newline);
+
+        let format = parse(text, "docs/notes.md");
+        assert_eq!(None, format.indentation_style);
+        assert!(!format.byte_order_mark);
+    }
+
+    #[test]
+    fn indent_size_fallbacks() {
+        let format = parse(
+            "[*]\nindent_style = space\nindent_size = tab\ntab_width = 2\n",
+            "a",
+        );
+        assert_eq!(Some(IndentationStyle::Spaces(2)), format.indentation_style);
+        assert_eq!(Some(2), format.tab_size.map(NonZeroU8::get));
+
+        let format = parse("[*]\nindent_style = space\nindent_size = 3\n", "a");
+        assert_eq!(Some(IndentationStyle::Spaces(3)), format.indentation_style);
+        assert_eq!(Some(3), format.tab_size.map(NonZeroU8::get));
+
+        let format = parse("[*]\nindent_style = space\n", "a");
+        assert_eq!(None, format.indentation_style);
+        assert_eq!(None, format.tab_size);
+
+        let format = parse(
+            "[*]\nindent_style = space\nindent_size = 3\nindent_size = unset\n",
+            "a",
+        );
+        assert_eq!(None, format.indentation_style);
+    }
+
+    #[test]
+    fn path_normalization() {
+        assert_eq!(
+            Path::new("/a/c/d"),
+            normalize_path(Path::new("/a/b/../c/./d"))
+        );
+        assert_eq!(Path::new("/a"), normalize_path(Path::new("/a/b/c/../..")));
+        assert_eq!(Path::new("../a"), normalize_path(Path::new("./../a")));
+    }
+
+    #[test]
+    fn load_through_parent_directory() {
+        let root = std::env::temp_dir().join(format!("pepper-editorconfig-{}", std::process::id()));
+        let directory = root.join("project");
+        fs::create_dir_all(directory.join("a")).unwrap();
+        fs::create_dir_all(directory.join("b")).unwrap();
+        fs::write(root.join(EDITORCONFIG_FILE_NAME), "root = true\n").unwrap();
+        fs::write(
+            directory.join(EDITORCONFIG_FILE_NAME),
+            "[*.rs]\nindent_style = tab\n",
+        )
+        .unwrap();
+        fs::write(
+            directory.join("a").join(EDITORCONFIG_FILE_NAME),
+            "root = true\n[*.rs]\nend_of_line = crlf\n",
+        )
+        .unwrap();
+
+        let mut properties = EditorConfigProperties::default();
+        properties.load(&directory.join("a").join("..").join("b").join("main.rs"));
+        let mut format = BufferFormat::default();
+        properties.apply_to(&mut format);
+        let _ = fs::remove_dir_all(&root);
+
+        assert_eq!(Some(IndentationStyle::Tabs), format.indentation_style);
+        assert_eq!(LineEnding::Lf, format.line_ending);
+    }
+}
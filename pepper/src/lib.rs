@@ -9,6 +9,7 @@ pub mod config;
 pub mod cursor;
 pub mod editor;
 pub mod editor_utils;
+pub mod editorconfig;
 pub mod events;
 pub mod glob;
 pub mod help;
@@ -19,6 +20,9 @@ pub mod picker;
 pub mod platform;
 pub mod plugin;
 pub mod serialization;
+pub mod session_file;
+pub mod snippet;
+pub mod state_file;
 pub mod syntax;
 pub mod theme;
 pub mod ui;
@@ -53,6 +57,7 @@ pub struct Args {
     pub quit: bool,
     pub server: bool,
     pub configs: Vec<ArgsConfig>,
+    pub load_session: Option<String>,
     pub files: Vec<String>,
 }
 
@@ -82,6 +87,7 @@ fn print_help() {
     println!("  --server                 only run as server");
     println!("  -c, --config[!]          sources config file at path (repeatable) (server only)");
     println!("                           with `!` it will suppress the 'file not found' error");
+    println!("  --load-session           loads a session saved with `session-save` on start (server only)");
 }
 
 impl Args {
@@ -142,6 +148,10 @@ impl Args {
                         None => error(format_args!("expected config path after {}", arg)),
                     }
                 }
+                "--load-session" => match args.next() {
+                    Some(arg) => parsed.load_session = Some(arg_to_str(&arg).into()),
+                    None => error(format_args!("expected session name after {}", arg)),
+                },
                 "--" => {
                     while let Some(arg) = args.next() {
                         let arg = arg_to_str(&arg);
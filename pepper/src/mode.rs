@@ -1,18 +1,18 @@
 use crate::{
     client::ClientHandle,
-    editor::{Editor, EditorContext, EditorFlow, KeysIterator},
+    editor::{EditorContext, EditorFlow, KeysIterator},
     plugin::PluginHandle,
 };
 
 mod command;
 mod insert;
-mod normal;
+pub(crate) mod normal;
 pub(crate) mod picker;
 pub(crate) mod read_line;
 
 pub(crate) trait ModeState {
-    fn on_enter(editor: &mut Editor);
-    fn on_exit(editor: &mut Editor);
+    fn on_enter(ctx: &mut EditorContext, client_handle: ClientHandle);
+    fn on_exit(ctx: &mut EditorContext, client_handle: ClientHandle);
     fn on_keys(
         ctx: &mut EditorContext,
         client_handle: ClientHandle,
@@ -53,28 +53,29 @@ impl Mode {
         self.kind
     }
 
-    pub(crate) fn change_to(editor: &mut Editor, next: ModeKind) {
-        if editor.mode.kind == next {
+    pub(crate) fn change_to(ctx: &mut EditorContext, client_handle: ClientHandle, next: ModeKind) {
+        let previous = ctx.clients.get(client_handle).mode.kind;
+        if previous == next {
             return;
         }
 
-        match editor.mode.kind {
-            ModeKind::Normal => normal::State::on_exit(editor),
-            ModeKind::Insert => insert::State::on_exit(editor),
-            ModeKind::Command => command::State::on_exit(editor),
-            ModeKind::ReadLine => read_line::State::on_exit(editor),
-            ModeKind::Picker => picker::State::on_exit(editor),
-            ModeKind::Plugin => editor.mode.plugin_handle = None,
+        match previous {
+            ModeKind::Normal => normal::State::on_exit(ctx, client_handle),
+            ModeKind::Insert => insert::State::on_exit(ctx, client_handle),
+            ModeKind::Command => command::State::on_exit(ctx, client_handle),
+            ModeKind::ReadLine => read_line::State::on_exit(ctx, client_handle),
+            ModeKind::Picker => picker::State::on_exit(ctx, client_handle),
+            ModeKind::Plugin => ctx.clients.get_mut(client_handle).mode.plugin_handle = None,
         }
 
-        editor.mode.kind = next;
+        ctx.clients.get_mut(client_handle).mode.kind = next;
 
-        match editor.mode.kind {
-            ModeKind::Normal => normal::State::on_enter(editor),
-            ModeKind::Insert => insert::State::on_enter(editor),
-            ModeKind::Command => command::State::on_enter(editor),
-            ModeKind::ReadLine => read_line::State::on_enter(editor),
-            ModeKind::Picker => picker::State::on_enter(editor),
+        match next {
+            ModeKind::Normal => normal::State::on_enter(ctx, client_handle),
+            ModeKind::Insert => insert::State::on_enter(ctx, client_handle),
+            ModeKind::Command => command::State::on_enter(ctx, client_handle),
+            ModeKind::ReadLine => read_line::State::on_enter(ctx, client_handle),
+            ModeKind::Picker => picker::State::on_enter(ctx, client_handle),
             ModeKind::Plugin => (),
         }
     }
@@ -84,19 +85,20 @@ impl Mode {
         client_handle: ClientHandle,
         keys: &mut KeysIterator,
     ) -> Option<EditorFlow> {
-        match ctx.editor.mode.kind {
+        let mode = &ctx.clients.get(client_handle).mode;
+        match mode.kind {
             ModeKind::Normal => normal::State::on_keys(ctx, client_handle, keys),
             ModeKind::Insert => insert::State::on_keys(ctx, client_handle, keys),
             ModeKind::Command => command::State::on_keys(ctx, client_handle, keys),
             ModeKind::ReadLine => read_line::State::on_keys(ctx, client_handle, keys),
             ModeKind::Picker => picker::State::on_keys(ctx, client_handle, keys),
-            ModeKind::Plugin => match ctx.editor.mode.plugin_handle {
+            ModeKind::Plugin => match mode.plugin_handle {
                 Some(plugin_handle) => {
                     let on_keys = ctx.plugins.get(plugin_handle).on_keys;
                     on_keys(plugin_handle, ctx, client_handle, keys)
                 }
                 None => {
-                    Mode::change_to(&mut ctx.editor, ModeKind::default());
+                    Mode::change_to(ctx, client_handle, ModeKind::default());
                     None
                 }
             },
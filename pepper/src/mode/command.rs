@@ -35,21 +35,23 @@ impl Default for State {
 }
 
 impl ModeState for State {
-    fn on_enter(editor: &mut Editor) {
-        let state = &mut editor.mode.command_state;
-        state.read_state = ReadCommandState::NavigatingHistory(editor.commands.history_len());
+    fn on_enter(ctx: &mut EditorContext, client_handle: ClientHandle) {
+        let client = ctx.clients.get_mut(client_handle);
+        let state = &mut client.mode.command_state;
+        state.read_state = ReadCommandState::NavigatingHistory(ctx.editor.commands.history.len());
         state.completion_index = 0;
         state.completion_source = CompletionSource::Custom(&[]);
         state.completion_path_hash = None;
 
-        editor.read_line.set_prompt(":");
-        editor.read_line.input_mut().clear();
-        editor.picker.clear();
+        client.read_line.set_prompt(":");
+        client.read_line.input_mut().clear();
+        client.picker.clear();
     }
 
-    fn on_exit(editor: &mut Editor) {
-        editor.read_line.input_mut().clear();
-        editor.picker.clear();
+    fn on_exit(ctx: &mut EditorContext, client_handle: ClientHandle) {
+        let client = ctx.clients.get_mut(client_handle);
+        client.read_line.input_mut().clear();
+        client.picker.clear();
     }
 
     fn on_keys(
@@ -57,8 +59,9 @@ impl ModeState for State {
         client_handle: ClientHandle,
         keys: &mut KeysIterator,
     ) -> Option<EditorFlow> {
-        let state = &mut ctx.editor.mode.command_state;
-        match ctx.editor.read_line.poll(
+        let client = ctx.clients.get_mut(client_handle);
+        let state = &mut client.mode.command_state;
+        match client.read_line.poll(
             &mut ctx.platform,
             &mut ctx.editor.string_pool,
             &ctx.editor.buffered_keys,
@@ -83,15 +86,16 @@ impl ModeState for State {
                             *i = ctx
                                 .editor
                                 .commands
-                                .history_len()
+                                .history
+                                .len()
                                 .saturating_sub(1)
                                 .min(*i + 1);
-                            let entry = ctx.editor.commands.history_entry(*i);
-                            let input = ctx.editor.read_line.input_mut();
+                            let entry = ctx.editor.commands.history.entry(*i);
+                            let input = client.read_line.input_mut();
                             input.clear();
                             input.push_str(entry);
                         }
-                        ReadCommandState::TypingCommand => apply_completion(ctx, 1),
+                        ReadCommandState::TypingCommand => apply_completion(ctx, client_handle, 1),
                     },
                     Key {
                         code: KeyCode::Char('p' | 'k'),
@@ -107,23 +111,23 @@ impl ModeState for State {
                     } => match state.read_state {
                         ReadCommandState::NavigatingHistory(ref mut i) => {
                             *i = i.saturating_sub(1);
-                            let entry = ctx.editor.commands.history_entry(*i);
-                            let input = ctx.editor.read_line.input_mut();
+                            let entry = ctx.editor.commands.history.entry(*i);
+                            let input = client.read_line.input_mut();
                             input.clear();
                             input.push_str(entry);
                         }
-                        ReadCommandState::TypingCommand => apply_completion(ctx, -1),
+                        ReadCommandState::TypingCommand => apply_completion(ctx, client_handle, -1),
                     },
-                    _ => update_autocomplete_entries(ctx),
+                    _ => update_autocomplete_entries(ctx, client_handle),
                 }
             }
-            ReadLinePoll::Canceled => ctx.editor.enter_mode(ModeKind::default()),
+            ReadLinePoll::Canceled => Editor::enter_mode(ctx, client_handle, ModeKind::default()),
             ReadLinePoll::Submitted => {
-                let input = ctx.editor.read_line.input();
+                let input = ctx.clients.get(client_handle).read_line.input();
                 ctx.editor.commands.add_to_history(input);
 
                 let command = ctx.editor.string_pool.acquire_with(input);
-                ctx.editor.enter_mode(ModeKind::default());
+                Editor::enter_mode(ctx, client_handle, ModeKind::default());
 
                 let result =
                     CommandManager::eval_single(ctx, Some(client_handle), &command, "", false);
@@ -142,30 +146,32 @@ impl ModeState for State {
     }
 }
 
-fn apply_completion(ctx: &mut EditorContext, cursor_movement: isize) {
-    ctx.editor.picker.move_cursor(cursor_movement);
-    if let Some((_, entry)) = ctx.editor.picker.current_entry(&ctx.editor.word_database) {
-        let input = ctx.editor.read_line.input_mut();
-        input.truncate(ctx.editor.mode.command_state.completion_index);
+fn apply_completion(ctx: &mut EditorContext, client_handle: ClientHandle, cursor_movement: isize) {
+    let client = ctx.clients.get_mut(client_handle);
+    client.picker.move_cursor(cursor_movement);
+    if let Some((_, entry)) = client.picker.current_entry(&ctx.editor.word_database) {
+        let input = client.read_line.input_mut();
+        input.truncate(client.mode.command_state.completion_index);
         input.push_str(entry);
     }
 }
 
-fn update_autocomplete_entries(ctx: &mut EditorContext) {
-    let state = &mut ctx.editor.mode.command_state;
+fn update_autocomplete_entries(ctx: &mut EditorContext, client_handle: ClientHandle) {
+    let client = ctx.clients.get_mut(client_handle);
+    let state = &mut client.mode.command_state;
 
-    let input = ctx.editor.read_line.input();
+    let input = client.read_line.input();
     let mut tokens = CommandTokenizer(input);
 
     let mut last_token = match tokens.next() {
         Some(token) => token.slice,
         None => {
-            ctx.editor.picker.clear();
+            client.picker.clear();
             state.completion_index = input.len();
             state.completion_source = CompletionSource::Custom(&[]);
             if input.trim().is_empty() {
                 state.read_state =
-                    ReadCommandState::NavigatingHistory(ctx.editor.commands.history_len());
+                    ReadCommandState::NavigatingHistory(ctx.editor.commands.history.len());
             }
             return;
         }
@@ -175,7 +181,7 @@ fn update_autocomplete_entries(ctx: &mut EditorContext) {
     if let ReadCommandState::NavigatingHistory(_) = state.read_state {
         state.read_state = ReadCommandState::TypingCommand;
     }
-    ctx.editor.picker.clear_cursor();
+    client.picker.clear_cursor();
 
     let mut arg_count = 0;
     let ends_with_whitespace = input.ends_with(&[' ', '\t'][..]);
@@ -208,29 +214,29 @@ fn update_autocomplete_entries(ctx: &mut EditorContext) {
 
     if state.completion_source != completion_source {
         state.completion_path_hash = None;
-        ctx.editor.picker.clear();
+        client.picker.clear();
 
         match completion_source {
             CompletionSource::Commands => {
                 for command in ctx.editor.commands.commands() {
-                    ctx.editor.picker.add_custom_entry(command.name);
+                    client.picker.add_custom_entry(command.name);
                 }
                 for name in ctx.editor.commands.macros.names() {
                     if !name.starts_with('-') {
-                        ctx.editor.picker.add_custom_entry(name);
+                        client.picker.add_custom_entry(name);
                     }
                 }
             }
             CompletionSource::Buffers => {
                 for buffer in ctx.editor.buffers.iter() {
                     if let Some(path) = buffer.path.to_str() {
-                        ctx.editor.picker.add_custom_entry(path);
+                        client.picker.add_custom_entry(path);
                     }
                 }
             }
             CompletionSource::Custom(completions) => {
                 for completion in completions {
-                    ctx.editor.picker.add_custom_entry(completion);
+                    client.picker.add_custom_entry(completion);
                 }
             }
             _ => (),
@@ -263,7 +269,7 @@ fn update_autocomplete_entries(ctx: &mut EditorContext) {
 
         let parent_hash = hash_bytes(parent.as_bytes());
         if state.completion_path_hash != Some(parent_hash) {
-            set_files_in_path_as_entries(&mut ctx.editor.picker, parent);
+            set_files_in_path_as_entries(&mut client.picker, parent);
             state.completion_path_hash = Some(parent_hash);
         }
 
@@ -272,5 +278,5 @@ fn update_autocomplete_entries(ctx: &mut EditorContext) {
     }
 
     state.completion_source = completion_source;
-    ctx.editor.picker.filter(WordIndicesIter::empty(), pattern);
+    client.picker.filter(WordIndicesIter::empty(), pattern);
 }
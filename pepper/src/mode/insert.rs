@@ -1,26 +1,71 @@
-use std::fmt::Write;
+use std::{env, fmt::Write, fs, path::Path};
 
 use crate::{
-    buffer::BufferHandle,
-    buffer_position::{BufferPosition, BufferRange},
+    buffer::{BufferContent, BufferHandle, BufferLine},
+    buffer_position::{BufferPosition, BufferPositionIndex, BufferRange},
     buffer_view::{BufferViewHandle, CursorMovement, CursorMovementKind},
     client::ClientHandle,
+    config::CompletionScope,
+    cursor::Cursor,
     editor::{Editor, EditorContext, EditorFlow, KeysIterator},
-    editor_utils::AUTO_MACRO_REGISTER,
+    editor_utils::{indentation_unit, leading_whitespace, AUTO_MACRO_REGISTER},
     mode::{ModeKind, ModeState},
+    picker::EntrySource,
     platform::{Key, KeyCode},
     plugin::{CompletionContext, PluginHandle},
-    word_database::WordKind,
+    snippet::parse_snippet,
+    syntax::Syntax,
+    word_database::{WordDatabase, WordIndicesIter, WordIter, WordKind, WordUsage},
 };
 
+const PROXIMITY_LINE_RANGE: usize = 64;
+
+struct CompletionSnippet {
+    entry_index: usize,
+    body: String,
+}
+
+struct SnippetTabstopRange {
+    index: u32,
+    range: BufferRange,
+}
+
 #[derive(Default)]
 pub struct State {
     editing_buffer_handle: Option<BufferHandle>,
     completion_positions: Vec<BufferPosition>,
     completing_plugin_handle: Option<PluginHandle>,
+    applied_completion_word_index: Option<usize>,
+    word_proximity_bonuses: Vec<u8>,
+    words_in_scope: Vec<bool>,
+    is_completing_path: bool,
+    path_completion_requested: bool,
+    path_completion_directory: String,
+    snippet_tabstops: Vec<SnippetTabstopRange>,
+    snippet_tabstop_index: u32,
+    completion_snippets: Vec<CompletionSnippet>,
 }
 
 impl State {
+    // `entry_index` is the index of the picker custom entry that expands into the snippet
+    pub fn add_completion_snippet(&mut self, entry_index: usize, body: &str) {
+        self.completion_snippets.push(CompletionSnippet {
+            entry_index,
+            body: body.into(),
+        });
+    }
+
+    pub fn find_completion_snippet(&self, entry_index: usize) -> Option<&str> {
+        self.completion_snippets
+            .iter()
+            .find(|s| s.entry_index == entry_index)
+            .map(|s| s.body.as_str())
+    }
+
+    pub fn clear_completion_snippets(&mut self) {
+        self.completion_snippets.clear();
+    }
+
     pub fn on_buffer_insert_text(&mut self, handle: BufferHandle, range: BufferRange) {
         if self.editing_buffer_handle == Some(handle) {
             for position in &mut self.completion_positions {
@@ -28,6 +73,21 @@ impl State {
                     *position = position.insert(range);
                 }
             }
+
+            for tabstop in &mut self.snippet_tabstops {
+                let is_empty = tabstop.range.from == tabstop.range.to;
+                if tabstop.index == self.snippet_tabstop_index {
+                    if tabstop.range.from != range.from {
+                        tabstop.range.from = tabstop.range.from.insert(range);
+                    }
+                    tabstop.range.to = tabstop.range.to.insert(range);
+                } else {
+                    tabstop.range.from = tabstop.range.from.insert(range);
+                    if is_empty || tabstop.range.to != range.from {
+                        tabstop.range.to = tabstop.range.to.insert(range);
+                    }
+                }
+            }
         }
     }
 
@@ -36,18 +96,24 @@ impl State {
             for position in &mut self.completion_positions {
                 *position = position.delete(range);
             }
+            for tabstop in &mut self.snippet_tabstops {
+                tabstop.range.from = tabstop.range.from.delete(range);
+                tabstop.range.to = tabstop.range.to.delete(range);
+            }
         }
     }
 }
 
 impl ModeState for State {
-    fn on_enter(editor: &mut Editor) {
-        cancel_completion(editor);
+    fn on_enter(ctx: &mut EditorContext, client_handle: ClientHandle) {
+        cancel_completion(ctx, client_handle);
     }
 
-    fn on_exit(editor: &mut Editor) {
-        editor.mode.insert_state.editing_buffer_handle = None;
-        cancel_completion(editor);
+    fn on_exit(ctx: &mut EditorContext, client_handle: ClientHandle) {
+        let state = &mut ctx.clients.get_mut(client_handle).mode.insert_state;
+        state.editing_buffer_handle = None;
+        state.snippet_tabstops.clear();
+        cancel_completion(ctx, client_handle);
     }
 
     fn on_keys(
@@ -58,13 +124,16 @@ impl ModeState for State {
         let handle = match ctx.clients.get(client_handle).buffer_view_handle() {
             Some(handle) => handle,
             None => {
-                ctx.editor.enter_mode(ModeKind::default());
+                Editor::enter_mode(ctx, client_handle, ModeKind::default());
                 return Some(EditorFlow::Continue);
             }
         };
 
-        ctx.editor.mode.insert_state.editing_buffer_handle =
-            Some(ctx.editor.buffer_views.get(handle).buffer_handle);
+        ctx.clients
+            .get_mut(client_handle)
+            .mode
+            .insert_state
+            .editing_buffer_handle = Some(ctx.editor.buffer_views.get(handle).buffer_handle);
 
         let key = keys.next(&ctx.editor.buffered_keys);
         let register = ctx.editor.registers.get_mut(AUTO_MACRO_REGISTER);
@@ -79,7 +148,7 @@ impl ModeState for State {
                     .buffers
                     .get_mut(buffer_view.buffer_handle)
                     .commit_edits();
-                ctx.editor.enter_mode(ModeKind::default());
+                Editor::enter_mode(ctx, client_handle, ModeKind::default());
                 return Some(EditorFlow::Continue);
             }
             Key { code: KeyCode::Left, shift: false, control: false, alt: false } => {
@@ -88,31 +157,33 @@ impl ModeState for State {
                     CursorMovement::ColumnsBackward(1),
                     CursorMovementKind::PositionAndAnchor,
                 );
-                cancel_completion(&mut ctx.editor);
+                cancel_completion(ctx, client_handle);
                 return Some(EditorFlow::Continue);
             }
             Key { code: KeyCode::Down, shift: false, control: false, alt: false } => {
+                let buffer_view = ctx.editor.buffer_views.get(handle);
+                let buffer = ctx.editor.buffers.get(buffer_view.buffer_handle);
+                let config = buffer.config(&ctx.editor.config, &ctx.editor.scoped_configs);
+                let tab_size = config.tab_size.get();
                 ctx.editor.buffer_views.get_mut(handle).move_cursors(
                     &ctx.editor.buffers,
-                    CursorMovement::LinesForward {
-                        count: 1,
-                        tab_size: ctx.editor.config.tab_size.get(),
-                    },
+                    CursorMovement::LinesForward { count: 1, tab_size },
                     CursorMovementKind::PositionAndAnchor,
                 );
-                cancel_completion(&mut ctx.editor);
+                cancel_completion(ctx, client_handle);
                 return Some(EditorFlow::Continue);
             }
             Key { code: KeyCode::Up, shift: false, control: false, alt: false } => {
+                let buffer_view = ctx.editor.buffer_views.get(handle);
+                let buffer = ctx.editor.buffers.get(buffer_view.buffer_handle);
+                let config = buffer.config(&ctx.editor.config, &ctx.editor.scoped_configs);
+                let tab_size = config.tab_size.get();
                 ctx.editor.buffer_views.get_mut(handle).move_cursors(
                     &ctx.editor.buffers,
-                    CursorMovement::LinesBackward {
-                        count: 1,
-                        tab_size: ctx.editor.config.tab_size.get(),
-                    },
+                    CursorMovement::LinesBackward { count: 1, tab_size },
                     CursorMovementKind::PositionAndAnchor,
                 );
-                cancel_completion(&mut ctx.editor);
+                cancel_completion(ctx, client_handle);
                 return Some(EditorFlow::Continue);
             }
             Key { code: KeyCode::Right, shift: false, control: false, alt: false } => {
@@ -121,21 +192,24 @@ impl ModeState for State {
                     CursorMovement::ColumnsForward(1),
                     CursorMovementKind::PositionAndAnchor,
                 );
-                cancel_completion(&mut ctx.editor);
+                cancel_completion(ctx, client_handle);
                 return Some(EditorFlow::Continue);
             }
             Key { code: KeyCode::Char('\t'), control: false, alt: false, .. } => {
-                static SPACES_BUF: &[u8; u8::MAX as usize] = &[b' '; u8::MAX as usize];
-                let text = if ctx.editor.config.indent_with_tabs {
-                    "\t"
-                } else {
-                    let len = ctx.editor.config.tab_size.get() as usize;
-                    unsafe { std::str::from_utf8_unchecked(&SPACES_BUF[..len]) }
-                };
+                if try_expand_snippet(ctx, client_handle, handle) {
+                    return Some(EditorFlow::Continue);
+                }
+                if !ctx.clients.get(client_handle).mode.insert_state.snippet_tabstops.is_empty() {
+                    cancel_completion(ctx, client_handle);
+                    select_next_snippet_tabstop(ctx, client_handle, handle);
+                    return Some(EditorFlow::Continue);
+                }
 
-                ctx.editor
-                    .buffer_views
-                    .get(handle)
+                let buffer_view = ctx.editor.buffer_views.get(handle);
+                let buffer = ctx.editor.buffers.get(buffer_view.buffer_handle);
+                let config = buffer.config(&ctx.editor.config, &ctx.editor.scoped_configs);
+                let text = indentation_unit(buffer.indentation_style(&config, &ctx.editor.scoped_configs));
+                buffer_view
                     .insert_text_at_cursor_positions(
                         &mut ctx.editor.buffers,
                         &mut ctx.editor.word_database,
@@ -145,48 +219,33 @@ impl ModeState for State {
             }
             Key { code: KeyCode::Char('\n'), control: false, alt: false, .. }
             | Key { code: KeyCode::Char('m'), shift: false, control: true, alt: false } => {
-                let buffer_view = ctx.editor.buffer_views.get(handle);
-                let cursor_count = buffer_view.cursors[..].len();
-                let buffer = ctx.editor.buffers.get_mut(buffer_view.buffer_handle);
-
-                let mut buf = ctx.editor.string_pool.acquire();
-                for i in (0..cursor_count).rev() {
-                    let position = buffer_view.cursors[i].position;
-
-                    buf.push('\n');
-                    let indentation_word = buffer
-                        .content()
-                        .word_at(BufferPosition::line_col(position.line_index, 0));
-                    if indentation_word.kind == WordKind::Whitespace {
-                        let indentation_len = position
-                            .column_byte_index
-                            .min(indentation_word.text.len() as _);
-                        buf.push_str(&indentation_word.text[..indentation_len as usize]);
-                    }
-
-                    buffer.insert_text(
+                insert_line_breaks(ctx, handle);
+            }
+            Key { code: KeyCode::Char(c), control: false, alt: false, .. } => {
+                delete_snippet_placeholders(ctx, client_handle, handle);
+                let edits = insert_auto_paired_char(ctx, handle, c);
+                if edits.is_empty() {
+                    let mut buf = [0; std::mem::size_of::<char>()];
+                    let s = c.encode_utf8(&mut buf);
+                    let buffer_view = ctx.editor.buffer_views.get(handle);
+                    buffer_view.insert_text_at_cursor_positions(
+                        &mut ctx.editor.buffers,
                         &mut ctx.editor.word_database,
-                        position,
-                        &buf,
+                        s,
                         &mut ctx.editor.events,
                     );
-                    buf.clear();
                 }
-                ctx.editor.string_pool.release(buf);
-            }
-            Key { code: KeyCode::Char(c), control: false, alt: false, .. } => {
-                let mut buf = [0; std::mem::size_of::<char>()];
-                let s = c.encode_utf8(&mut buf);
-                let buffer_view = ctx.editor.buffer_views.get(handle);
-                buffer_view.insert_text_at_cursor_positions(
-                    &mut ctx.editor.buffers,
-                    &mut ctx.editor.word_database,
-                    s,
-                    &mut ctx.editor.events,
-                );
+                if !c.is_whitespace() {
+                    dedent_lines_on_trigger(ctx, handle, &edits);
+                }
             }
             Key { code: KeyCode::Backspace, shift: false, control: false, alt: false }
             | Key { code: KeyCode::Char('h'), shift: false, control: true, alt: false } => {
+                if delete_snippet_placeholders(ctx, client_handle, handle) {
+                    update_completions(ctx, client_handle, handle);
+                    return Some(EditorFlow::Continue);
+                }
+                delete_empty_auto_pairs(ctx, handle);
                 let buffer_view = ctx.editor.buffer_views.get_mut(handle);
                 buffer_view.move_cursors(
                     &ctx.editor.buffers,
@@ -200,6 +259,10 @@ impl ModeState for State {
                 );
             }
             Key { code: KeyCode::Delete, shift: false, control: false, alt: false } => {
+                if delete_snippet_placeholders(ctx, client_handle, handle) {
+                    update_completions(ctx, client_handle, handle);
+                    return Some(EditorFlow::Continue);
+                }
                 let buffer_view = ctx.editor.buffer_views.get_mut(handle);
                 buffer_view.move_cursors(
                     &ctx.editor.buffers,
@@ -233,6 +296,12 @@ impl ModeState for State {
                 apply_completion(ctx, client_handle, handle, -1);
                 return Some(EditorFlow::Continue);
             }
+            Key { code: KeyCode::Char('f'), shift: false, control: true, alt: false } => {
+                cancel_completion(ctx, client_handle);
+                ctx.clients.get_mut(client_handle).mode.insert_state.path_completion_requested = true;
+                update_completions(ctx, client_handle, handle);
+                return Some(EditorFlow::Continue);
+            }
             _ => return Some(EditorFlow::Continue),
         };
 
@@ -242,10 +311,510 @@ impl ModeState for State {
     }
 }
 
-fn cancel_completion(editor: &mut Editor) {
-    editor.picker.clear();
-    editor.mode.insert_state.completion_positions.clear();
-    editor.mode.insert_state.completing_plugin_handle = None;
+fn insert_line_breaks(ctx: &mut EditorContext, buffer_view_handle: BufferViewHandle) {
+    let buffer_view = ctx.editor.buffer_views.get(buffer_view_handle);
+    let buffer = ctx.editor.buffers.get_mut(buffer_view.buffer_handle);
+    let config = buffer.config(&ctx.editor.config, &ctx.editor.scoped_configs);
+    let syntax = ctx.editor.syntaxes.get(buffer.syntax_handle());
+    let indentation_unit =
+        indentation_unit(buffer.indentation_style(&config, &ctx.editor.scoped_configs));
+
+    let mut middle_line_columns = Vec::with_capacity(buffer_view.cursors[..].len());
+    let mut buf = ctx.editor.string_pool.acquire();
+    for cursor in buffer_view.cursors[..].iter().rev() {
+        let position = cursor.position;
+        let line = buffer.content().lines()[position.line_index as usize].as_str();
+        let (before, after) = line.split_at(position.column_byte_index as usize);
+        let indentation = leading_whitespace(before);
+
+        buf.push('\n');
+        buf.push_str(indentation);
+        let mut middle_line_column = None;
+        if syntax.increases_indentation(before) {
+            buf.push_str(indentation_unit);
+            if syntax.decreases_indentation(after) {
+                middle_line_column = Some((buf.len() - 1) as BufferPositionIndex);
+                buf.push('\n');
+                buf.push_str(indentation);
+            }
+        }
+
+        buffer.insert_text(
+            &mut ctx.editor.word_database,
+            position,
+            &buf,
+            &mut ctx.editor.events,
+        );
+        middle_line_columns.push(middle_line_column);
+        buf.clear();
+    }
+    ctx.editor.string_pool.release(buf);
+
+    if middle_line_columns.iter().all(Option::is_none) {
+        return;
+    }
+
+    ctx.trigger_event_handlers();
+    let mut cursors = ctx
+        .editor
+        .buffer_views
+        .get_mut(buffer_view_handle)
+        .cursors
+        .mut_guard();
+    if cursors[..].len() != middle_line_columns.len() {
+        return;
+    }
+    for (i, column) in middle_line_columns.iter().rev().enumerate() {
+        if let Some(column) = *column {
+            let cursor = &mut cursors[i];
+            cursor.position = BufferPosition::line_col(cursor.position.line_index - 1, column);
+            cursor.anchor = cursor.position;
+        }
+    }
+}
+
+// `edits` are the auto pair edits for each cursor (empty if the char was simply inserted)
+fn dedent_lines_on_trigger(
+    ctx: &mut EditorContext,
+    buffer_view_handle: BufferViewHandle,
+    edits: &[AutoPairEdit],
+) {
+    ctx.trigger_event_handlers();
+
+    let buffer_view = ctx.editor.buffer_views.get(buffer_view_handle);
+    let buffer = ctx.editor.buffers.get_mut(buffer_view.buffer_handle);
+    let syntax = ctx.editor.syntaxes.get(buffer.syntax_handle());
+
+    let mut previous_line_index = None;
+    for (i, cursor) in buffer_view.cursors[..].iter().enumerate().rev() {
+        if let Some(AutoPairEdit::SkipClosing) = edits.get(i) {
+            continue;
+        }
+
+        let position = cursor.position;
+        if previous_line_index == Some(position.line_index) {
+            continue;
+        }
+        previous_line_index = Some(position.line_index);
+
+        let lines = buffer.content().lines();
+        let line = lines[position.line_index as usize].as_str();
+        let before = &line[..position.column_byte_index as usize];
+        if !syntax.is_dedent_trigger(before) {
+            continue;
+        }
+
+        let indentation_len = leading_whitespace(before).len();
+        let opener_indentation_len = match find_block_opener_line(
+            &lines[..position.line_index as usize],
+            syntax,
+            indentation_len,
+        ) {
+            Some(opener) => leading_whitespace(opener).len(),
+            None => continue,
+        };
+        if indentation_len <= opener_indentation_len {
+            continue;
+        }
+
+        let range = BufferRange::between(
+            BufferPosition::line_col(position.line_index, opener_indentation_len as _),
+            BufferPosition::line_col(position.line_index, indentation_len as _),
+        );
+        buffer.delete_range(&mut ctx.editor.word_database, range, &mut ctx.editor.events);
+    }
+}
+
+// the closest line before a closing line that opens its block, skipping over nested blocks
+// stops at the first line indented less than the closing line as no opener could be found past it
+fn find_block_opener_line<'a>(
+    lines: &'a [BufferLine],
+    syntax: &Syntax,
+    indentation_len: usize,
+) -> Option<&'a str> {
+    let mut depth = 0;
+    for line in lines.iter().rev() {
+        let line = line.as_str();
+        if syntax.increases_indentation(line) {
+            if depth == 0 {
+                return Some(line);
+            }
+            depth -= 1;
+        }
+        if syntax.decreases_indentation(line) {
+            depth += 1;
+        }
+
+        let line_indentation_len = leading_whitespace(line).len();
+        if line_indentation_len < line.len() && line_indentation_len < indentation_len {
+            break;
+        }
+    }
+    None
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+#[derive(Clone, Copy)]
+enum AutoPairEdit {
+    Insert,
+    InsertPair(char),
+    SkipClosing,
+}
+
+fn insert_auto_paired_char(
+    ctx: &mut EditorContext,
+    buffer_view_handle: BufferViewHandle,
+    c: char,
+) -> Vec<AutoPairEdit> {
+    let buffer_view = ctx.editor.buffer_views.get(buffer_view_handle);
+    let buffer = ctx.editor.buffers.get_mut(buffer_view.buffer_handle);
+    let syntax = ctx.editor.syntaxes.get(buffer.syntax_handle());
+
+    let closing = syntax
+        .auto_pairs()
+        .find(|&(open, _)| open == c)
+        .map(|(_, close)| close);
+    let is_closing = syntax.auto_pairs().any(|(_, close)| close == c);
+    if closing.is_none() && !is_closing {
+        return Vec::new();
+    }
+
+    let mut edits = Vec::with_capacity(buffer_view.cursors[..].len());
+    let mut buf = [0; 2 * std::mem::size_of::<char>()];
+    for cursor in buffer_view.cursors[..].iter().rev() {
+        let position = cursor.position;
+        let line = buffer.content().lines()[position.line_index as usize].as_str();
+        let (before, after) = line.split_at(position.column_byte_index as usize);
+        let previous_char = before.chars().next_back();
+        let next_char = after.chars().next();
+
+        let edit = if cursor.anchor != cursor.position {
+            AutoPairEdit::Insert
+        } else if is_closing && next_char == Some(c) {
+            AutoPairEdit::SkipClosing
+        } else {
+            match closing {
+                Some(_) if next_char.map(is_word_char).unwrap_or(false) => AutoPairEdit::Insert,
+                Some(close) if close == c && previous_char.map(is_word_char).unwrap_or(false) => {
+                    AutoPairEdit::Insert
+                }
+                Some(close) => AutoPairEdit::InsertPair(close),
+                None => AutoPairEdit::Insert,
+            }
+        };
+
+        let len = c.encode_utf8(&mut buf).len();
+        let len = match edit {
+            AutoPairEdit::Insert => len,
+            AutoPairEdit::InsertPair(close) => len + close.encode_utf8(&mut buf[len..]).len(),
+            AutoPairEdit::SkipClosing => 0,
+        };
+        let text = unsafe { std::str::from_utf8_unchecked(&buf[..len]) };
+        buffer.insert_text(
+            &mut ctx.editor.word_database,
+            position,
+            text,
+            &mut ctx.editor.events,
+        );
+        edits.push(edit);
+    }
+
+    ctx.trigger_event_handlers();
+    edits.reverse();
+
+    let mut cursors = ctx
+        .editor
+        .buffer_views
+        .get_mut(buffer_view_handle)
+        .cursors
+        .mut_guard();
+    if cursors[..].len() != edits.len() {
+        return edits;
+    }
+    for (i, edit) in edits.iter().enumerate() {
+        let cursor = &mut cursors[i];
+        match *edit {
+            AutoPairEdit::Insert => continue,
+            AutoPairEdit::InsertPair(close) => {
+                cursor.position.column_byte_index -= close.len_utf8() as BufferPositionIndex;
+            }
+            AutoPairEdit::SkipClosing => {
+                cursor.position.column_byte_index += c.len_utf8() as BufferPositionIndex;
+            }
+        }
+        cursor.anchor = cursor.position;
+    }
+
+    edits
+}
+
+fn delete_empty_auto_pairs(ctx: &mut EditorContext, buffer_view_handle: BufferViewHandle) {
+    let buffer_view = ctx.editor.buffer_views.get(buffer_view_handle);
+    let buffer = ctx.editor.buffers.get_mut(buffer_view.buffer_handle);
+    let syntax = ctx.editor.syntaxes.get(buffer.syntax_handle());
+
+    let mut deleted_any = false;
+    for cursor in buffer_view.cursors[..].iter().rev() {
+        if cursor.anchor != cursor.position {
+            continue;
+        }
+
+        let position = cursor.position;
+        let line = buffer.content().lines()[position.line_index as usize].as_str();
+        let (before, after) = line.split_at(position.column_byte_index as usize);
+        let (previous_char, next_char) = match (before.chars().next_back(), after.chars().next()) {
+            (Some(previous_char), Some(next_char)) => (previous_char, next_char),
+            _ => continue,
+        };
+
+        if syntax
+            .auto_pairs()
+            .any(|pair| pair == (previous_char, next_char))
+        {
+            let mut end = position;
+            end.column_byte_index += next_char.len_utf8() as BufferPositionIndex;
+            buffer.delete_range(
+                &mut ctx.editor.word_database,
+                BufferRange::between(position, end),
+                &mut ctx.editor.events,
+            );
+            deleted_any = true;
+        }
+    }
+
+    if deleted_any {
+        ctx.trigger_event_handlers();
+    }
+}
+
+fn cancel_completion(ctx: &mut EditorContext, client_handle: ClientHandle) {
+    let client = ctx.clients.get_mut(client_handle);
+    let state = &mut client.mode.insert_state;
+    accept_applied_completion(state, &mut ctx.editor.word_database);
+    client.picker.clear();
+    state.clear_completion_snippets();
+    state.completion_positions.clear();
+    state.completing_plugin_handle = None;
+    state.is_completing_path = false;
+    state.path_completion_requested = false;
+}
+
+fn accept_applied_completion(state: &mut State, word_database: &mut WordDatabase) {
+    if let Some(index) = state.applied_completion_word_index.take() {
+        word_database.accept(index);
+    }
+}
+
+fn rank_completion_words(
+    ctx: &mut EditorContext,
+    client_handle: ClientHandle,
+    buffer_handle: BufferHandle,
+    cursor_position: BufferPosition,
+) {
+    fn mark_words_in_scope(
+        words_in_scope: &mut Vec<bool>,
+        word_database: &WordDatabase,
+        content: &BufferContent,
+    ) {
+        for line in content.lines() {
+            for word in WordIter(line.as_str()).of_kind(WordKind::Identifier) {
+                if let Some(index) = word_database.index_of(word) {
+                    if index >= words_in_scope.len() {
+                        words_in_scope.resize(index + 1, false);
+                    }
+                    words_in_scope[index] = true;
+                }
+            }
+        }
+    }
+
+    let editor = &ctx.editor;
+    let state = &mut ctx.clients.get_mut(client_handle).mode.insert_state;
+    state.word_proximity_bonuses.clear();
+    state.words_in_scope.clear();
+
+    let lines = editor.buffers.get(buffer_handle).content().lines();
+    let line_index = cursor_position.line_index as usize;
+    let from = line_index.saturating_sub(PROXIMITY_LINE_RANGE);
+    let to = lines.len().min(line_index + PROXIMITY_LINE_RANGE + 1);
+    for (i, line) in lines[from..to].iter().enumerate() {
+        let distance = line_index.abs_diff(from + i);
+        let bonus = if distance <= 4 {
+            3
+        } else if distance <= 16 {
+            2
+        } else {
+            1
+        };
+
+        for word in WordIter(line.as_str()).of_kind(WordKind::Identifier) {
+            if let Some(index) = editor.word_database.index_of(word) {
+                if index >= state.word_proximity_bonuses.len() {
+                    state.word_proximity_bonuses.resize(index + 1, 0);
+                }
+                let previous_bonus = &mut state.word_proximity_bonuses[index];
+                *previous_bonus = (*previous_bonus).max(bonus);
+            }
+        }
+    }
+
+    let buffer = editor.buffers.get(buffer_handle);
+    match buffer
+        .config(&editor.config, &editor.scoped_configs)
+        .completion_scope
+    {
+        CompletionScope::All => (),
+        CompletionScope::Buffer => mark_words_in_scope(
+            &mut state.words_in_scope,
+            &editor.word_database,
+            editor.buffers.get(buffer_handle).content(),
+        ),
+        CompletionScope::Syntax => {
+            let syntax_handle = editor.buffers.get(buffer_handle).syntax_handle();
+            for buffer in editor.buffers.iter() {
+                if buffer.syntax_handle() == syntax_handle {
+                    mark_words_in_scope(
+                        &mut state.words_in_scope,
+                        &editor.word_database,
+                        buffer.content(),
+                    );
+                }
+            }
+        }
+    }
+}
+
+fn word_usage_bonus(usage: WordUsage) -> u32 {
+    let recency_bonus = match usage.ticks_since_accepted {
+        Some(ticks) if ticks < 8 => 3,
+        Some(ticks) if ticks < 32 => 2,
+        Some(ticks) if ticks < 128 => 1,
+        _ => 0,
+    };
+    let frequency_bonus = (usage.count >= 8) as u32 + (usage.accepted_count >= 4) as u32;
+    recency_bonus + frequency_bonus
+}
+
+fn is_path_delimiter(c: char) -> bool {
+    c.is_whitespace()
+        || matches!(
+            c,
+            '"' | '\'' | '`' | '(' | ')' | '[' | ']' | '<' | '>' | '{' | '}' | ',' | ';' | '='
+        )
+}
+
+fn find_path_start(text: &str, is_delimiter: fn(char) -> bool) -> usize {
+    match text.char_indices().rev().find(|&(_, c)| is_delimiter(c)) {
+        Some((i, c)) => i + c.len_utf8(),
+        None => 0,
+    }
+}
+
+fn path_component_start(text: &str) -> usize {
+    find_path_start(text, |c| std::path::is_separator(c) || is_path_delimiter(c))
+}
+
+// paths only complete automatically when they are clearly paths
+// so typing something like `a/b` does not read a directory on every keystroke
+fn looks_like_path(line: &str, token_start: usize) -> bool {
+    let token = &line[token_start..];
+    token.starts_with("./")
+        || token.starts_with("../")
+        || token.starts_with("~/")
+        || token.starts_with('/')
+        || line[..token_start].ends_with(['"', '\''])
+}
+
+fn update_path_completions(
+    ctx: &mut EditorContext,
+    client_handle: ClientHandle,
+    buffer_view_handle: BufferViewHandle,
+) -> bool {
+    let client = ctx.clients.get_mut(client_handle);
+    let state = &mut client.mode.insert_state;
+    let buffer_view = ctx.editor.buffer_views.get(buffer_view_handle);
+    let buffer = ctx.editor.buffers.get(buffer_view.buffer_handle);
+    let content = buffer.content();
+
+    let main_cursor_position = buffer_view.cursors.main_cursor().position;
+    let line = content.lines()[main_cursor_position.line_index as usize].as_str();
+    let line = &line[..main_cursor_position.column_byte_index as usize];
+
+    let token_start = find_path_start(line, is_path_delimiter);
+    let token = &line[token_start..];
+    let component_start = token_start + path_component_start(token);
+    let directory = &line[token_start..component_start];
+    let filter = &line[component_start..];
+    if !state.path_completion_requested
+        && (directory.is_empty() || filter.is_empty() || !looks_like_path(line, token_start))
+    {
+        return false;
+    }
+
+    if !state.is_completing_path || state.path_completion_directory != directory {
+        let directory_path = Path::new(directory);
+        let home_directory = directory.strip_prefix("~/").zip(env::var_os("HOME"));
+        let directory_path = if let Some((directory, home)) = home_directory {
+            Path::new(&home).join(directory)
+        } else if directory_path.is_absolute() {
+            directory_path.to_path_buf()
+        } else {
+            let buffer_directory = buffer
+                .path
+                .parent()
+                .map(|p| ctx.editor.current_directory.join(p).join(directory_path))
+                .filter(|p| p.is_dir());
+            match buffer_directory {
+                Some(path) => path,
+                None => ctx.editor.current_directory.join(directory_path),
+            }
+        };
+
+        let entries = match fs::read_dir(&directory_path) {
+            Ok(entries) => entries,
+            Err(_) => return false,
+        };
+
+        client.picker.clear();
+        for entry in entries.flatten() {
+            let name = entry.file_name();
+            let name = match name.to_str() {
+                Some(name) => name,
+                None => continue,
+            };
+            match entry.file_type() {
+                Ok(file_type) if file_type.is_dir() => {
+                    client.picker.add_custom_entry_fmt(format_args!(
+                        "{}{}",
+                        name,
+                        std::path::MAIN_SEPARATOR
+                    ));
+                }
+                _ => client.picker.add_custom_entry(name),
+            }
+        }
+
+        state.is_completing_path = true;
+        state.path_completion_directory.clear();
+        state.path_completion_directory.push_str(directory);
+        state.completing_plugin_handle = None;
+        state.applied_completion_word_index = None;
+    }
+
+    state.completion_positions.clear();
+    for cursor in &buffer_view.cursors[..] {
+        let line = content.lines()[cursor.position.line_index as usize].as_str();
+        let line = &line[..cursor.position.column_byte_index as usize];
+        let mut position = cursor.position;
+        position.column_byte_index = path_component_start(line) as _;
+        state.completion_positions.push(position);
+    }
+
+    client.picker.filter(WordIndicesIter::empty(), filter);
+    true
 }
 
 fn update_completions(
@@ -253,6 +822,19 @@ fn update_completions(
     client_handle: ClientHandle,
     buffer_view_handle: BufferViewHandle,
 ) {
+    if update_path_completions(ctx, client_handle, buffer_view_handle) {
+        return;
+    }
+    if ctx
+        .clients
+        .get(client_handle)
+        .mode
+        .insert_state
+        .is_completing_path
+    {
+        cancel_completion(ctx, client_handle);
+    }
+
     let buffer_view = ctx.editor.buffer_views.get(buffer_view_handle);
     let buffer_handle = buffer_view.buffer_handle;
     let buffer = ctx.editor.buffers.get(buffer_handle);
@@ -265,29 +847,33 @@ fn update_completions(
     let main_cursor_index = buffer_view.cursors.main_cursor_index();
 
     loop {
-        match ctx
-            .editor
-            .mode
-            .insert_state
-            .completion_positions
-            .get(main_cursor_index)
-        {
+        let state = &mut ctx.clients.get_mut(client_handle).mode.insert_state;
+        match state.completion_positions.get(main_cursor_index) {
             Some(&position) => {
                 if main_cursor_position < position {
-                    cancel_completion(&mut ctx.editor);
+                    cancel_completion(ctx, client_handle);
                     return;
                 }
                 if position == word.position {
                     break;
                 }
 
-                ctx.editor.mode.insert_state.completion_positions.clear();
+                state.completion_positions.clear();
             }
             None => {
-                ctx.editor.picker.clear();
+                let client = ctx.clients.get_mut(client_handle);
+                let state = &mut client.mode.insert_state;
+                accept_applied_completion(state, &mut ctx.editor.word_database);
+                client.picker.clear();
+                state.clear_completion_snippets();
 
+                let config = ctx
+                    .editor
+                    .buffers
+                    .get(buffer_handle)
+                    .config(&ctx.editor.config, &ctx.editor.scoped_configs);
                 let completion_requested = word.kind == WordKind::Identifier
-                    && word.text.len() >= ctx.editor.config.completion_min_len as _;
+                    && word.text.len() >= config.completion_min_len as _;
                 let completion_ctx = CompletionContext {
                     client_handle,
                     buffer_handle,
@@ -296,28 +882,23 @@ fn update_completions(
                     completion_requested,
                 };
 
-                ctx.editor.mode.insert_state.completing_plugin_handle = None;
+                state.completing_plugin_handle = None;
                 for plugin_handle in ctx.plugins.handles() {
                     let on_completion = ctx.plugins.get(plugin_handle).on_completion;
                     if on_completion(plugin_handle, ctx, &completion_ctx) {
-                        ctx.editor.mode.insert_state.completing_plugin_handle = Some(plugin_handle);
+                        let state = &mut ctx.clients.get_mut(client_handle).mode.insert_state;
+                        state.completing_plugin_handle = Some(plugin_handle);
                         break;
                     }
                 }
 
-                if !completion_requested
-                    && ctx
-                        .editor
-                        .mode
-                        .insert_state
-                        .completing_plugin_handle
-                        .is_none()
-                {
-                    cancel_completion(&mut ctx.editor);
+                let state = &mut ctx.clients.get_mut(client_handle).mode.insert_state;
+                if !completion_requested && state.completing_plugin_handle.is_none() {
+                    cancel_completion(ctx, client_handle);
                     return;
                 }
 
-                ctx.editor.mode.insert_state.completion_positions.clear();
+                state.completion_positions.clear();
 
                 let buffer_view = ctx.editor.buffer_views.get(buffer_view_handle);
                 let buffer = ctx.editor.buffers.get(buffer_handle).content();
@@ -327,11 +908,11 @@ fn update_completions(
                         WordKind::Identifier => word.position,
                         _ => cursor.position,
                     };
-                    ctx.editor
-                        .mode
-                        .insert_state
-                        .completion_positions
-                        .push(position);
+                    state.completion_positions.push(position);
+                }
+
+                if state.completing_plugin_handle.is_none() {
+                    rank_completion_words(ctx, client_handle, buffer_handle, main_cursor_position);
                 }
 
                 break;
@@ -349,14 +930,47 @@ fn update_completions(
     {
         Some(filter) => filter,
         None => {
-            cancel_completion(&mut ctx.editor);
+            cancel_completion(ctx, client_handle);
             return;
         }
     };
 
-    ctx.editor
-        .picker
-        .filter_completion(ctx.editor.word_database.word_indices(), completion_filter);
+    let client = ctx.clients.get_mut(client_handle);
+    let state = &client.mode.insert_state;
+    let word_database = &ctx.editor.word_database;
+    let config = ctx
+        .editor
+        .buffers
+        .get(buffer_handle)
+        .config(&ctx.editor.config, &ctx.editor.scoped_configs);
+    let restrict_to_scope = config.completion_scope != CompletionScope::All;
+    let rank = |i, _: &str| {
+        if restrict_to_scope && !state.words_in_scope.get(i).copied().unwrap_or(false) {
+            return None;
+        }
+        let proximity_bonus = state.word_proximity_bonuses.get(i).copied().unwrap_or(0);
+        Some(proximity_bonus as u32 + word_usage_bonus(word_database.usage(i)))
+    };
+
+    if state.completing_plugin_handle.is_some() {
+        client.picker.filter_completion_ranked(
+            word_database.word_indices(),
+            completion_filter,
+            rank,
+        );
+    } else {
+        let picker = &mut client.picker;
+        picker.clear_custom_entries();
+        let path = ctx.editor.buffers.get(buffer_handle).path.to_str();
+        for trigger in ctx.editor.snippets.triggers(path.unwrap_or("")) {
+            picker.add_custom_entry(trigger);
+        }
+        picker.filter_words_and_custom_completion_ranked(
+            word_database.word_indices(),
+            completion_filter,
+            rank,
+        );
+    }
 }
 
 fn apply_completion(
@@ -365,11 +979,19 @@ fn apply_completion(
     buffer_view_handle: BufferViewHandle,
     cursor_movement: isize,
 ) {
-    ctx.editor.picker.move_cursor(cursor_movement);
-    let entry = match ctx.editor.picker.current_entry(&ctx.editor.word_database) {
-        Some((_, entry)) => entry,
+    let client = ctx.clients.get_mut(client_handle);
+    client.picker.move_cursor(cursor_movement);
+    let (source, entry) = match client.picker.current_entry(&ctx.editor.word_database) {
+        Some((source, entry)) => {
+            let state = &mut client.mode.insert_state;
+            state.applied_completion_word_index = match source {
+                EntrySource::WordDatabase(i) => Some(i),
+                EntrySource::Custom(_) => None,
+            };
+            (source, entry)
+        }
         None => {
-            cancel_completion(&mut ctx.editor);
+            cancel_completion(ctx, client_handle);
 
             let buffer_view = ctx.editor.buffer_views.get(buffer_view_handle);
             let buffer_handle = buffer_view.buffer_handle;
@@ -389,7 +1011,8 @@ fn apply_completion(
                     continue;
                 }
 
-                ctx.editor.mode.insert_state.completing_plugin_handle = Some(plugin_handle);
+                let state = &mut ctx.clients.get_mut(client_handle).mode.insert_state;
+                state.completing_plugin_handle = Some(plugin_handle);
 
                 let buffer_view = ctx.editor.buffer_views.get(buffer_view_handle);
                 let buffer = ctx.editor.buffers.get(buffer_handle).content();
@@ -399,11 +1022,7 @@ fn apply_completion(
                         WordKind::Identifier => word.position,
                         _ => cursor.position,
                     };
-                    ctx.editor
-                        .mode
-                        .insert_state
-                        .completion_positions
-                        .push(position);
+                    state.completion_positions.push(position);
                 }
 
                 break;
@@ -413,14 +1032,576 @@ fn apply_completion(
         }
     };
 
+    if let EntrySource::Custom(i) = source {
+        if let Some(snippet) = client.mode.insert_state.find_completion_snippet(i) {
+            let body = ctx.editor.string_pool.acquire_with(snippet);
+            expand_snippet_at_completion_positions(ctx, client_handle, buffer_view_handle, &body);
+            ctx.editor.string_pool.release(body);
+            return;
+        }
+    }
+
     let completion = ctx.editor.string_pool.acquire_with(entry);
     let buffer_view = ctx.editor.buffer_views.get(buffer_view_handle);
     buffer_view.apply_completion(
         &mut ctx.editor.buffers,
         &mut ctx.editor.word_database,
         &completion,
-        &ctx.editor.mode.insert_state.completion_positions,
+        &ctx.clients
+            .get(client_handle)
+            .mode
+            .insert_state
+            .completion_positions,
         &mut ctx.editor.events,
     );
     ctx.editor.string_pool.release(completion);
 }
+
+fn try_expand_snippet(
+    ctx: &mut EditorContext,
+    client_handle: ClientHandle,
+    buffer_view_handle: BufferViewHandle,
+) -> bool {
+    let state = &mut ctx.clients.get_mut(client_handle).mode.insert_state;
+    let buffer_view = ctx.editor.buffer_views.get(buffer_view_handle);
+    let buffer = ctx.editor.buffers.get(buffer_view.buffer_handle);
+    let content = buffer.content();
+
+    let main_cursor_position = buffer_view.cursors.main_cursor().position;
+    let word = content.word_at(content.position_before(main_cursor_position));
+    let path = buffer.path.to_str().unwrap_or("");
+    let snippet = match word.kind {
+        WordKind::Identifier if word.end_position() == main_cursor_position => {
+            ctx.editor.snippets.find(path, word.text)
+        }
+        _ => None,
+    };
+    let body = match snippet {
+        Some(snippet) => ctx.editor.string_pool.acquire_with(snippet),
+        None => return false,
+    };
+
+    state.completion_positions.clear();
+    for cursor in &buffer_view.cursors[..] {
+        let cursor_word = content.word_at(content.position_before(cursor.position));
+        let position =
+            if cursor_word.text == word.text && cursor_word.end_position() == cursor.position {
+                cursor_word.position
+            } else {
+                cursor.position
+            };
+        state.completion_positions.push(position);
+    }
+
+    expand_snippet_at_completion_positions(ctx, client_handle, buffer_view_handle, &body);
+    ctx.editor.string_pool.release(body);
+    true
+}
+
+fn expand_snippet_at_completion_positions(
+    ctx: &mut EditorContext,
+    client_handle: ClientHandle,
+    buffer_view_handle: BufferViewHandle,
+    body: &str,
+) {
+    let state = &mut ctx.clients.get_mut(client_handle).mode.insert_state;
+    let mut positions = std::mem::take(&mut state.completion_positions);
+    cancel_completion(ctx, client_handle);
+    expand_snippet(ctx, client_handle, buffer_view_handle, body, &positions);
+    positions.clear();
+    ctx.clients
+        .get_mut(client_handle)
+        .mode
+        .insert_state
+        .completion_positions = positions;
+}
+
+fn expand_snippet(
+    ctx: &mut EditorContext,
+    client_handle: ClientHandle,
+    buffer_view_handle: BufferViewHandle,
+    body: &str,
+    positions: &[BufferPosition],
+) {
+    fn text_position(start: BufferPosition, text: &str, offset: usize) -> BufferPosition {
+        let text = &text[..offset];
+        match text.rfind('\n') {
+            Some(i) => BufferPosition::line_col(
+                start.line_index + text.matches('\n').count() as BufferPositionIndex,
+                (offset - i - 1) as _,
+            ),
+            None => BufferPosition::line_col(
+                start.line_index,
+                start.column_byte_index + offset as BufferPositionIndex,
+            ),
+        }
+    }
+
+    let state = &mut ctx.clients.get_mut(client_handle).mode.insert_state;
+    let mut tabstops = std::mem::take(&mut state.snippet_tabstops);
+    tabstops.clear();
+    let mut parsed_tabstops = Vec::new();
+    let mut text = ctx.editor.string_pool.acquire();
+    let mut indentation = ctx.editor.string_pool.acquire();
+
+    let buffer_view = ctx.editor.buffer_views.get(buffer_view_handle);
+    let buffer = ctx.editor.buffers.get_mut(buffer_view.buffer_handle);
+    let config = buffer.config(&ctx.editor.config, &ctx.editor.scoped_configs);
+    let tab_text = indentation_unit(buffer.indentation_style(&config, &ctx.editor.scoped_configs));
+    for (cursor, &position) in buffer_view.cursors[..].iter().zip(positions.iter()).rev() {
+        indentation.clear();
+        let indentation_word = buffer
+            .content()
+            .word_at(BufferPosition::line_col(position.line_index, 0));
+        if indentation_word.kind == WordKind::Whitespace {
+            let indentation_len = position
+                .column_byte_index
+                .min(indentation_word.text.len() as _);
+            indentation.push_str(&indentation_word.text[..indentation_len as usize]);
+        }
+
+        text.clear();
+        parsed_tabstops.clear();
+        parse_snippet(
+            body,
+            &indentation,
+            tab_text,
+            &mut text,
+            &mut parsed_tabstops,
+        );
+
+        let range = BufferRange::between(position, cursor.position);
+        buffer.delete_range(&mut ctx.editor.word_database, range, &mut ctx.editor.events);
+        for tabstop in &mut tabstops {
+            tabstop.range.from = tabstop.range.from.delete(range);
+            tabstop.range.to = tabstop.range.to.delete(range);
+        }
+
+        let range = buffer.insert_text(
+            &mut ctx.editor.word_database,
+            position,
+            &text,
+            &mut ctx.editor.events,
+        );
+        for tabstop in &mut tabstops {
+            tabstop.range.from = tabstop.range.from.insert(range);
+            tabstop.range.to = tabstop.range.to.insert(range);
+        }
+
+        for tabstop in &parsed_tabstops {
+            let from = text_position(range.from, &text, tabstop.from);
+            let to = text_position(range.from, &text, tabstop.to);
+            tabstops.push(SnippetTabstopRange {
+                index: tabstop.index,
+                range: BufferRange::between(from, to),
+            });
+        }
+    }
+
+    ctx.editor.string_pool.release(text);
+    ctx.editor.string_pool.release(indentation);
+    ctx.trigger_event_handlers();
+
+    let state = &mut ctx.clients.get_mut(client_handle).mode.insert_state;
+    state.snippet_tabstops = tabstops;
+    state.snippet_tabstop_index = 0;
+    select_next_snippet_tabstop(ctx, client_handle, buffer_view_handle);
+}
+
+fn select_next_snippet_tabstop(
+    ctx: &mut EditorContext,
+    client_handle: ClientHandle,
+    buffer_view_handle: BufferViewHandle,
+) {
+    let state = &mut ctx.clients.get_mut(client_handle).mode.insert_state;
+    let next_index = state
+        .snippet_tabstops
+        .iter()
+        .map(|t| t.index)
+        .filter(|&i| i > state.snippet_tabstop_index)
+        .min()
+        .unwrap_or(0);
+    state.snippet_tabstop_index = next_index;
+
+    let mut cursors = ctx
+        .editor
+        .buffer_views
+        .get_mut(buffer_view_handle)
+        .cursors
+        .mut_guard();
+    cursors.clear();
+    for tabstop in &state.snippet_tabstops {
+        if tabstop.index == next_index {
+            cursors.add(Cursor {
+                anchor: tabstop.range.from,
+                position: tabstop.range.to,
+            });
+        }
+    }
+    drop(cursors);
+
+    if next_index == 0 {
+        state.snippet_tabstops.clear();
+    }
+}
+
+fn delete_snippet_placeholders(
+    ctx: &mut EditorContext,
+    client_handle: ClientHandle,
+    buffer_view_handle: BufferViewHandle,
+) -> bool {
+    if ctx
+        .clients
+        .get(client_handle)
+        .mode
+        .insert_state
+        .snippet_tabstops
+        .is_empty()
+    {
+        return false;
+    }
+
+    let buffer_view = ctx.editor.buffer_views.get(buffer_view_handle);
+    if buffer_view.cursors[..]
+        .iter()
+        .all(|c| c.anchor == c.position)
+    {
+        return false;
+    }
+
+    buffer_view.delete_text_in_cursor_ranges(
+        &mut ctx.editor.buffers,
+        &mut ctx.editor.word_database,
+        &mut ctx.editor.events,
+    );
+    ctx.trigger_event_handlers();
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::path::PathBuf;
+
+    use crate::{
+        buffer::BufferProperties,
+        client::ClientManager,
+        events::{ClientEvent, KeyParser, TargetClient},
+        platform::Platform,
+        plugin::{Plugin, PluginCollection, PluginDefinition},
+    };
+
+    fn insert_context(text: &str) -> (EditorContext, ClientHandle, BufferViewHandle) {
+        let mut ctx = EditorContext {
+            editor: Editor::new(PathBuf::new()),
+            platform: Platform::default(),
+            clients: ClientManager::default(),
+            plugins: PluginCollection::default(),
+        };
+
+        let buffer = ctx.editor.buffers.add_new();
+        buffer.properties = BufferProperties::text();
+        buffer.insert_text(
+            &mut ctx.editor.word_database,
+            BufferPosition::zero(),
+            text,
+            &mut ctx.editor.events,
+        );
+        let buffer_handle = buffer.handle();
+
+        let client_handle = ClientHandle(0);
+        ctx.clients.on_client_joined(client_handle);
+        let buffer_view_handle = ctx
+            .editor
+            .buffer_views
+            .add_new(client_handle, buffer_handle);
+        ctx.clients
+            .get_mut(client_handle)
+            .set_buffer_view_handle(Some(buffer_view_handle), &ctx.editor.buffer_views);
+        ctx.trigger_event_handlers();
+
+        (ctx, client_handle, buffer_view_handle)
+    }
+
+    fn execute_keys(ctx: &mut EditorContext, client_handle: ClientHandle, keys: &str) {
+        let keys = match ctx.editor.buffered_keys.parse(keys) {
+            Ok(keys) => keys,
+            Err(error) => panic!("{}", error),
+        };
+        Editor::execute_keys(ctx, client_handle, keys);
+    }
+
+    fn buffer_text(ctx: &EditorContext, buffer_view_handle: BufferViewHandle) -> String {
+        let buffer_handle = ctx
+            .editor
+            .buffer_views
+            .get(buffer_view_handle)
+            .buffer_handle;
+        ctx.editor.buffers.get(buffer_handle).content().to_string()
+    }
+
+    fn path_completion_entries(ctx: &EditorContext, client_handle: ClientHandle) -> Vec<&str> {
+        let mut entries: Vec<_> = ctx
+            .clients
+            .get(client_handle)
+            .picker
+            .entries(&ctx.editor.word_database)
+            .collect();
+        entries.sort_unstable();
+        entries
+    }
+
+    #[test]
+    fn path_completions() {
+        let directory =
+            env::temp_dir().join(format!("pepper-path-completions-{}", std::process::id()));
+        fs::create_dir_all(directory.join("src").join("nested")).unwrap();
+        fs::write(directory.join("src").join("main.rs"), "").unwrap();
+        fs::write(directory.join("src").join("mode.rs"), "").unwrap();
+
+        let path_context = || {
+            let (mut ctx, client_handle, buffer_view_handle) = insert_context("");
+            ctx.editor.current_directory = directory.clone();
+            (ctx, client_handle, buffer_view_handle)
+        };
+
+        let (mut ctx, client_handle, _) = path_context();
+        execute_keys(&mut ctx, client_handle, "isrc/m");
+        assert!(
+            !ctx.clients
+                .get(client_handle)
+                .mode
+                .insert_state
+                .is_completing_path
+        );
+        execute_keys(&mut ctx, client_handle, "<c-f>");
+        assert!(
+            ctx.clients
+                .get(client_handle)
+                .mode
+                .insert_state
+                .is_completing_path
+        );
+        assert_eq!(
+            ["main.rs", "mode.rs"],
+            &path_completion_entries(&ctx, client_handle)[..]
+        );
+
+        let (mut ctx, client_handle, _) = path_context();
+        execute_keys(&mut ctx, client_handle, "i\"src/n");
+        let nested = format!("nested{}", std::path::MAIN_SEPARATOR);
+        assert_eq!(
+            [&nested[..]],
+            &path_completion_entries(&ctx, client_handle)[..]
+        );
+
+        let (mut ctx, client_handle, buffer_view_handle) = path_context();
+        execute_keys(&mut ctx, client_handle, "i./src/ma<c-n>");
+        assert_eq!("./src/main.rs", buffer_text(&ctx, buffer_view_handle));
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn apply_plugin_snippet_completion() {
+        let (mut ctx, client_handle, buffer_view_handle) = insert_context("");
+        PluginCollection::add(
+            &mut ctx,
+            PluginDefinition {
+                instantiate: |_, _| {
+                    Some(Plugin {
+                        on_completion: |_, ctx, completion_ctx| {
+                            let client = ctx.clients.get_mut(completion_ctx.client_handle);
+                            client.picker.clear();
+                            client.picker.add_custom_entry("foo(…)");
+                            client
+                                .mode
+                                .insert_state
+                                .add_completion_snippet(0, "foo(${1:x})$0");
+                            true
+                        },
+                        ..Default::default()
+                    })
+                },
+                help_pages: &[],
+            },
+        );
+
+        execute_keys(&mut ctx, client_handle, "ifo<c-n>");
+        assert_eq!("foo(x)", buffer_text(&ctx, buffer_view_handle));
+        let cursors = &ctx.editor.buffer_views.get(buffer_view_handle).cursors;
+        assert_eq!(1, cursors[..].len());
+        assert_eq!(
+            BufferRange::between(
+                BufferPosition::line_col(0, 4),
+                BufferPosition::line_col(0, 5)
+            ),
+            cursors[0].to_range()
+        );
+        assert_eq!(0, ctx.clients.get(client_handle).picker.len());
+
+        execute_keys(&mut ctx, client_handle, "y<tab>");
+        assert_eq!("foo(y)", buffer_text(&ctx, buffer_view_handle));
+        let cursors = &ctx.editor.buffer_views.get(buffer_view_handle).cursors;
+        assert_eq!(BufferPosition::line_col(0, 6), cursors[0].position);
+    }
+
+    fn set_main_cursor(
+        ctx: &mut EditorContext,
+        handle: BufferViewHandle,
+        position: BufferPosition,
+    ) {
+        let mut cursors = ctx.editor.buffer_views.get_mut(handle).cursors.mut_guard();
+        cursors.clear();
+        cursors.add(Cursor {
+            anchor: position,
+            position,
+        });
+    }
+
+    fn set_brace_syntax(ctx: &mut EditorContext) {
+        let syntax = ctx.editor.syntaxes.get_current();
+        assert!(syntax.set_auto_pairs("{}"));
+        syntax.set_indent_rule("%{").unwrap();
+        syntax.set_dedent_rule("%}").unwrap();
+    }
+
+    #[test]
+    fn skip_closing_char_without_dedent() {
+        let (mut ctx, client_handle, buffer_view_handle) = insert_context("{\n    }");
+        set_brace_syntax(&mut ctx);
+        set_main_cursor(&mut ctx, buffer_view_handle, BufferPosition::line_col(1, 4));
+
+        execute_keys(&mut ctx, client_handle, "i}");
+        assert_eq!("{\n    }", buffer_text(&ctx, buffer_view_handle));
+        let cursors = &ctx.editor.buffer_views.get(buffer_view_handle).cursors;
+        assert_eq!(BufferPosition::line_col(1, 5), cursors[0].position);
+    }
+
+    #[test]
+    fn dedent_to_block_opener_indentation() {
+        let (mut ctx, client_handle, buffer_view_handle) = insert_context("{\n    {\n    ");
+        set_brace_syntax(&mut ctx);
+        set_main_cursor(&mut ctx, buffer_view_handle, BufferPosition::line_col(2, 4));
+
+        execute_keys(&mut ctx, client_handle, "i}");
+        assert_eq!("{\n    {\n    }", buffer_text(&ctx, buffer_view_handle));
+
+        execute_keys(&mut ctx, client_handle, "<enter>}");
+        assert_eq!("{\n    {\n    }\n}", buffer_text(&ctx, buffer_view_handle));
+    }
+
+    #[test]
+    fn dedent_stops_at_less_indented_line() {
+        let (mut ctx, client_handle, buffer_view_handle) = insert_context("{\nx\n    ");
+        set_brace_syntax(&mut ctx);
+        set_main_cursor(&mut ctx, buffer_view_handle, BufferPosition::line_col(2, 4));
+
+        execute_keys(&mut ctx, client_handle, "i}");
+        assert_eq!("{\nx\n    }", buffer_text(&ctx, buffer_view_handle));
+    }
+
+    #[test]
+    fn auto_pairs() {
+        fn assert_keys(text: &str, keys: &str, expected_text: &str, expected_column: usize) {
+            let (mut ctx, client_handle, buffer_view_handle) = insert_context(text);
+            assert!(ctx.editor.syntaxes.get_current().set_auto_pairs("()\"\""));
+            set_main_cursor(
+                &mut ctx,
+                buffer_view_handle,
+                BufferPosition::line_col(0, text.len() as _),
+            );
+
+            execute_keys(&mut ctx, client_handle, keys);
+            assert_eq!(expected_text, buffer_text(&ctx, buffer_view_handle));
+            let cursors = &ctx.editor.buffer_views.get(buffer_view_handle).cursors;
+            assert_eq!(
+                BufferPosition::line_col(0, expected_column as _),
+                cursors[0].position
+            );
+        }
+
+        assert_keys("", "i(", "()", 1);
+        assert_keys("", "i(a", "(a)", 2);
+        assert_keys("", "i(a)", "(a)", 3);
+        assert_keys("", "i((", "(())", 2);
+        assert_keys("", "i\"", "\"\"", 1);
+        assert_keys("", "i\"a\"", "\"a\"", 3);
+        assert_keys("a", "i\"", "a\"", 2);
+        assert_keys("", "i[", "[", 1);
+        assert_keys("", "ia)", "a)", 2);
+
+        assert_keys("", "i(<backspace>", "", 0);
+        assert_keys("", "i((<backspace>", "()", 1);
+        assert_keys("", "i(a<backspace>", "()", 1);
+        assert_keys("", "i(a<backspace><backspace>", "", 0);
+    }
+
+    #[test]
+    fn auto_pairs_without_syntax_pairs() {
+        let (mut ctx, client_handle, buffer_view_handle) = insert_context("");
+        execute_keys(&mut ctx, client_handle, "i(\"<backspace>");
+        assert_eq!("(", buffer_text(&ctx, buffer_view_handle));
+    }
+
+    fn send_keys(ctx: &mut EditorContext, client_handle: ClientHandle, keys: &str) {
+        for key in KeyParser::new(keys) {
+            let key = key.unwrap();
+            let event = ClientEvent::Key(TargetClient::Sender, key);
+            Editor::on_client_event(ctx, client_handle, event);
+        }
+    }
+
+    #[test]
+    fn input_state_is_kept_per_client() {
+        let (mut ctx, client_handle, buffer_view_handle) = insert_context("");
+        let other_client_handle = ClientHandle(1);
+        ctx.clients.on_client_joined(other_client_handle);
+        let buffer_handle = ctx
+            .editor
+            .buffer_views
+            .get(buffer_view_handle)
+            .buffer_handle;
+        let other_buffer_view_handle = ctx
+            .editor
+            .buffer_views
+            .add_new(other_client_handle, buffer_handle);
+        ctx.clients
+            .get_mut(other_client_handle)
+            .set_buffer_view_handle(Some(other_buffer_view_handle), &ctx.editor.buffer_views);
+        for handle in [client_handle, other_client_handle] {
+            Editor::on_client_event(&mut ctx, handle, ClientEvent::Resize(80, 24));
+        }
+
+        send_keys(&mut ctx, client_handle, "i");
+        send_keys(&mut ctx, other_client_handle, "g");
+        send_keys(&mut ctx, client_handle, "ab");
+        assert_eq!("ab", buffer_text(&ctx, buffer_view_handle));
+        assert_eq!(ModeKind::Insert, ctx.clients.get(client_handle).mode.kind());
+        assert_eq!(
+            ModeKind::Normal,
+            ctx.clients.get(other_client_handle).mode.kind()
+        );
+
+        send_keys(&mut ctx, other_client_handle, "g12");
+        assert_eq!(ModeKind::Insert, ctx.clients.get(client_handle).mode.kind());
+        let other_client = ctx.clients.get(other_client_handle);
+        assert_eq!(ModeKind::ReadLine, other_client.mode.kind());
+        assert_eq!("12", other_client.read_line.input());
+
+        send_keys(&mut ctx, client_handle, "<esc>");
+        assert_eq!(ModeKind::Normal, ctx.clients.get(client_handle).mode.kind());
+        assert_eq!(
+            ModeKind::ReadLine,
+            ctx.clients.get(other_client_handle).mode.kind()
+        );
+
+        Editor::enter_mode(&mut ctx, other_client_handle, ModeKind::default());
+        ctx.clients.on_client_left(other_client_handle);
+        let other_client = ctx.clients.get(other_client_handle);
+        assert_eq!(ModeKind::default(), other_client.mode.kind());
+        assert_eq!("", other_client.read_line.input());
+        assert_eq!("ab", buffer_text(&ctx, buffer_view_handle));
+    }
+}
@@ -2,21 +2,27 @@ use std::{cmp::Ordering, fmt::Write, path::Path};
 
 use crate::{
     buffer::{
-        find_path_and_position_at, parse_path_and_position, BufferContent, BufferHandle,
-        BufferProperties,
+        find_path_and_position_at, parse_path_and_position, Buffer, BufferContent, BufferHandle,
+        BufferProperties, SurroundPair,
     },
     buffer_position::{BufferPosition, BufferPositionIndex, BufferRange},
     buffer_view::{BufferViewHandle, CursorMovement, CursorMovementKind},
     client::{ClientHandle, ViewAnchor},
     cursor::Cursor,
     editor::{Editor, EditorContext, EditorFlow, KeysIterator},
-    editor_utils::{hash_bytes, MessageKind, RegisterKey, AUTO_MACRO_REGISTER, SEARCH_REGISTER},
+    editor_utils::{
+        dedent_len, hash_bytes, indentation_unit, leading_whitespace, MessageKind, RegisterKey,
+        TextCase, AUTO_MACRO_REGISTER, SEARCH_REGISTER,
+    },
+    events::EditorEventQueue,
     help::HELP_PREFIX,
     mode::{picker, read_line, ModeKind, ModeState},
     navigation_history::{NavigationHistory, NavigationMovement},
     pattern::PatternEscaper,
     platform::{Key, KeyCode},
-    word_database::WordKind,
+    plugin::{PluginHandle, TextObjectContext},
+    syntax::Syntax,
+    word_database::{WordDatabase, WordKind},
 };
 
 enum CharJump {
@@ -27,6 +33,7 @@ enum CharJump {
 
 pub struct State {
     pub movement_kind: CursorMovementKind,
+    block_selection_anchor: Option<BufferPosition>,
     pub search_index: usize,
     last_char_jump: CharJump,
     is_recording_auto_macro: bool,
@@ -36,8 +43,14 @@ pub struct State {
 }
 
 impl State {
-    fn on_movement_keys(editor: &mut Editor, keys: &KeysIterator, from_index: usize) {
-        let state = &mut editor.mode.normal_state;
+    fn on_movement_keys(
+        ctx: &mut EditorContext,
+        client_handle: ClientHandle,
+        keys: &KeysIterator,
+        from_index: usize,
+    ) {
+        let editor = &mut ctx.editor;
+        let state = &mut ctx.clients.get_mut(client_handle).mode.normal_state;
         match state.movement_kind {
             CursorMovementKind::PositionAndAnchor => state.is_recording_auto_macro = false,
             CursorMovementKind::PositionOnly => {
@@ -59,13 +72,20 @@ impl State {
         }
     }
 
-    fn on_edit_keys(editor: &mut Editor, keys: &KeysIterator, from_index: usize) {
+    fn on_edit_keys(
+        ctx: &mut EditorContext,
+        client_handle: ClientHandle,
+        keys: &KeysIterator,
+        from_index: usize,
+    ) {
+        let editor = &mut ctx.editor;
         let auto_macro_register = editor.registers.get_mut(AUTO_MACRO_REGISTER);
-        let state = &mut editor.mode.normal_state;
+        let state = &mut ctx.clients.get_mut(client_handle).mode.normal_state;
         if !state.is_recording_auto_macro {
             auto_macro_register.clear();
         }
         state.is_recording_auto_macro = false;
+        state.block_selection_anchor = None;
 
         if auto_macro_register.is_empty() && state.count > 0 {
             let _ = write!(auto_macro_register, "{}", state.count);
@@ -82,7 +102,9 @@ impl State {
         keys: &mut KeysIterator,
         handle: BufferViewHandle,
     ) -> Option<EditorFlow> {
-        let state = &mut ctx.editor.mode.normal_state;
+        let client = ctx.clients.get_mut(client_handle);
+        let viewport_height = client.viewport_size.1;
+        let state = &mut client.mode.normal_state;
         let keys_from_index = keys.index;
         match keys.next(&ctx.editor.buffered_keys) {
             Key {
@@ -100,27 +122,39 @@ impl State {
                 control: false,
                 alt: false,
                 ..
-            } => ctx.editor.buffer_views.get_mut(handle).move_cursors(
-                &ctx.editor.buffers,
-                CursorMovement::LinesForward {
-                    count: state.count.max(1) as _,
-                    tab_size: ctx.editor.config.tab_size.get(),
-                },
-                state.movement_kind,
-            ),
+            } => {
+                let buffer_view = ctx.editor.buffer_views.get(handle);
+                let buffer = ctx.editor.buffers.get(buffer_view.buffer_handle);
+                let config = buffer.config(&ctx.editor.config, &ctx.editor.scoped_configs);
+                let tab_size = config.tab_size.get();
+                ctx.editor.buffer_views.get_mut(handle).move_cursors(
+                    &ctx.editor.buffers,
+                    CursorMovement::LinesForward {
+                        count: state.count.max(1) as _,
+                        tab_size,
+                    },
+                    state.movement_kind,
+                )
+            }
             Key {
                 code: KeyCode::Char('k'),
                 control: false,
                 alt: false,
                 ..
-            } => ctx.editor.buffer_views.get_mut(handle).move_cursors(
-                &ctx.editor.buffers,
-                CursorMovement::LinesBackward {
-                    count: state.count.max(1) as _,
-                    tab_size: ctx.editor.config.tab_size.get(),
-                },
-                state.movement_kind,
-            ),
+            } => {
+                let buffer_view = ctx.editor.buffer_views.get(handle);
+                let buffer = ctx.editor.buffers.get(buffer_view.buffer_handle);
+                let config = buffer.config(&ctx.editor.config, &ctx.editor.scoped_configs);
+                let tab_size = config.tab_size.get();
+                ctx.editor.buffer_views.get_mut(handle).move_cursors(
+                    &ctx.editor.buffers,
+                    CursorMovement::LinesBackward {
+                        count: state.count.max(1) as _,
+                        tab_size,
+                    },
+                    state.movement_kind,
+                )
+            }
             Key {
                 code: KeyCode::Char('l'),
                 control: false,
@@ -251,8 +285,11 @@ impl State {
                 }
 
                 let buffer_view = ctx.editor.buffer_views.get_mut(handle);
-                let buffer = ctx.editor.buffers.get(buffer_view.buffer_handle).content();
+                let buffer = ctx.editor.buffers.get(buffer_view.buffer_handle);
+                let syntax = ctx.editor.syntaxes.get(buffer.syntax_handle());
+                let buffer = buffer.content();
                 let mut cursors = buffer_view.cursors.mut_guard();
+                let mut plugin_text_object = None;
 
                 match keys.next(&ctx.editor.buffered_keys) {
                     Key {
@@ -337,10 +374,59 @@ impl State {
                         alt: false,
                         ..
                     } => delimiter_pair(buffer, &mut cursors[..], '`'),
+                    Key {
+                        code: KeyCode::Char('p'),
+                        control: false,
+                        alt: false,
+                        ..
+                    } => select_text_objects(&mut cursors[..], |p| {
+                        Some(paragraph_text_object(buffer, p.line_index, false))
+                    }),
+                    Key {
+                        code: KeyCode::Char('i'),
+                        control: false,
+                        alt: false,
+                        ..
+                    } => select_text_objects(&mut cursors[..], |p| {
+                        indentation_block_text_object(buffer, syntax, p.line_index, false)
+                    }),
+                    Key {
+                        code: KeyCode::Char(','),
+                        control: false,
+                        alt: false,
+                        ..
+                    } => {
+                        select_text_objects(&mut cursors[..], |p| buffer.find_argument_at(p, false))
+                    }
+                    Key {
+                        code: KeyCode::Char('f'),
+                        control: false,
+                        alt: false,
+                        ..
+                    } => select_text_objects(&mut cursors[..], |p| {
+                        function_text_object(buffer, syntax, p.line_index, false)
+                    }),
+                    Key {
+                        code: KeyCode::Char(c),
+                        control: false,
+                        alt: false,
+                        ..
+                    } => plugin_text_object = ctx.plugins.find_text_object(c).map(|h| (h, c)),
                     _ => (),
                 }
 
                 state.movement_kind = CursorMovementKind::PositionOnly;
+                drop(cursors);
+                if let Some((plugin_handle, key)) = plugin_text_object {
+                    select_plugin_text_objects(
+                        ctx,
+                        client_handle,
+                        handle,
+                        plugin_handle,
+                        key,
+                        false,
+                    );
+                }
             }
             Key {
                 code: KeyCode::Char('A'),
@@ -390,8 +476,11 @@ impl State {
                 }
 
                 let buffer_view = ctx.editor.buffer_views.get_mut(handle);
-                let buffer = ctx.editor.buffers.get(buffer_view.buffer_handle).content();
+                let buffer = ctx.editor.buffers.get(buffer_view.buffer_handle);
+                let syntax = ctx.editor.syntaxes.get(buffer.syntax_handle());
+                let buffer = buffer.content();
                 let mut cursors = buffer_view.cursors.mut_guard();
+                let mut plugin_text_object = None;
 
                 match keys.next(&ctx.editor.buffered_keys) {
                     Key {
@@ -479,10 +568,59 @@ impl State {
                         alt: false,
                         ..
                     } => delimiter_pair(buffer, &mut cursors[..], '\''),
+                    Key {
+                        code: KeyCode::Char('p'),
+                        control: false,
+                        alt: false,
+                        ..
+                    } => select_text_objects(&mut cursors[..], |p| {
+                        Some(paragraph_text_object(buffer, p.line_index, true))
+                    }),
+                    Key {
+                        code: KeyCode::Char('i'),
+                        control: false,
+                        alt: false,
+                        ..
+                    } => select_text_objects(&mut cursors[..], |p| {
+                        indentation_block_text_object(buffer, syntax, p.line_index, true)
+                    }),
+                    Key {
+                        code: KeyCode::Char(','),
+                        control: false,
+                        alt: false,
+                        ..
+                    } => {
+                        select_text_objects(&mut cursors[..], |p| buffer.find_argument_at(p, true))
+                    }
+                    Key {
+                        code: KeyCode::Char('f'),
+                        control: false,
+                        alt: false,
+                        ..
+                    } => select_text_objects(&mut cursors[..], |p| {
+                        function_text_object(buffer, syntax, p.line_index, true)
+                    }),
+                    Key {
+                        code: KeyCode::Char(c),
+                        control: false,
+                        alt: false,
+                        ..
+                    } => plugin_text_object = ctx.plugins.find_text_object(c).map(|h| (h, c)),
                     _ => (),
                 }
 
                 state.movement_kind = CursorMovementKind::PositionOnly;
+                drop(cursors);
+                if let Some((plugin_handle, key)) = plugin_text_object {
+                    select_plugin_text_objects(
+                        ctx,
+                        client_handle,
+                        handle,
+                        plugin_handle,
+                        key,
+                        true,
+                    );
+                }
             }
             Key {
                 code: KeyCode::Char('g' | 'G'),
@@ -502,7 +640,8 @@ impl State {
                         alt: false,
                         ..
                     } => {
-                        if state.count > 0 {
+                        let count = state.count;
+                        if count > 0 {
                             NavigationHistory::save_snapshot(
                                 ctx.clients.get_mut(client_handle),
                                 &ctx.editor.buffer_views,
@@ -510,7 +649,7 @@ impl State {
                             let buffer_view = ctx.editor.buffer_views.get_mut(handle);
                             let buffer = ctx.editor.buffers.get(buffer_view.buffer_handle);
                             let buffer = buffer.content();
-                            let line_index = state.count - 1;
+                            let line_index = count - 1;
                             let mut position = BufferPosition::line_col(line_index as _, 0);
                             position = buffer.saturate_position(position);
                             let word = buffer.word_at(position);
@@ -544,6 +683,7 @@ impl State {
                         alt: false,
                         ..
                     } => {
+                        let movement_kind = state.movement_kind;
                         NavigationHistory::save_snapshot(
                             ctx.clients.get_mut(client_handle),
                             &ctx.editor.buffer_views,
@@ -552,7 +692,7 @@ impl State {
                         buffer_view.move_cursors(
                             &ctx.editor.buffers,
                             CursorMovement::LastLine,
-                            state.movement_kind,
+                            movement_kind,
                         );
                     }
                     Key {
@@ -561,6 +701,7 @@ impl State {
                         alt: false,
                         ..
                     } => {
+                        let movement_kind = state.movement_kind;
                         NavigationHistory::save_snapshot(
                             ctx.clients.get_mut(client_handle),
                             &ctx.editor.buffer_views,
@@ -569,7 +710,7 @@ impl State {
                         buffer_view.move_cursors(
                             &ctx.editor.buffers,
                             CursorMovement::FirstLine,
-                            state.movement_kind,
+                            movement_kind,
                         );
                     }
                     Key {
@@ -713,8 +854,11 @@ impl State {
                                     }
                                     jumped = true;
 
-                                    ctx.editor.mode.normal_state.movement_kind =
-                                        CursorMovementKind::PositionAndAnchor;
+                                    ctx.clients
+                                        .get_mut(client_handle)
+                                        .mode
+                                        .normal_state
+                                        .movement_kind = CursorMovementKind::PositionAndAnchor;
                                     let client = ctx.clients.get_mut(client_handle);
                                     if should_close_current_buffer {
                                         if let Some(buffer_view_handle) =
@@ -911,6 +1055,25 @@ impl State {
                     }
                 };
             }
+            Key {
+                code: KeyCode::Char('v'),
+                shift: false,
+                control: true,
+                alt: false,
+            } => match state.block_selection_anchor.take() {
+                Some(_) => {
+                    let buffer_view = ctx.editor.buffer_views.get_mut(handle);
+                    for cursor in &mut buffer_view.cursors.mut_guard()[..] {
+                        cursor.anchor = cursor.position;
+                    }
+                    state.movement_kind = CursorMovementKind::PositionAndAnchor;
+                }
+                None => {
+                    let buffer_view = ctx.editor.buffer_views.get(handle);
+                    state.block_selection_anchor = Some(buffer_view.cursors.main_cursor().position);
+                    state.movement_kind = CursorMovementKind::PositionOnly;
+                }
+            },
             Key {
                 code: KeyCode::Char('V'),
                 control: false,
@@ -1082,12 +1245,16 @@ impl State {
                 alt: false,
                 ..
             } => {
-                let half_height = ctx.clients.get(client_handle).viewport_size.1 / 2;
+                let half_height = viewport_height / 2;
+                let buffer_view = ctx.editor.buffer_views.get(handle);
+                let buffer = ctx.editor.buffers.get(buffer_view.buffer_handle);
+                let config = buffer.config(&ctx.editor.config, &ctx.editor.scoped_configs);
+                let tab_size = config.tab_size.get();
                 ctx.editor.buffer_views.get_mut(handle).move_cursors(
                     &ctx.editor.buffers,
                     CursorMovement::LinesForward {
                         count: half_height as usize * state.count.max(1) as usize,
-                        tab_size: ctx.editor.config.tab_size.get(),
+                        tab_size,
                     },
                     state.movement_kind,
                 );
@@ -1099,12 +1266,16 @@ impl State {
                 alt: false,
                 ..
             } => {
-                let half_height = ctx.clients.get(client_handle).viewport_size.1 / 2;
+                let half_height = viewport_height / 2;
+                let buffer_view = ctx.editor.buffer_views.get(handle);
+                let buffer = ctx.editor.buffers.get(buffer_view.buffer_handle);
+                let config = buffer.config(&ctx.editor.config, &ctx.editor.scoped_configs);
+                let tab_size = config.tab_size.get();
                 ctx.editor.buffer_views.get_mut(handle).move_cursors(
                     &ctx.editor.buffers,
                     CursorMovement::LinesBackward {
                         count: half_height as usize * state.count.max(1) as usize,
-                        tab_size: ctx.editor.config.tab_size.get(),
+                        tab_size,
                     },
                     state.movement_kind,
                 );
@@ -1127,7 +1298,7 @@ impl State {
                     .get_mut(buffer_view.buffer_handle)
                     .commit_edits();
                 state.movement_kind = CursorMovementKind::PositionAndAnchor;
-                Self::on_edit_keys(&mut ctx.editor, keys, keys_from_index);
+                Self::on_edit_keys(ctx, client_handle, keys, keys_from_index);
                 return Some(EditorFlow::Continue);
             }
             Key {
@@ -1145,8 +1316,8 @@ impl State {
                     &mut ctx.editor.events,
                 );
 
-                Self::on_edit_keys(&mut ctx.editor, keys, keys_from_index);
-                ctx.editor.enter_mode(ModeKind::Insert);
+                Self::on_edit_keys(ctx, client_handle, keys, keys_from_index);
+                Editor::enter_mode(ctx, client_handle, ModeKind::Insert);
                 return Some(EditorFlow::Continue);
             }
             Key {
@@ -1158,7 +1329,11 @@ impl State {
                 let buffer_view = ctx.editor.buffer_views.get(handle);
                 let cursor_count = buffer_view.cursors[..].len();
                 let buffer = ctx.editor.buffers.get_mut(buffer_view.buffer_handle);
+                let config = buffer.config(&ctx.editor.config, &ctx.editor.scoped_configs);
                 let count = state.count.max(1);
+                let indentation_width = buffer
+                    .indentation_style(&config, &ctx.editor.scoped_configs)
+                    .width(config.tab_size.get());
 
                 for i in 0..cursor_count {
                     let range = ctx.editor.buffer_views.get(handle).cursors[i].to_range();
@@ -1172,7 +1347,7 @@ impl State {
                                 Some((i, c @ '\t')) => i + c.len_utf8(),
                                 Some((i, c @ ' ')) => {
                                     match chars
-                                        .take(ctx.editor.config.tab_size.get() as usize - 1)
+                                        .take(indentation_width as usize - 1)
                                         .take_while(|(_, c)| *c == ' ')
                                         .last()
                                     {
@@ -1196,7 +1371,7 @@ impl State {
                 }
 
                 buffer.commit_edits();
-                Self::on_edit_keys(&mut ctx.editor, keys, keys_from_index);
+                Self::on_edit_keys(ctx, client_handle, keys, keys_from_index);
                 return Some(EditorFlow::Continue);
             }
             Key {
@@ -1205,22 +1380,17 @@ impl State {
                 alt: false,
                 ..
             } => {
-                let cursor_count = ctx.editor.buffer_views.get(handle).cursors[..].len();
-
-                let extender = if ctx.editor.config.indent_with_tabs {
-                    let count = state.count.max(1) as _;
-                    std::iter::repeat('\t').take(count)
-                } else {
-                    let tab_size = ctx.editor.config.tab_size.get() as usize;
-                    let count = state.count.max(1) as usize * tab_size;
-                    std::iter::repeat(' ').take(count)
-                };
-
                 let buffer_view = ctx.editor.buffer_views.get(handle);
+                let cursor_count = buffer_view.cursors[..].len();
                 let buffer = ctx.editor.buffers.get_mut(buffer_view.buffer_handle);
+                let config = buffer.config(&ctx.editor.config, &ctx.editor.scoped_configs);
+                let indentation_unit =
+                    indentation_unit(buffer.indentation_style(&config, &ctx.editor.scoped_configs));
 
                 let mut buf = ctx.editor.string_pool.acquire();
-                buf.extend(extender);
+                for _ in 0..state.count.max(1) {
+                    buf.push_str(indentation_unit);
+                }
                 for i in 0..cursor_count {
                     let range = ctx.editor.buffer_views.get(handle).cursors[i].to_range();
                     for line_index in range.from.line_index..=range.to.line_index {
@@ -1235,133 +1405,479 @@ impl State {
                 ctx.editor.string_pool.release(buf);
 
                 buffer.commit_edits();
-                Self::on_edit_keys(&mut ctx.editor, keys, keys_from_index);
+                Self::on_edit_keys(ctx, client_handle, keys, keys_from_index);
                 return Some(EditorFlow::Continue);
             }
             Key {
-                code: KeyCode::Char('c' | 'C'),
+                code: KeyCode::Char('='),
                 control: false,
                 alt: false,
                 ..
-            } => match keys.next(&ctx.editor.buffered_keys) {
-                Key {
-                    code: KeyCode::None,
-                    ..
-                } => return None,
-                Key {
-                    code: KeyCode::Char('c'),
-                    control: false,
-                    alt: false,
-                    ..
-                } => {
-                    let buffer_view = ctx.editor.buffer_views.get_mut(handle);
-                    for cursor in &mut buffer_view.cursors.mut_guard()[..] {
-                        std::mem::swap(&mut cursor.anchor, &mut cursor.position);
-                    }
-                }
-                Key {
-                    code: KeyCode::Char('C'),
-                    control: false,
-                    alt: false,
-                    ..
-                } => {
-                    let buffer_view = ctx.editor.buffer_views.get_mut(handle);
-                    for cursor in &mut buffer_view.cursors.mut_guard()[..] {
-                        if cursor.position < cursor.anchor {
-                            std::mem::swap(&mut cursor.anchor, &mut cursor.position);
-                        }
-                    }
-                }
-                Key {
-                    code: KeyCode::Char('l'),
-                    control: false,
-                    alt: false,
-                    ..
-                } => {
-                    let buffer_view = ctx.editor.buffer_views.get_mut(handle);
-                    let buffer = ctx.editor.buffers.get(buffer_view.buffer_handle).content();
-
-                    let mut cursors = buffer_view.cursors.mut_guard();
-                    let cursor_count = cursors[..].len();
+            } => {
+                let buffer_view = ctx.editor.buffer_views.get(handle);
+                let buffer = ctx.editor.buffers.get_mut(buffer_view.buffer_handle);
+                let config = buffer.config(&ctx.editor.config, &ctx.editor.scoped_configs);
+                let syntax = ctx.editor.syntaxes.get(buffer.syntax_handle());
+                let indentation_style =
+                    buffer.indentation_style(&config, &ctx.editor.scoped_configs);
+                let indentation_unit = indentation_unit(indentation_style);
+                let indentation_width = indentation_style.width(config.tab_size.get());
 
-                    for i in 0..cursor_count {
-                        let cursor = &mut cursors[i];
-                        if cursor.anchor.line_index == cursor.position.line_index {
+                let mut buf = ctx.editor.string_pool.acquire();
+                for cursor in &buffer_view.cursors[..] {
+                    let range = cursor.to_range();
+                    for line_index in range.from.line_index..=range.to.line_index {
+                        let lines = buffer.content().lines();
+                        let line = lines[line_index as usize].as_str();
+                        if line.trim().is_empty() {
                             continue;
                         }
 
-                        let range = BufferRange::between(cursor.anchor, cursor.position);
-                        if range.to == cursor.position {
-                            cursor.anchor = range.from;
-                            cursor.position = BufferPosition::line_col(
-                                range.from.line_index,
-                                buffer.lines()[range.from.line_index as usize]
-                                    .as_str()
-                                    .len() as _,
-                            );
-
-                            for line_index in (range.from.line_index + 1)..range.to.line_index {
-                                let line_len = buffer.lines()[line_index as usize].as_str().len();
-                                cursors.add(Cursor {
-                                    anchor: BufferPosition::line_col(line_index, 0),
-                                    position: BufferPosition::line_col(line_index, line_len as _),
-                                });
-                            }
-
-                            cursors.add(Cursor {
-                                anchor: BufferPosition::line_col(range.to.line_index, 0),
-                                position: range.to,
-                            });
-                        } else {
-                            cursor.anchor = range.to;
-                            cursor.position = BufferPosition::line_col(range.to.line_index, 0);
-
-                            for line_index in (range.from.line_index + 1)..range.to.line_index {
-                                let line_len = buffer.lines()[line_index as usize].as_str().len();
-                                cursors.add(Cursor {
-                                    anchor: BufferPosition::line_col(line_index, line_len as _),
-                                    position: BufferPosition::line_col(line_index, 0),
-                                });
+                        buf.clear();
+                        let previous_line = lines[..line_index as usize]
+                            .iter()
+                            .map(|l| l.as_str())
+                            .rfind(|l| !l.trim().is_empty());
+                        if let Some(previous_line) = previous_line {
+                            buf.push_str(leading_whitespace(previous_line));
+                            if syntax.increases_indentation(previous_line) {
+                                buf.push_str(indentation_unit);
                             }
+                        }
+                        if syntax.decreases_indentation(line) {
+                            buf.truncate(buf.len() - dedent_len(&buf, indentation_width));
+                        }
 
-                            cursors.add(Cursor {
-                                anchor: BufferPosition::line_col(
-                                    range.from.line_index,
-                                    buffer.lines()[range.from.line_index as usize]
-                                        .as_str()
-                                        .len() as _,
-                                ),
-                                position: range.from,
-                            });
+                        let indentation = leading_whitespace(line);
+                        if indentation == buf {
+                            continue;
                         }
+
+                        let range = BufferRange::between(
+                            BufferPosition::line_col(line_index, 0),
+                            BufferPosition::line_col(line_index, indentation.len() as _),
+                        );
+                        buffer.delete_range(
+                            &mut ctx.editor.word_database,
+                            range,
+                            &mut ctx.editor.events,
+                        );
+                        buffer.insert_text(
+                            &mut ctx.editor.word_database,
+                            BufferPosition::line_col(line_index, 0),
+                            &buf,
+                            &mut ctx.editor.events,
+                        );
                     }
                 }
-                Key {
-                    code: KeyCode::Char('D'),
-                    control: false,
-                    alt: false,
-                    ..
-                } => {
-                    let mut cursors = ctx.editor.buffer_views.get_mut(handle).cursors.mut_guard();
-                    let main_cursor = *cursors.main_cursor();
-                    cursors.clear();
-                    cursors.add(main_cursor);
-                    state.movement_kind = CursorMovementKind::PositionAndAnchor;
-                }
-                Key {
-                    code: KeyCode::Char('d'),
-                    control: false,
-                    alt: false,
-                    ..
-                } => {
-                    let mut cursors = ctx.editor.buffer_views.get_mut(handle).cursors.mut_guard();
-                    if !cursors[..].is_empty() {
-                        let main_cursor_position = cursors.main_cursor().position;
-                        let main_cursor_index = cursors.main_cursor_index();
-                        cursors.swap_remove(main_cursor_index);
-                        cursors.set_main_cursor_near_position(main_cursor_position);
+                ctx.editor.string_pool.release(buf);
 
-                        state.movement_kind = CursorMovementKind::PositionAndAnchor;
+                buffer.commit_edits();
+                Self::on_edit_keys(ctx, client_handle, keys, keys_from_index);
+                return Some(EditorFlow::Continue);
+            }
+            Key {
+                code: KeyCode::Char('#'),
+                control: false,
+                alt: false,
+                ..
+            } => {
+                let buffer_view = ctx.editor.buffer_views.get(handle);
+                let buffer = ctx.editor.buffers.get_mut(buffer_view.buffer_handle);
+                let syntax = ctx.editor.syntaxes.get(buffer.syntax_handle());
+
+                let mut line_ranges = Vec::new();
+                for cursor in &buffer_view.cursors[..] {
+                    let range = cursor.to_range();
+                    match line_ranges.last_mut() {
+                        Some((_, to)) if range.from.line_index <= *to => {
+                            *to = range.to.line_index.max(*to);
+                        }
+                        _ => line_ranges.push((range.from.line_index, range.to.line_index)),
+                    }
+                }
+
+                let mut buf = ctx.editor.string_pool.acquire();
+                let line_comment = syntax.line_comment();
+                if !line_comment.is_empty() {
+                    toggle_line_comments(
+                        buffer,
+                        &mut ctx.editor.word_database,
+                        &mut ctx.editor.events,
+                        &line_ranges,
+                        line_comment,
+                        &mut buf,
+                    );
+                } else if let Some((begin, end)) = syntax.block_comment() {
+                    toggle_block_comments(
+                        buffer,
+                        &mut ctx.editor.word_database,
+                        &mut ctx.editor.events,
+                        &line_ranges,
+                        (begin, end),
+                        &mut buf,
+                    );
+                } else {
+                    ctx.editor
+                        .status_bar
+                        .write(MessageKind::Error)
+                        .str("syntax has no comment delimiters");
+                }
+                ctx.editor.string_pool.release(buf);
+
+                buffer.commit_edits();
+                Self::on_edit_keys(ctx, client_handle, keys, keys_from_index);
+                return Some(EditorFlow::Continue);
+            }
+            Key {
+                code: KeyCode::Char('&'),
+                control: false,
+                alt: false,
+                ..
+            } => {
+                let buffer_view = ctx.editor.buffer_views.get(handle);
+                let buffer = ctx.editor.buffers.get_mut(buffer_view.buffer_handle);
+                let config = buffer.config(&ctx.editor.config, &ctx.editor.scoped_configs);
+                let indentation_style =
+                    buffer.indentation_style(&config, &ctx.editor.scoped_configs);
+                let tab_size = config.tab_size.get();
+
+                let mut buf = ctx.editor.string_pool.acquire();
+                buffer_view.align_cursors(
+                    &mut ctx.editor.buffers,
+                    &mut ctx.editor.word_database,
+                    indentation_style,
+                    tab_size,
+                    &mut buf,
+                    &mut ctx.editor.events,
+                );
+                ctx.editor.string_pool.release(buf);
+
+                ctx.editor
+                    .buffers
+                    .get_mut(buffer_view.buffer_handle)
+                    .commit_edits();
+                Self::on_edit_keys(ctx, client_handle, keys, keys_from_index);
+                return Some(EditorFlow::Continue);
+            }
+            Key {
+                code: KeyCode::Char(c @ ('a' | 'x')),
+                shift: false,
+                control: true,
+                alt: false,
+            } => {
+                let delta = match c {
+                    'a' => state.count.max(1) as i64,
+                    _ => -(state.count.max(1) as i64),
+                };
+                increment_numbers(ctx, handle, delta, false);
+                Self::on_edit_keys(ctx, client_handle, keys, keys_from_index);
+                return Some(EditorFlow::Continue);
+            }
+            Key {
+                code: KeyCode::Char(c @ ('+' | '-')),
+                control: false,
+                alt: false,
+                ..
+            } => {
+                let delta = match c {
+                    '+' => state.count.max(1) as i64,
+                    _ => -(state.count.max(1) as i64),
+                };
+                increment_numbers(ctx, handle, delta, true);
+                Self::on_edit_keys(ctx, client_handle, keys, keys_from_index);
+                return Some(EditorFlow::Continue);
+            }
+            Key {
+                code: KeyCode::Char('~'),
+                control: false,
+                alt: false,
+                ..
+            } => {
+                let case = match keys.next(&ctx.editor.buffered_keys) {
+                    Key {
+                        code: KeyCode::None,
+                        ..
+                    } => return None,
+                    Key {
+                        code: KeyCode::Char(c),
+                        control: false,
+                        alt: false,
+                        ..
+                    } => match c {
+                        'l' => Some(TextCase::Lower),
+                        'u' => Some(TextCase::Upper),
+                        '~' => Some(TextCase::Swap),
+                        's' => Some(TextCase::Snake),
+                        'S' => Some(TextCase::ScreamingSnake),
+                        'k' => Some(TextCase::Kebab),
+                        'c' => Some(TextCase::Camel),
+                        'p' => Some(TextCase::Pascal),
+                        _ => None,
+                    },
+                    _ => None,
+                };
+
+                if let Some(case) = case {
+                    let buffer_view = ctx.editor.buffer_views.get(handle);
+                    let mut buf = ctx.editor.string_pool.acquire();
+                    buffer_view.convert_case(
+                        &mut ctx.editor.buffers,
+                        &mut ctx.editor.word_database,
+                        case,
+                        &mut buf,
+                        &mut ctx.editor.events,
+                    );
+                    ctx.editor.string_pool.release(buf);
+
+                    ctx.editor
+                        .buffers
+                        .get_mut(buffer_view.buffer_handle)
+                        .commit_edits();
+                    Self::on_edit_keys(ctx, client_handle, keys, keys_from_index);
+                    return Some(EditorFlow::Continue);
+                }
+            }
+            Key {
+                code: KeyCode::Char('S'),
+                control: false,
+                alt: false,
+                ..
+            } => match keys.next(&ctx.editor.buffered_keys) {
+                Key {
+                    code: KeyCode::None,
+                    ..
+                } => return None,
+                Key {
+                    code: KeyCode::Char('a'),
+                    control: false,
+                    alt: false,
+                    ..
+                } => match keys.next(&ctx.editor.buffered_keys) {
+                    Key {
+                        code: KeyCode::None,
+                        ..
+                    } => return None,
+                    Key {
+                        code: KeyCode::Char('t'),
+                        control: false,
+                        alt: false,
+                        ..
+                    } => read_line::surround::enter_tag_mode(ctx, client_handle, None),
+                    Key {
+                        code: KeyCode::Char(c),
+                        control: false,
+                        alt: false,
+                        ..
+                    } => {
+                        surround_with_key(ctx, handle, None, c);
+                        Self::on_edit_keys(ctx, client_handle, keys, keys_from_index);
+                        return Some(EditorFlow::Continue);
+                    }
+                    _ => (),
+                },
+                Key {
+                    code: KeyCode::Char('d'),
+                    control: false,
+                    alt: false,
+                    ..
+                } => match keys.next(&ctx.editor.buffered_keys) {
+                    Key {
+                        code: KeyCode::None,
+                        ..
+                    } => return None,
+                    Key {
+                        code: KeyCode::Char(c),
+                        control: false,
+                        alt: false,
+                        ..
+                    } => {
+                        let buffer_view = ctx.editor.buffer_views.get(handle);
+                        buffer_view.change_surround(
+                            &mut ctx.editor.buffers,
+                            &mut ctx.editor.word_database,
+                            SurroundPair::from_key(c),
+                            None,
+                            &mut ctx.editor.events,
+                        );
+                        ctx.editor
+                            .buffers
+                            .get_mut(buffer_view.buffer_handle)
+                            .commit_edits();
+                        Self::on_edit_keys(ctx, client_handle, keys, keys_from_index);
+                        return Some(EditorFlow::Continue);
+                    }
+                    _ => (),
+                },
+                Key {
+                    code: KeyCode::Char('c'),
+                    control: false,
+                    alt: false,
+                    ..
+                } => match keys.next(&ctx.editor.buffered_keys) {
+                    Key {
+                        code: KeyCode::None,
+                        ..
+                    } => return None,
+                    Key {
+                        code: KeyCode::Char(old),
+                        control: false,
+                        alt: false,
+                        ..
+                    } => match keys.next(&ctx.editor.buffered_keys) {
+                        Key {
+                            code: KeyCode::None,
+                            ..
+                        } => return None,
+                        Key {
+                            code: KeyCode::Char('t'),
+                            control: false,
+                            alt: false,
+                            ..
+                        } => {
+                            let pair = SurroundPair::from_key(old);
+                            read_line::surround::enter_tag_mode(ctx, client_handle, Some(pair));
+                        }
+                        Key {
+                            code: KeyCode::Char(c),
+                            control: false,
+                            alt: false,
+                            ..
+                        } => {
+                            surround_with_key(ctx, handle, Some(SurroundPair::from_key(old)), c);
+                            Self::on_edit_keys(ctx, client_handle, keys, keys_from_index);
+                            return Some(EditorFlow::Continue);
+                        }
+                        _ => (),
+                    },
+                    _ => (),
+                },
+                _ => (),
+            },
+            Key {
+                code: KeyCode::Char('c' | 'C'),
+                control: false,
+                alt: false,
+                ..
+            } => match keys.next(&ctx.editor.buffered_keys) {
+                Key {
+                    code: KeyCode::None,
+                    ..
+                } => return None,
+                Key {
+                    code: KeyCode::Char('c'),
+                    control: false,
+                    alt: false,
+                    ..
+                } => {
+                    let buffer_view = ctx.editor.buffer_views.get_mut(handle);
+                    for cursor in &mut buffer_view.cursors.mut_guard()[..] {
+                        std::mem::swap(&mut cursor.anchor, &mut cursor.position);
+                    }
+                }
+                Key {
+                    code: KeyCode::Char('C'),
+                    control: false,
+                    alt: false,
+                    ..
+                } => {
+                    let buffer_view = ctx.editor.buffer_views.get_mut(handle);
+                    for cursor in &mut buffer_view.cursors.mut_guard()[..] {
+                        if cursor.position < cursor.anchor {
+                            std::mem::swap(&mut cursor.anchor, &mut cursor.position);
+                        }
+                    }
+                }
+                Key {
+                    code: KeyCode::Char('l'),
+                    control: false,
+                    alt: false,
+                    ..
+                } => {
+                    let buffer_view = ctx.editor.buffer_views.get_mut(handle);
+                    let buffer = ctx.editor.buffers.get(buffer_view.buffer_handle).content();
+
+                    let mut cursors = buffer_view.cursors.mut_guard();
+                    let cursor_count = cursors[..].len();
+
+                    for i in 0..cursor_count {
+                        let cursor = &mut cursors[i];
+                        if cursor.anchor.line_index == cursor.position.line_index {
+                            continue;
+                        }
+
+                        let range = BufferRange::between(cursor.anchor, cursor.position);
+                        if range.to == cursor.position {
+                            cursor.anchor = range.from;
+                            cursor.position = BufferPosition::line_col(
+                                range.from.line_index,
+                                buffer.lines()[range.from.line_index as usize]
+                                    .as_str()
+                                    .len() as _,
+                            );
+
+                            for line_index in (range.from.line_index + 1)..range.to.line_index {
+                                let line_len = buffer.lines()[line_index as usize].as_str().len();
+                                cursors.add(Cursor {
+                                    anchor: BufferPosition::line_col(line_index, 0),
+                                    position: BufferPosition::line_col(line_index, line_len as _),
+                                });
+                            }
+
+                            cursors.add(Cursor {
+                                anchor: BufferPosition::line_col(range.to.line_index, 0),
+                                position: range.to,
+                            });
+                        } else {
+                            cursor.anchor = range.to;
+                            cursor.position = BufferPosition::line_col(range.to.line_index, 0);
+
+                            for line_index in (range.from.line_index + 1)..range.to.line_index {
+                                let line_len = buffer.lines()[line_index as usize].as_str().len();
+                                cursors.add(Cursor {
+                                    anchor: BufferPosition::line_col(line_index, line_len as _),
+                                    position: BufferPosition::line_col(line_index, 0),
+                                });
+                            }
+
+                            cursors.add(Cursor {
+                                anchor: BufferPosition::line_col(
+                                    range.from.line_index,
+                                    buffer.lines()[range.from.line_index as usize]
+                                        .as_str()
+                                        .len() as _,
+                                ),
+                                position: range.from,
+                            });
+                        }
+                    }
+                }
+                Key {
+                    code: KeyCode::Char('D'),
+                    control: false,
+                    alt: false,
+                    ..
+                } => {
+                    let mut cursors = ctx.editor.buffer_views.get_mut(handle).cursors.mut_guard();
+                    let main_cursor = *cursors.main_cursor();
+                    cursors.clear();
+                    cursors.add(main_cursor);
+                    state.movement_kind = CursorMovementKind::PositionAndAnchor;
+                }
+                Key {
+                    code: KeyCode::Char('d'),
+                    control: false,
+                    alt: false,
+                    ..
+                } => {
+                    let mut cursors = ctx.editor.buffer_views.get_mut(handle).cursors.mut_guard();
+                    if !cursors[..].is_empty() {
+                        let main_cursor_position = cursors.main_cursor().position;
+                        let main_cursor_index = cursors.main_cursor_index();
+                        cursors.swap_remove(main_cursor_index);
+                        cursors.set_main_cursor_near_position(main_cursor_position);
+
+                        state.movement_kind = CursorMovementKind::PositionAndAnchor;
                     }
                 }
                 Key {
@@ -1445,7 +1961,12 @@ impl State {
                     let cursor_count = cursors[..].len();
                     let offset = state.count.max(1) as usize;
                     cursors.set_main_cursor_index((index + offset) % cursor_count);
-                    let ranges = &mut ctx.editor.mode.normal_state.last_copy_ranges;
+                    let ranges = &mut ctx
+                        .clients
+                        .get_mut(client_handle)
+                        .mode
+                        .normal_state
+                        .last_copy_ranges;
                     if !ranges.is_empty() {
                         let offset = offset % ranges.len();
                         ranges.rotate_right(offset);
@@ -1463,7 +1984,12 @@ impl State {
                     let cursor_count = cursors[..].len();
                     let offset = state.count.max(1) as usize % cursor_count;
                     cursors.set_main_cursor_index((index + cursor_count - offset) % cursor_count);
-                    let ranges = &mut ctx.editor.mode.normal_state.last_copy_ranges;
+                    let ranges = &mut ctx
+                        .clients
+                        .get_mut(client_handle)
+                        .mode
+                        .normal_state
+                        .last_copy_ranges;
                     if !ranges.is_empty() {
                         let offset = offset % ranges.len();
                         ranges.rotate_left(offset);
@@ -1474,25 +2000,37 @@ impl State {
                     control: false,
                     alt: false,
                     ..
-                } => read_line::filter_cursors::enter_filter_mode(ctx),
+                } => read_line::filter_cursors::enter_filter_mode(ctx, client_handle),
                 Key {
                     code: KeyCode::Char('F'),
                     control: false,
                     alt: false,
                     ..
-                } => read_line::filter_cursors::enter_except_mode(ctx),
+                } => read_line::filter_cursors::enter_except_mode(ctx, client_handle),
                 Key {
                     code: KeyCode::Char('s'),
                     control: false,
                     alt: false,
                     ..
-                } => read_line::split_cursors::enter_by_pattern_mode(ctx),
+                } => read_line::split_cursors::enter_by_pattern_mode(ctx, client_handle),
                 Key {
                     code: KeyCode::Char('S'),
                     control: false,
                     alt: false,
                     ..
-                } => read_line::split_cursors::enter_by_separators_mode(ctx),
+                } => read_line::split_cursors::enter_by_separators_mode(ctx, client_handle),
+                Key {
+                    code: KeyCode::Char('a'),
+                    control: false,
+                    alt: false,
+                    ..
+                } => select_all_occurrences(ctx, client_handle, false),
+                Key {
+                    code: KeyCode::Char('A'),
+                    control: false,
+                    alt: false,
+                    ..
+                } => select_all_occurrences(ctx, client_handle, true),
                 _ => (),
             },
             Key {
@@ -1536,7 +2074,7 @@ impl State {
                     ..
                 } => {
                     if let Some(key) = RegisterKey::from_char(c) {
-                        let register = ctx.editor.registers.get_mut(key);
+                        let register = ctx.editor.registers.get_marker_mut(key);
                         register.clear();
 
                         let buffer_view = ctx.editor.buffer_views.get(handle);
@@ -1572,7 +2110,7 @@ impl State {
                 ..
             } => {
                 let mut text = ctx.editor.string_pool.acquire();
-                copy_text(ctx, handle, &mut text);
+                copy_text(ctx, client_handle, handle, &mut text);
                 if !text.is_empty() {
                     ctx.platform.write_to_clipboard(&text);
                 }
@@ -1586,7 +2124,7 @@ impl State {
             } => {
                 let mut text = ctx.editor.string_pool.acquire();
                 ctx.platform.read_from_clipboard(&mut text);
-                paste_text(ctx, handle, &text);
+                paste_text(ctx, client_handle, handle, &text);
                 ctx.editor.string_pool.release(text);
                 return Some(EditorFlow::Continue);
             }
@@ -1611,7 +2149,7 @@ impl State {
                     if key == c {
                         if let Some(key) = RegisterKey::from_char(key) {
                             let mut text = ctx.editor.string_pool.acquire();
-                            copy_text(ctx, handle, &mut text);
+                            copy_text(ctx, client_handle, handle, &mut text);
                             if !text.is_empty() {
                                 let register = ctx.editor.registers.get_mut(key);
                                 register.clear();
@@ -1623,7 +2161,7 @@ impl State {
                         if let Some(key) = RegisterKey::from_char(key) {
                             let register = ctx.editor.registers.get(key);
                             let text = ctx.editor.string_pool.acquire_with(register);
-                            paste_text(ctx, handle, &text);
+                            paste_text(ctx, client_handle, handle, &text);
                             ctx.editor.string_pool.release(text);
                             return Some(EditorFlow::Continue);
                         }
@@ -1664,8 +2202,24 @@ impl State {
             _ => (),
         }
 
-        Self::on_movement_keys(&mut ctx.editor, keys, keys_from_index);
-        ctx.editor.mode.normal_state.count = 0;
+        let state = &mut ctx.clients.get_mut(client_handle).mode.normal_state;
+        if let Some(anchor) = state.block_selection_anchor {
+            match state.movement_kind {
+                CursorMovementKind::PositionAndAnchor => state.block_selection_anchor = None,
+                CursorMovementKind::PositionOnly => {
+                    let buffer_view = ctx.editor.buffer_views.get_mut(handle);
+                    let buffer = ctx.editor.buffers.get(buffer_view.buffer_handle);
+                    let tab_size = buffer
+                        .config(&ctx.editor.config, &ctx.editor.scoped_configs)
+                        .tab_size
+                        .get();
+                    buffer_view.select_block(&ctx.editor.buffers, anchor, tab_size);
+                }
+            }
+        }
+
+        Self::on_movement_keys(ctx, client_handle, keys, keys_from_index);
+        ctx.clients.get_mut(client_handle).mode.normal_state.count = 0;
         Some(EditorFlow::Continue)
     }
 }
@@ -1674,6 +2228,7 @@ impl Default for State {
     fn default() -> Self {
         Self {
             movement_kind: CursorMovementKind::PositionAndAnchor,
+            block_selection_anchor: None,
             search_index: 0,
             last_char_jump: CharJump::None,
             is_recording_auto_macro: false,
@@ -1685,13 +2240,14 @@ impl Default for State {
 }
 
 impl ModeState for State {
-    fn on_enter(editor: &mut Editor) {
-        let state = &mut editor.mode.normal_state;
+    fn on_enter(ctx: &mut EditorContext, client_handle: ClientHandle) {
+        let state = &mut ctx.clients.get_mut(client_handle).mode.normal_state;
         state.is_recording_auto_macro = false;
+        state.block_selection_anchor = None;
         state.count = 0;
     }
 
-    fn on_exit(_: &mut Editor) {}
+    fn on_exit(_: &mut EditorContext, _: ClientHandle) {}
 
     fn on_keys(
         ctx: &mut EditorContext,
@@ -1739,7 +2295,7 @@ impl ModeState for State {
             }
         }
 
-        let state = &mut ctx.editor.mode.normal_state;
+        let state = &mut ctx.clients.get_mut(client_handle).mode.normal_state;
 
         let mut handled_keys = false;
         let previous_index = keys.index;
@@ -1831,55 +2387,19 @@ impl ModeState for State {
                 ..
             } => match keys.next(&ctx.editor.buffered_keys) {
                 Key {
-                    code: KeyCode::None,
-                    ..
-                } => return None,
-                Key {
-                    code: KeyCode::Char(c),
-                    control: false,
-                    alt: false,
-                    ..
-                } => {
-                    handled_keys = true;
-                    let c = c.to_ascii_lowercase();
-                    if let Some(key) = RegisterKey::from_char(c) {
-                        let register = ctx.editor.registers.get(key);
-                        let (path, position) = parse_path_and_position(register);
-                        let path = ctx.editor.string_pool.acquire_with(path);
-                        match ctx.editor.buffer_view_handle_from_path(
-                            client_handle,
-                            Path::new(&path),
-                            BufferProperties::text(),
-                            false,
-                        ) {
-                            Ok(handle) => {
-                                let client = ctx.clients.get_mut(client_handle);
-                                client
-                                    .set_buffer_view_handle(Some(handle), &ctx.editor.buffer_views);
-
-                                if let Some(position) = position {
-                                    let buffer_view = ctx.editor.buffer_views.get_mut(handle);
-                                    let buffer = ctx.editor.buffers.get(buffer_view.buffer_handle);
-                                    let position = buffer.content().saturate_position(position);
-
-                                    let mut cursors = buffer_view.cursors.mut_guard();
-                                    cursors.clear();
-                                    cursors.add(Cursor {
-                                        anchor: position,
-                                        position,
-                                    });
-                                }
-
-                                ctx.editor.mode.normal_state.movement_kind =
-                                    CursorMovementKind::PositionAndAnchor;
-                            }
-                            Err(error) => ctx
-                                .editor
-                                .status_bar
-                                .write(MessageKind::Error)
-                                .fmt(format_args!("invalid marker '{}': {}", &path, error)),
-                        }
-                        ctx.editor.string_pool.release(path);
+                    code: KeyCode::None,
+                    ..
+                } => return None,
+                Key {
+                    code: KeyCode::Char(c),
+                    control: false,
+                    alt: false,
+                    ..
+                } => {
+                    handled_keys = true;
+                    let c = c.to_ascii_lowercase();
+                    if let Some(key) = RegisterKey::from_char(c) {
+                        go_to_marker(ctx, client_handle, key);
                     }
                 }
                 _ => (),
@@ -1891,7 +2411,7 @@ impl ModeState for State {
                 ..
             } => {
                 handled_keys = true;
-                ctx.editor.enter_mode(ModeKind::Command);
+                Editor::enter_mode(ctx, client_handle, ModeKind::Command);
             }
             Key {
                 code: KeyCode::Char('g' | 'G'),
@@ -1912,7 +2432,34 @@ impl ModeState for State {
                             ..
                         } => {
                             handled_keys = true;
-                            picker::opened_buffers::enter_mode(ctx);
+                            picker::opened_buffers::enter_mode(ctx, client_handle);
+                        }
+                        Key {
+                            code: KeyCode::Char('r'),
+                            control: false,
+                            alt: false,
+                            ..
+                        } => {
+                            handled_keys = true;
+                            picker::recent_files::enter_mode(ctx, client_handle);
+                        }
+                        Key {
+                            code: KeyCode::Char('n'),
+                            control: false,
+                            alt: false,
+                            ..
+                        } => {
+                            handled_keys = true;
+                            picker::navigation_history::enter_mode(ctx, client_handle);
+                        }
+                        Key {
+                            code: KeyCode::Char('M'),
+                            control: false,
+                            alt: false,
+                            ..
+                        } => {
+                            handled_keys = true;
+                            picker::markers::enter_mode(ctx, client_handle);
                         }
                         Key {
                             code: KeyCode::Char('b'),
@@ -2007,7 +2554,7 @@ impl ModeState for State {
         }
 
         if handled_keys {
-            let state = &mut ctx.editor.mode.normal_state;
+            let state = &mut ctx.clients.get_mut(client_handle).mode.normal_state;
             state.is_recording_auto_macro = false;
             state.count = 0;
             Some(EditorFlow::Continue)
@@ -2040,8 +2587,13 @@ impl ModeState for State {
     }
 }
 
-fn copy_text(ctx: &mut EditorContext, buffer_view_handle: BufferViewHandle, text: &mut String) {
-    let state = &mut ctx.editor.mode.normal_state;
+fn copy_text(
+    ctx: &mut EditorContext,
+    client_handle: ClientHandle,
+    buffer_view_handle: BufferViewHandle,
+    text: &mut String,
+) {
+    let state = &mut ctx.clients.get_mut(client_handle).mode.normal_state;
     let buffer_view = ctx.editor.buffer_views.get(buffer_view_handle);
     let ranges_start = state.last_copy_ranges.len();
     buffer_view.append_selection_text_and_ranges(
@@ -2056,8 +2608,13 @@ fn copy_text(ctx: &mut EditorContext, buffer_view_handle: BufferViewHandle, text
     state.movement_kind = CursorMovementKind::PositionAndAnchor;
 }
 
-fn paste_text(ctx: &mut EditorContext, buffer_view_handle: BufferViewHandle, text: &str) {
-    let state = &mut ctx.editor.mode.normal_state;
+fn paste_text(
+    ctx: &mut EditorContext,
+    client_handle: ClientHandle,
+    buffer_view_handle: BufferViewHandle,
+    text: &str,
+) {
+    let state = &mut ctx.clients.get_mut(client_handle).mode.normal_state;
     let buffer_view = ctx.editor.buffer_views.get(buffer_view_handle);
     buffer_view.delete_text_in_cursor_ranges(
         &mut ctx.editor.buffers,
@@ -2071,8 +2628,9 @@ fn paste_text(ctx: &mut EditorContext, buffer_view_handle: BufferViewHandle, tex
     ctx.trigger_event_handlers();
 
     let buffer_view = ctx.editor.buffer_views.get(buffer_view_handle);
-    let hash = ctx.editor.mode.normal_state.last_copy_hash;
-    let ranges = &ctx.editor.mode.normal_state.last_copy_ranges[..];
+    let state = &ctx.clients.get(client_handle).mode.normal_state;
+    let hash = state.last_copy_hash;
+    let ranges = &state.last_copy_ranges[..];
     let cursors = &buffer_view.cursors[..];
     if hash == hash_bytes(text.as_bytes()) && ranges.len() == cursors.len() {
         let buffer = ctx.editor.buffers.get_mut(buffer_view.buffer_handle);
@@ -2101,8 +2659,420 @@ fn paste_text(ctx: &mut EditorContext, buffer_view_handle: BufferViewHandle, tex
         .commit_edits();
 }
 
+// when every non blank line is already commented, the comments are removed instead
+fn toggle_line_comments(
+    buffer: &mut Buffer,
+    word_database: &mut WordDatabase,
+    events: &mut EditorEventQueue,
+    line_ranges: &[(BufferPositionIndex, BufferPositionIndex)],
+    delimiter: &str,
+    buf: &mut String,
+) {
+    let lines = buffer.content().lines();
+    let is_commented = line_ranges
+        .iter()
+        .flat_map(|&(from, to)| from..=to)
+        .map(|i| lines[i as usize].as_str().trim_start())
+        .filter(|l| !l.is_empty())
+        .all(|l| l.starts_with(delimiter));
+
+    buf.clear();
+    buf.push_str(delimiter);
+    buf.push(' ');
+
+    for &(from, to) in line_ranges {
+        let indentation_len = buffer.content().lines()[from as usize..=to as usize]
+            .iter()
+            .map(|l| l.as_str())
+            .filter(|l| !l.trim().is_empty())
+            .map(|l| leading_whitespace(l).len())
+            .min()
+            .unwrap_or(0);
+
+        for line_index in from..=to {
+            let line = buffer.content().lines()[line_index as usize].as_str();
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            if is_commented {
+                let indentation_len = leading_whitespace(line).len();
+                let mut comment_len = delimiter.len();
+                if line[indentation_len + comment_len..].starts_with(' ') {
+                    comment_len += 1;
+                }
+                let range = BufferRange::between(
+                    BufferPosition::line_col(line_index, indentation_len as _),
+                    BufferPosition::line_col(line_index, (indentation_len + comment_len) as _),
+                );
+                buffer.delete_range(word_database, range, events);
+            } else {
+                let position = BufferPosition::line_col(line_index, indentation_len as _);
+                buffer.insert_text(word_database, position, buf, events);
+            }
+        }
+    }
+}
+
+// wraps each line range in a block comment or unwraps it if it is already wrapped
+fn toggle_block_comments(
+    buffer: &mut Buffer,
+    word_database: &mut WordDatabase,
+    events: &mut EditorEventQueue,
+    line_ranges: &[(BufferPositionIndex, BufferPositionIndex)],
+    (begin, end): (&str, &str),
+    buf: &mut String,
+) {
+    for &(from, to) in line_ranges.iter().rev() {
+        let lines = buffer.content().lines();
+        let has_text = |&i: &BufferPositionIndex| !lines[i as usize].as_str().trim().is_empty();
+        let first_index = match (from..=to).find(has_text) {
+            Some(i) => i,
+            None => continue,
+        };
+        let last_index = (from..=to).rev().find(has_text).unwrap_or(first_index);
+
+        let first_line = lines[first_index as usize].as_str();
+        let last_line = lines[last_index as usize].as_str().trim_end();
+        let indentation_len = leading_whitespace(first_line).len();
+        let first_rest = &first_line[indentation_len..];
+
+        let is_commented = first_rest.starts_with(begin)
+            && last_line.ends_with(end)
+            && (first_index != last_index
+                || first_rest.trim_end().len() >= begin.len() + end.len());
+
+        if is_commented {
+            let mut end_from = last_line.len() - end.len();
+            let min_end_from = if first_index == last_index {
+                indentation_len + begin.len()
+            } else {
+                0
+            };
+            if end_from > min_end_from && last_line[..end_from].ends_with(' ') {
+                end_from -= 1;
+            }
+            let range = BufferRange::between(
+                BufferPosition::line_col(last_index, end_from as _),
+                BufferPosition::line_col(last_index, last_line.len() as _),
+            );
+            buffer.delete_range(word_database, range, events);
+
+            let first_line = buffer.content().lines()[first_index as usize].as_str();
+            let mut begin_len = begin.len();
+            if first_line[indentation_len + begin_len..].starts_with(' ') {
+                begin_len += 1;
+            }
+            let range = BufferRange::between(
+                BufferPosition::line_col(first_index, indentation_len as _),
+                BufferPosition::line_col(first_index, (indentation_len + begin_len) as _),
+            );
+            buffer.delete_range(word_database, range, events);
+        } else {
+            buf.clear();
+            buf.push(' ');
+            buf.push_str(end);
+            let position = BufferPosition::line_col(last_index, last_line.len() as _);
+            buffer.insert_text(word_database, position, buf, events);
+
+            buf.clear();
+            buf.push_str(begin);
+            buf.push(' ');
+            let position = BufferPosition::line_col(first_index, indentation_len as _);
+            buffer.insert_text(word_database, position, buf, events);
+        }
+    }
+}
+
+fn select_text_objects<F>(cursors: &mut [Cursor], mut find: F)
+where
+    F: FnMut(BufferPosition) -> Option<BufferRange>,
+{
+    for cursor in cursors {
+        if let Some(range) = find(cursor.position) {
+            cursor.anchor = range.from;
+            cursor.position = range.to;
+        }
+    }
+}
+
+fn select_plugin_text_objects(
+    ctx: &mut EditorContext,
+    client_handle: ClientHandle,
+    buffer_view_handle: BufferViewHandle,
+    plugin_handle: PluginHandle,
+    key: char,
+    around: bool,
+) {
+    let buffer_view = ctx.editor.buffer_views.get(buffer_view_handle);
+    let buffer_handle = buffer_view.buffer_handle;
+    let cursor_count = buffer_view.cursors[..].len();
+    let on_text_object = ctx.plugins.get(plugin_handle).on_text_object;
+
+    let mut ranges = Vec::with_capacity(cursor_count);
+    for i in 0..cursor_count {
+        let cursor_position = ctx.editor.buffer_views.get(buffer_view_handle).cursors[i].position;
+        let text_object_ctx = TextObjectContext {
+            client_handle,
+            buffer_handle,
+            key,
+            cursor_position,
+            around,
+        };
+        ranges.push(on_text_object(plugin_handle, ctx, &text_object_ctx));
+    }
+
+    let buffer_view = ctx.editor.buffer_views.get_mut(buffer_view_handle);
+    let mut cursors = buffer_view.cursors.mut_guard();
+    for (cursor, range) in cursors[..].iter_mut().zip(ranges) {
+        if let Some(range) = range {
+            cursor.anchor = range.from;
+            cursor.position = range.to;
+        }
+    }
+}
+
+fn lines_range(
+    buffer: &BufferContent,
+    from: BufferPositionIndex,
+    to: BufferPositionIndex,
+) -> BufferRange {
+    let to_len = buffer.lines()[to as usize].as_str().len();
+    BufferRange::between(
+        BufferPosition::line_col(from, 0),
+        BufferPosition::line_col(to, to_len as _),
+    )
+}
+
+// selecting around a paragraph also selects the blank lines after it (or before it if it's the last one)
+fn paragraph_text_object(
+    buffer: &BufferContent,
+    line_index: BufferPositionIndex,
+    around: bool,
+) -> BufferRange {
+    let (from, to) = buffer.find_paragraph_at(line_index);
+    if !around {
+        return lines_range(buffer, from, to);
+    }
+
+    let last_line_index = (buffer.lines().len() - 1) as BufferPositionIndex;
+    if to < last_line_index {
+        let (_, next_to) = buffer.find_paragraph_at(to + 1);
+        if next_to < last_line_index {
+            BufferRange::between(
+                BufferPosition::line_col(from, 0),
+                BufferPosition::line_col(next_to + 1, 0),
+            )
+        } else {
+            lines_range(buffer, from, next_to)
+        }
+    } else if from > 0 {
+        let (previous_from, _) = buffer.find_paragraph_at(from - 1);
+        match previous_from.checked_sub(1) {
+            Some(line_index) => {
+                let mut range = lines_range(buffer, line_index, to);
+                range.from.column_byte_index =
+                    buffer.lines()[line_index as usize].as_str().len() as _;
+                range
+            }
+            None => lines_range(buffer, previous_from, to),
+        }
+    } else {
+        lines_range(buffer, from, to)
+    }
+}
+
+// the line right after a block that closes it (like a `}`) if there's one
+fn block_closing_line(
+    buffer: &BufferContent,
+    syntax: &Syntax,
+    header_index: BufferPositionIndex,
+    to: BufferPositionIndex,
+) -> BufferPositionIndex {
+    let lines = buffer.lines();
+    let header = lines[header_index as usize].as_str();
+    match lines.get(to as usize + 1).map(|l| l.as_str()) {
+        Some(line)
+            if leading_whitespace(line).len() == leading_whitespace(header).len()
+                && syntax.decreases_indentation(line) =>
+        {
+            to + 1
+        }
+        _ => to,
+    }
+}
+
+// selecting around an indentation block also selects its header line and closing line
+fn indentation_block_text_object(
+    buffer: &BufferContent,
+    syntax: &Syntax,
+    line_index: BufferPositionIndex,
+    around: bool,
+) -> Option<BufferRange> {
+    let (from, to) = buffer.find_indentation_block_at(line_index)?;
+    if !around || from == 0 {
+        return Some(lines_range(buffer, from, to));
+    }
+
+    let header_index = from - 1;
+    let to = block_closing_line(buffer, syntax, header_index, to);
+    Some(lines_range(buffer, header_index, to))
+}
+
+// functions begin at lines matching the syntax `function` rule and span the lines indented after it
+fn function_text_object(
+    buffer: &BufferContent,
+    syntax: &Syntax,
+    line_index: BufferPositionIndex,
+    around: bool,
+) -> Option<BufferRange> {
+    let lines = buffer.lines();
+    let line_index = line_index.min((lines.len() - 1) as _);
+    for header_index in (0..=line_index).rev() {
+        let header = lines[header_index as usize].as_str();
+        if !syntax.is_function_start(header) {
+            continue;
+        }
+
+        let indentation_len = leading_whitespace(header).len();
+        let mut body_to = header_index;
+        for (i, line) in lines.iter().enumerate().skip(header_index as usize + 1) {
+            let line = line.as_str();
+            if line.trim().is_empty() {
+                continue;
+            }
+            if leading_whitespace(line).len() <= indentation_len {
+                break;
+            }
+            body_to = i as _;
+        }
+
+        let to = block_closing_line(buffer, syntax, header_index, body_to);
+        if to < line_index {
+            continue;
+        }
+
+        let range = if around {
+            lines_range(buffer, header_index, to)
+        } else if body_to > header_index {
+            lines_range(buffer, header_index + 1, body_to)
+        } else {
+            lines_range(buffer, header_index, header_index)
+        };
+        return Some(range);
+    }
+
+    None
+}
+
+// surrounds selections with the pair for `key` or, if `pair` is `Some`, replaces that surrounding pair with it
+fn surround_with_key(
+    ctx: &mut EditorContext,
+    buffer_view_handle: BufferViewHandle,
+    pair: Option<SurroundPair>,
+    key: char,
+) {
+    let (left, right) = match SurroundPair::from_key(key) {
+        SurroundPair::Balanced(left, right) => (left, right),
+        _ => (key, key),
+    };
+    let mut left_buf = [0; 4];
+    let mut right_buf = [0; 4];
+    let delimiters = (
+        &*left.encode_utf8(&mut left_buf),
+        &*right.encode_utf8(&mut right_buf),
+    );
+
+    let buffer_view = ctx.editor.buffer_views.get(buffer_view_handle);
+    match pair {
+        Some(pair) => buffer_view.change_surround(
+            &mut ctx.editor.buffers,
+            &mut ctx.editor.word_database,
+            pair,
+            Some(delimiters),
+            &mut ctx.editor.events,
+        ),
+        None => buffer_view.add_surround(
+            &mut ctx.editor.buffers,
+            &mut ctx.editor.word_database,
+            delimiters,
+            &mut ctx.editor.events,
+        ),
+    }
+    ctx.editor
+        .buffers
+        .get_mut(buffer_view.buffer_handle)
+        .commit_edits();
+}
+
+fn increment_numbers(
+    ctx: &mut EditorContext,
+    buffer_view_handle: BufferViewHandle,
+    delta: i64,
+    sequential: bool,
+) {
+    let buffer_view = ctx.editor.buffer_views.get(buffer_view_handle);
+    let mut buf = ctx.editor.string_pool.acquire();
+    buffer_view.increment_numbers(
+        &mut ctx.editor.buffers,
+        &mut ctx.editor.word_database,
+        delta,
+        sequential,
+        &mut buf,
+        &mut ctx.editor.events,
+    );
+    ctx.editor.string_pool.release(buf);
+
+    ctx.editor
+        .buffers
+        .get_mut(buffer_view.buffer_handle)
+        .commit_edits();
+}
+
+pub(crate) fn go_to_marker(ctx: &mut EditorContext, client_handle: ClientHandle, key: RegisterKey) {
+    let register = ctx.editor.registers.get(key);
+    let (path, position) = parse_path_and_position(register);
+    let path = ctx.editor.string_pool.acquire_with(path);
+    match ctx.editor.buffer_view_handle_from_path(
+        client_handle,
+        Path::new(&path),
+        BufferProperties::text(),
+        false,
+    ) {
+        Ok(handle) => {
+            let client = ctx.clients.get_mut(client_handle);
+            client.set_buffer_view_handle(Some(handle), &ctx.editor.buffer_views);
+
+            if let Some(position) = position {
+                let buffer_view = ctx.editor.buffer_views.get_mut(handle);
+                let buffer = ctx.editor.buffers.get(buffer_view.buffer_handle);
+                let position = buffer.content().saturate_position(position);
+
+                let mut cursors = buffer_view.cursors.mut_guard();
+                cursors.clear();
+                cursors.add(Cursor {
+                    anchor: position,
+                    position,
+                });
+            }
+
+            ctx.clients
+                .get_mut(client_handle)
+                .mode
+                .normal_state
+                .movement_kind = CursorMovementKind::PositionAndAnchor;
+        }
+        Err(error) => ctx
+            .editor
+            .status_bar
+            .write(MessageKind::Error)
+            .fmt(format_args!("invalid marker '{}': {}", &path, error)),
+    }
+    ctx.editor.string_pool.release(path);
+}
+
 fn find_char(ctx: &mut EditorContext, client_handle: ClientHandle, forward: bool) {
-    let state = &ctx.editor.mode.normal_state;
+    let state = &ctx.clients.get(client_handle).mode.normal_state;
     let skip;
     let ch;
     let next_ch;
@@ -2199,7 +3169,7 @@ where
         }
     }
 
-    let state = &mut ctx.editor.mode.normal_state;
+    let state = &mut ctx.clients.get_mut(client_handle).mode.normal_state;
     let mut cursors = buffer_view.cursors.mut_guard();
 
     let main_position = cursors.main_cursor().position;
@@ -2209,11 +3179,32 @@ where
     let main_cursor = cursors.main_cursor();
     main_cursor.position = search_ranges[state.search_index].from;
 
-    if let CursorMovementKind::PositionAndAnchor = ctx.editor.mode.normal_state.movement_kind {
+    if let CursorMovementKind::PositionAndAnchor = ctx
+        .clients
+        .get(client_handle)
+        .mode
+        .normal_state
+        .movement_kind
+    {
         main_cursor.anchor = main_cursor.position;
     }
 }
 
+// words are searched with word boundaries while selected text is searched literally
+fn write_search_for_text(register: &mut String, text: &str, is_word: bool) {
+    register.clear();
+    if is_word {
+        register.push_str("P/%b");
+        for c in PatternEscaper::escape(text) {
+            register.push(c);
+        }
+        register.push_str("%b");
+    } else {
+        register.push_str("F/");
+        register.push_str(text);
+    }
+}
+
 fn search_word_or_move_to_it(
     ctx: &mut EditorContext,
     client_handle: ClientHandle,
@@ -2226,7 +3217,7 @@ fn search_word_or_move_to_it(
     let buffer_view = ctx.editor.buffer_views.get_mut(handle);
     let buffer = ctx.editor.buffers.get_mut(buffer_view.buffer_handle);
 
-    let state = &mut ctx.editor.mode.normal_state;
+    let count = ctx.clients.get(client_handle).mode.normal_state.count;
 
     let main_cursor = &buffer_view.cursors.main_cursor();
     let main_position = main_cursor.position;
@@ -2256,18 +3247,7 @@ fn search_word_or_move_to_it(
         });
 
         let register = ctx.editor.registers.get_mut(SEARCH_REGISTER);
-        register.clear();
-        if valid_range {
-            register.push_str("F/");
-            register.push_str(text);
-        } else {
-            register.push_str("P/%b");
-            for c in PatternEscaper::escape(text) {
-                register.push(c);
-            }
-            register.push_str("%b");
-        }
-
+        write_search_for_text(register, text, !valid_range);
         let _ = ctx.editor.aux_pattern.compile_searcher(register);
         buffer.set_search(&ctx.editor.aux_pattern);
     } else {
@@ -2278,7 +3258,7 @@ fn search_word_or_move_to_it(
 
         let mut range_index = current_range_index;
         let mut cursors = ctx.editor.buffer_views.get_mut(handle).cursors.mut_guard();
-        for _ in 0..state.count.max(1) {
+        for _ in 0..count.max(1) {
             let i = index_selector(search_ranges.len(), range_index);
             let range = search_ranges[i];
             range_index = Ok(i);
@@ -2292,6 +3272,7 @@ fn search_word_or_move_to_it(
 
     let buffer_view = ctx.editor.buffer_views.get_mut(handle);
     let main_position = buffer_view.cursors.main_cursor().position;
+    let state = &mut ctx.clients.get_mut(client_handle).mode.normal_state;
     state.search_index = match buffer
         .search_ranges()
         .binary_search_by_key(&main_position, |r| r.from)
@@ -2300,7 +3281,77 @@ fn search_word_or_move_to_it(
         Err(i) => i,
     };
 
-    ctx.editor.mode.normal_state.movement_kind = CursorMovementKind::PositionAndAnchor;
+    state.movement_kind = CursorMovementKind::PositionAndAnchor;
+}
+
+pub(crate) fn select_all_occurrences(
+    ctx: &mut EditorContext,
+    client_handle: ClientHandle,
+    within_selections: bool,
+) {
+    let handle = match ctx.clients.get(client_handle).buffer_view_handle() {
+        Some(handle) => handle,
+        None => return,
+    };
+    let buffer_view = ctx.editor.buffer_views.get(handle);
+    let buffer = ctx.editor.buffers.get_mut(buffer_view.buffer_handle);
+
+    let main_cursor = buffer_view.cursors.main_cursor();
+    let main_position = main_cursor.position;
+    let main_range = main_cursor.to_range();
+
+    let valid_range = main_range.from.line_index == main_range.to.line_index
+        && main_range.from.column_byte_index != main_range.to.column_byte_index;
+    let text = if valid_range {
+        let line = buffer.content().lines()[main_position.line_index as usize].as_str();
+        &line[main_range.from.column_byte_index as usize..main_range.to.column_byte_index as usize]
+    } else {
+        buffer.content().word_at(main_position).text
+    };
+
+    let register = ctx.editor.registers.get_mut(SEARCH_REGISTER);
+    write_search_for_text(register, text, !valid_range);
+    let _ = ctx.editor.aux_pattern.compile_searcher(register);
+    buffer.set_search(&ctx.editor.aux_pattern);
+
+    let cursors = &buffer_view.cursors[..];
+    let ranges: Vec<_> = buffer
+        .search_ranges()
+        .iter()
+        .filter(|r| {
+            if !within_selections {
+                return true;
+            }
+            let i = cursors.partition_point(|c| c.to_range().from <= r.from);
+            i > 0 && r.to <= cursors[i - 1].to_range().to
+        })
+        .cloned()
+        .collect();
+    if ranges.is_empty() {
+        return;
+    }
+
+    NavigationHistory::save_snapshot(ctx.clients.get_mut(client_handle), &ctx.editor.buffer_views);
+
+    let mut cursors = ctx.editor.buffer_views.get_mut(handle).cursors.mut_guard();
+    cursors.clear();
+    for range in ranges {
+        cursors.add(Cursor {
+            anchor: range.from,
+            position: range.to,
+        });
+    }
+    cursors.set_main_cursor_near_position(main_position);
+
+    let state = &mut ctx.clients.get_mut(client_handle).mode.normal_state;
+    state.search_index = match buffer
+        .search_ranges()
+        .binary_search_by_key(&main_position, |r| r.from)
+    {
+        Ok(i) => i,
+        Err(i) => i,
+    };
+    state.movement_kind = CursorMovementKind::PositionAndAnchor;
 }
 
 fn move_to_lint(ctx: &mut EditorContext, client_handle: ClientHandle, forward: bool) {
@@ -2317,7 +3368,13 @@ fn move_to_lint(ctx: &mut EditorContext, client_handle: ClientHandle, forward: b
     }
 
     let main_position = buffer_view.cursors.main_cursor().position;
-    let mut count = ctx.editor.mode.normal_state.count.max(1) as usize;
+    let mut count = ctx
+        .clients
+        .get(client_handle)
+        .mode
+        .normal_state
+        .count
+        .max(1) as usize;
     let index = match lints.binary_search_by(|l| l.range.from.cmp(&main_position)) {
         Ok(i) => i,
         Err(i) => {
@@ -2347,3 +3404,232 @@ fn move_to_lint(ctx: &mut EditorContext, client_handle: ClientHandle, forward: b
         position,
     });
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::path::PathBuf;
+
+    use crate::{
+        buffer::BufferCollection, client::ClientManager, platform::Platform,
+        plugin::PluginCollection,
+    };
+
+    fn buffer_from_str<'a>(buffers: &'a mut BufferCollection, text: &str) -> &'a mut Buffer {
+        let mut word_database = WordDatabase::new();
+        let mut events = EditorEventQueue::default();
+        let buffer = buffers.add_new();
+        buffer.properties = BufferProperties::text();
+        buffer.insert_text(
+            &mut word_database,
+            BufferPosition::zero(),
+            text,
+            &mut events,
+        );
+        buffer
+    }
+
+    #[test]
+    fn line_comments_toggle() {
+        fn toggle(
+            text: &str,
+            line_ranges: &[(BufferPositionIndex, BufferPositionIndex)],
+        ) -> String {
+            let mut buffers = BufferCollection::default();
+            let buffer = buffer_from_str(&mut buffers, text);
+            let mut word_database = WordDatabase::new();
+            let mut events = EditorEventQueue::default();
+            let mut buf = String::new();
+            toggle_line_comments(
+                buffer,
+                &mut word_database,
+                &mut events,
+                line_ranges,
+                "//",
+                &mut buf,
+            );
+            buffer.content().to_string()
+        }
+
+        assert_eq!("// a", toggle("a", &[(0, 0)]));
+        assert_eq!("a", toggle("// a", &[(0, 0)]));
+        assert_eq!("a", toggle("//a", &[(0, 0)]));
+        assert_eq!(
+            "    // a\n    //     b\nc",
+            toggle("    a\n        b\nc", &[(0, 1)])
+        );
+        assert_eq!("  a\n    b", toggle("  // a\n    // b", &[(0, 1)]));
+        assert_eq!("// // a\n// b", toggle("// a\nb", &[(0, 1)]));
+        assert_eq!("// a\n\n  \n// b", toggle("a\n\n  \nb", &[(0, 3)]));
+        assert_eq!("a\n\nb", toggle("// a\n\n// b", &[(0, 2)]));
+        assert_eq!("// a\nb\n// c", toggle("a\nb\nc", &[(0, 0), (2, 2)]));
+        assert_eq!("", toggle("", &[(0, 0)]));
+    }
+
+    #[test]
+    fn block_comments_toggle() {
+        fn toggle(
+            text: &str,
+            line_ranges: &[(BufferPositionIndex, BufferPositionIndex)],
+        ) -> String {
+            let mut buffers = BufferCollection::default();
+            let buffer = buffer_from_str(&mut buffers, text);
+            let mut word_database = WordDatabase::new();
+            let mut events = EditorEventQueue::default();
+            let mut buf = String::new();
+            toggle_block_comments(
+                buffer,
+                &mut word_database,
+                &mut events,
+                line_ranges,
+                ("/*", "*/"),
+                &mut buf,
+            );
+            buffer.content().to_string()
+        }
+
+        assert_eq!("/* a */", toggle("a", &[(0, 0)]));
+        assert_eq!("a", toggle("/* a */", &[(0, 0)]));
+        assert_eq!("a", toggle("/*a*/", &[(0, 0)]));
+        assert_eq!("  /* a\n  b */", toggle("  a\n  b", &[(0, 1)]));
+        assert_eq!("  a\n  b", toggle("  /* a\n  b */", &[(0, 1)]));
+        assert_eq!("\n/* a\n\nb */\n", toggle("\na\n\nb\n", &[(0, 4)]));
+        assert_eq!("/* a */\nb\n/* c */", toggle("a\nb\nc", &[(0, 0), (2, 2)]));
+        assert_eq!("/* /* */", toggle("/*", &[(0, 0)]));
+        assert_eq!("  ", toggle("  ", &[(0, 0)]));
+    }
+
+    fn editor_context(text: &str) -> (EditorContext, ClientHandle, BufferViewHandle) {
+        let mut ctx = EditorContext {
+            editor: Editor::new(PathBuf::new()),
+            platform: Platform::default(),
+            clients: ClientManager::default(),
+            plugins: PluginCollection::default(),
+        };
+        let buffer = buffer_from_str(&mut ctx.editor.buffers, text);
+        let buffer_handle = buffer.handle();
+
+        let client_handle = ClientHandle(0);
+        ctx.clients.on_client_joined(client_handle);
+        let buffer_view_handle = ctx
+            .editor
+            .buffer_views
+            .add_new(client_handle, buffer_handle);
+        ctx.clients
+            .get_mut(client_handle)
+            .set_buffer_view_handle(Some(buffer_view_handle), &ctx.editor.buffer_views);
+
+        (ctx, client_handle, buffer_view_handle)
+    }
+
+    type CursorRange = ((u32, u32), (u32, u32));
+
+    fn set_cursors(
+        ctx: &mut EditorContext,
+        buffer_view_handle: BufferViewHandle,
+        ranges: &[CursorRange],
+        main_cursor_index: usize,
+    ) {
+        let buffer_view = ctx.editor.buffer_views.get_mut(buffer_view_handle);
+        let mut cursors = buffer_view.cursors.mut_guard();
+        cursors.clear();
+        for &((from_line, from_col), (to_line, to_col)) in ranges {
+            cursors.add(Cursor {
+                anchor: BufferPosition::line_col(from_line, from_col),
+                position: BufferPosition::line_col(to_line, to_col),
+            });
+        }
+        cursors.set_main_cursor_index(main_cursor_index);
+    }
+
+    fn cursor_ranges(
+        ctx: &EditorContext,
+        buffer_view_handle: BufferViewHandle,
+    ) -> Vec<CursorRange> {
+        let buffer_view = ctx.editor.buffer_views.get(buffer_view_handle);
+        buffer_view.cursors[..]
+            .iter()
+            .map(|c| {
+                (
+                    (c.anchor.line_index, c.anchor.column_byte_index),
+                    (c.position.line_index, c.position.column_byte_index),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn comments_toggle_without_delimiters() {
+        let (mut ctx, client_handle, buffer_view_handle) = editor_context("a");
+        let keys = match ctx.editor.buffered_keys.parse("#") {
+            Ok(keys) => keys,
+            Err(error) => panic!("{}", error),
+        };
+        Editor::execute_keys(&mut ctx, client_handle, keys);
+
+        let buffer_handle = ctx
+            .editor
+            .buffer_views
+            .get(buffer_view_handle)
+            .buffer_handle;
+        let buffer = ctx.editor.buffers.get(buffer_handle);
+        assert_eq!("a", buffer.content().to_string());
+        assert!(!ctx.editor.status_bar.is_empty());
+    }
+
+    #[test]
+    fn all_occurrences_selection() {
+        let text = "ab abc ab\nab ab\nab";
+
+        let (mut ctx, client_handle, buffer_view_handle) = editor_context(text);
+        set_cursors(&mut ctx, buffer_view_handle, &[((1, 1), (1, 1))], 0);
+        select_all_occurrences(&mut ctx, client_handle, false);
+        assert_eq!(
+            vec![
+                ((0, 0), (0, 2)),
+                ((0, 7), (0, 9)),
+                ((1, 0), (1, 2)),
+                ((1, 3), (1, 5)),
+                ((2, 0), (2, 2)),
+            ],
+            cursor_ranges(&ctx, buffer_view_handle)
+        );
+        let cursors = &ctx.editor.buffer_views.get(buffer_view_handle).cursors;
+        assert_eq!(2, cursors.main_cursor_index());
+
+        let (mut ctx, client_handle, buffer_view_handle) = editor_context(text);
+        set_cursors(
+            &mut ctx,
+            buffer_view_handle,
+            &[((0, 1), (0, 1)), ((1, 0), (1, 5))],
+            0,
+        );
+        select_all_occurrences(&mut ctx, client_handle, true);
+        assert_eq!(
+            vec![((1, 0), (1, 2)), ((1, 3), (1, 5))],
+            cursor_ranges(&ctx, buffer_view_handle)
+        );
+
+        let (mut ctx, client_handle, buffer_view_handle) = editor_context(text);
+        set_cursors(
+            &mut ctx,
+            buffer_view_handle,
+            &[((0, 0), (0, 2)), ((1, 0), (1, 5))],
+            0,
+        );
+        select_all_occurrences(&mut ctx, client_handle, true);
+        assert_eq!(
+            vec![((0, 0), (0, 2)), ((1, 0), (1, 2)), ((1, 3), (1, 5))],
+            cursor_ranges(&ctx, buffer_view_handle)
+        );
+
+        let (mut ctx, client_handle, buffer_view_handle) = editor_context("ab\n\nab");
+        set_cursors(&mut ctx, buffer_view_handle, &[((1, 0), (1, 0))], 0);
+        select_all_occurrences(&mut ctx, client_handle, false);
+        assert_eq!(
+            vec![((1, 0), (1, 0))],
+            cursor_ranges(&ctx, buffer_view_handle)
+        );
+    }
+}
@@ -1,10 +1,15 @@
+use std::{fmt, fs::File, io};
+
 use crate::{
-    buffer::BufferProperties,
+    buffer::{BufferContent, BufferProperties},
+    buffer_position::BufferPosition,
     client::ClientHandle,
     command::CommandManager,
     editor::{Editor, EditorContext, EditorFlow, KeysIterator},
-    editor_utils::{MessageKind, ReadLinePoll},
+    editor_utils::{parse_marker, MessageKind, ReadLinePoll, RegisterKey},
     mode::{ModeKind, ModeState},
+    navigation_history::NavigationHistory,
+    picker::Picker,
     platform::{Key, KeyCode},
     word_database::WordIndicesIter,
 };
@@ -16,6 +21,7 @@ pub struct State {
         &mut KeysIterator,
         ReadLinePoll,
     ) -> Option<EditorFlow>,
+    pub allow_marking: bool,
     continuation: String,
 }
 
@@ -23,20 +29,24 @@ impl Default for State {
     fn default() -> Self {
         Self {
             on_client_keys: |_, _, _, _| Some(EditorFlow::Continue),
+            allow_marking: true,
             continuation: String::new(),
         }
     }
 }
 
 impl ModeState for State {
-    fn on_enter(editor: &mut Editor) {
-        editor.read_line.input_mut().clear();
+    fn on_enter(ctx: &mut EditorContext, client_handle: ClientHandle) {
+        let client = ctx.clients.get_mut(client_handle);
+        client.read_line.input_mut().clear();
     }
 
-    fn on_exit(editor: &mut Editor) {
-        editor.mode.plugin_handle = None;
-        editor.read_line.input_mut().clear();
-        editor.picker.clear();
+    fn on_exit(ctx: &mut EditorContext, client_handle: ClientHandle) {
+        let client = ctx.clients.get_mut(client_handle);
+        client.mode.plugin_handle = None;
+        client.mode.picker_state.allow_marking = true;
+        client.read_line.input_mut().clear();
+        client.picker.clear();
     }
 
     fn on_keys(
@@ -44,8 +54,9 @@ impl ModeState for State {
         client_handle: ClientHandle,
         keys: &mut KeysIterator,
     ) -> Option<EditorFlow> {
-        let this = &mut ctx.editor.mode.picker_state;
-        let poll = ctx.editor.read_line.poll(
+        let client = ctx.clients.get_mut(client_handle);
+        let this = &mut client.mode.picker_state;
+        let poll = client.read_line.poll(
             &mut ctx.platform,
             &mut ctx.editor.string_pool,
             &ctx.editor.buffered_keys,
@@ -66,7 +77,7 @@ impl ModeState for State {
                     control: false,
                     alt: false,
                 } => {
-                    ctx.editor.picker.move_cursor(1);
+                    client.picker.move_cursor(1);
                 }
                 Key {
                     code: KeyCode::Char('p'),
@@ -80,7 +91,7 @@ impl ModeState for State {
                     control: false,
                     alt: false,
                 } => {
-                    ctx.editor.picker.move_cursor(-1);
+                    client.picker.move_cursor(-1);
                 }
                 Key {
                     code: KeyCode::Char('j'),
@@ -94,13 +105,12 @@ impl ModeState for State {
                     control: false,
                     alt: false,
                 } => {
-                    let picker_height = ctx
-                        .editor
+                    let picker_height = client
                         .picker
                         .len()
                         .min(ctx.editor.config.picker_max_height as _)
                         as isize;
-                    ctx.editor.picker.move_cursor(picker_height / 2);
+                    client.picker.move_cursor(picker_height / 2);
                 }
                 Key {
                     code: KeyCode::Char('k'),
@@ -114,13 +124,12 @@ impl ModeState for State {
                     control: false,
                     alt: false,
                 } => {
-                    let picker_height = ctx
-                        .editor
+                    let picker_height = client
                         .picker
                         .len()
                         .min(ctx.editor.config.picker_max_height as _)
                         as isize;
-                    ctx.editor.picker.move_cursor(-picker_height / 2);
+                    client.picker.move_cursor(-picker_height / 2);
                 }
                 Key {
                     code: KeyCode::Char('b'),
@@ -134,8 +143,8 @@ impl ModeState for State {
                     control: false,
                     alt: false,
                 } => {
-                    let cursor = ctx.editor.picker.cursor().unwrap_or(0) as isize;
-                    ctx.editor.picker.move_cursor(-cursor);
+                    let cursor = client.picker.cursor().unwrap_or(0) as isize;
+                    client.picker.move_cursor(-cursor);
                 }
                 Key {
                     code: KeyCode::Char('e'),
@@ -149,15 +158,32 @@ impl ModeState for State {
                     control: false,
                     alt: false,
                 } => {
-                    let cursor = ctx.editor.picker.cursor().unwrap_or(0) as isize;
-                    let entry_count = ctx.editor.picker.len() as isize;
-                    ctx.editor.picker.move_cursor(entry_count - cursor - 1);
+                    let cursor = client.picker.cursor().unwrap_or(0) as isize;
+                    let entry_count = client.picker.len() as isize;
+                    client.picker.move_cursor(entry_count - cursor - 1);
+                }
+                Key {
+                    code: KeyCode::Char('s'),
+                    shift: false,
+                    control: true,
+                    alt: false,
+                } if this.allow_marking => {
+                    client.picker.toggle_mark_current_entry();
+                    client.picker.move_cursor(1);
+                }
+                Key {
+                    code: KeyCode::Char('a'),
+                    shift: false,
+                    control: true,
+                    alt: false,
+                } if this.allow_marking => {
+                    client.picker.toggle_mark_filtered_entries();
                 }
                 _ => {
-                    ctx.editor
+                    client
                         .picker
-                        .filter(WordIndicesIter::empty(), ctx.editor.read_line.input());
-                    ctx.editor.picker.move_cursor(0);
+                        .filter(WordIndicesIter::empty(), client.read_line.input());
+                    client.picker.move_cursor(0);
                 }
             }
         }
@@ -172,7 +198,131 @@ pub mod opened_buffers {
 
     use std::path::Path;
 
-    pub fn enter_mode(ctx: &mut EditorContext) {
+    pub(super) fn on_client_keys(
+        ctx: &mut EditorContext,
+        client_handle: ClientHandle,
+        _: &mut KeysIterator,
+        poll: ReadLinePoll,
+    ) -> Option<EditorFlow> {
+        match poll {
+            ReadLinePoll::Pending => return Some(EditorFlow::Continue),
+            ReadLinePoll::Submitted => (),
+            ReadLinePoll::Canceled => {
+                Editor::enter_mode(ctx, client_handle, ModeKind::default());
+                return Some(EditorFlow::Continue);
+            }
+        }
+
+        let marked_len = ctx.clients.get(client_handle).picker.marked_len();
+        for i in 0..marked_len.max(1) {
+            let client = ctx.clients.get_mut(client_handle);
+            if marked_len > 0 {
+                client.picker.set_marked_cursor(Some(i));
+            }
+
+            let path = match client.picker.current_entry(&ctx.editor.word_database) {
+                Some((_, entry)) => entry,
+                None => break,
+            };
+
+            let path = ctx.editor.string_pool.acquire_with(path);
+            if let Ok(buffer_view_handle) = ctx.editor.buffer_view_handle_from_path(
+                client_handle,
+                Path::new(&path),
+                BufferProperties::text(),
+                false,
+            ) {
+                let client = ctx.clients.get_mut(client_handle);
+                client.set_buffer_view_handle(Some(buffer_view_handle), &ctx.editor.buffer_views);
+            }
+            ctx.editor.string_pool.release(path);
+        }
+
+        Editor::enter_mode(ctx, client_handle, ModeKind::default());
+        Some(EditorFlow::Continue)
+    }
+
+    pub fn enter_mode(ctx: &mut EditorContext, client_handle: ClientHandle) {
+        let client = ctx.clients.get_mut(client_handle);
+        client.read_line.set_prompt("buffer:");
+        client.picker.clear();
+
+        for path in ctx.editor.buffers.iter().filter_map(|b| b.path.to_str()) {
+            client.picker.add_custom_entry(path);
+        }
+
+        client.picker.filter(WordIndicesIter::empty(), "");
+        client.picker.move_cursor(0);
+
+        if client.picker.len() > 0 {
+            client.mode.picker_state.on_client_keys = on_client_keys;
+            Editor::enter_mode(ctx, client_handle, ModeKind::Picker);
+        } else {
+            ctx.editor
+                .status_bar
+                .write(MessageKind::Error)
+                .str("no buffer opened");
+        }
+    }
+}
+
+pub mod recent_files {
+    use super::*;
+
+    pub fn enter_mode(ctx: &mut EditorContext, client_handle: ClientHandle) {
+        let client = ctx.clients.get_mut(client_handle);
+        client.read_line.set_prompt("recent:");
+        client.picker.clear();
+
+        for path in ctx.editor.recent_files.entries().rev() {
+            client.picker.add_custom_entry(path);
+        }
+
+        client.picker.filter(WordIndicesIter::empty(), "");
+        client.picker.move_cursor(0);
+
+        if client.picker.len() > 0 {
+            client.mode.picker_state.on_client_keys = opened_buffers::on_client_keys;
+            Editor::enter_mode(ctx, client_handle, ModeKind::Picker);
+        } else {
+            ctx.editor
+                .status_bar
+                .write(MessageKind::Error)
+                .str("no recent file");
+        }
+    }
+}
+
+fn add_location_entry(
+    picker: &mut Picker,
+    label: fmt::Arguments,
+    path: &str,
+    position: BufferPosition,
+    content: Option<&BufferContent>,
+) {
+    let line_index = position.line_index as usize;
+    let text = content
+        .and_then(|c| c.lines().get(line_index))
+        .map(|l| l.as_str().trim())
+        .unwrap_or("");
+    picker.add_custom_entry_fmt(format_args!(
+        "{} {}:{},{}  {}",
+        label,
+        path,
+        position.line_index + 1,
+        position.column_byte_index + 1,
+        text,
+    ));
+}
+
+fn parse_entry_label(entry: &str) -> &str {
+    entry.split(' ').next().unwrap_or("")
+}
+
+pub mod navigation_history {
+    use super::*;
+
+    pub fn enter_mode(ctx: &mut EditorContext, client_handle: ClientHandle) {
         fn on_client_keys(
             ctx: &mut EditorContext,
             client_handle: ClientHandle,
@@ -183,53 +333,143 @@ pub mod opened_buffers {
                 ReadLinePoll::Pending => return Some(EditorFlow::Continue),
                 ReadLinePoll::Submitted => (),
                 ReadLinePoll::Canceled => {
-                    ctx.editor.enter_mode(ModeKind::default());
+                    Editor::enter_mode(ctx, client_handle, ModeKind::default());
                     return Some(EditorFlow::Continue);
                 }
             }
 
-            let path = match ctx.editor.picker.current_entry(&ctx.editor.word_database) {
-                Some((_, entry)) => entry,
-                _ => {
-                    ctx.editor.enter_mode(ModeKind::default());
-                    return Some(EditorFlow::Continue);
-                }
+            let picker = &ctx.clients.get(client_handle).picker;
+            let index = match picker.current_entry(&ctx.editor.word_database) {
+                Some((_, entry)) => parse_entry_label(entry).parse().ok(),
+                None => None,
             };
-
-            let path = ctx.editor.string_pool.acquire_with(path);
-            if let Ok(buffer_view_handle) = ctx.editor.buffer_view_handle_from_path(
-                client_handle,
-                Path::new(&path),
-                BufferProperties::text(),
-                false,
-            ) {
+            Editor::enter_mode(ctx, client_handle, ModeKind::default());
+            if let Some(index) = index {
                 let client = ctx.clients.get_mut(client_handle);
-                client.set_buffer_view_handle(Some(buffer_view_handle), &ctx.editor.buffer_views);
+                NavigationHistory::move_to_snapshot(client, &mut ctx.editor, index);
             }
-            ctx.editor.string_pool.release(path);
+            Some(EditorFlow::Continue)
+        }
+
+        let client = ctx.clients.get_mut(client_handle);
+        client.read_line.set_prompt("navigation:");
+        client.picker.clear();
+
+        let navigation_history = &client.navigation_history;
+        for index in (0..navigation_history.len()).rev() {
+            if let Some((buffer_handle, position)) = navigation_history.snapshot(index) {
+                let buffer = ctx.editor.buffers.get(buffer_handle);
+                add_location_entry(
+                    &mut client.picker,
+                    format_args!("{}", index),
+                    buffer.path.to_str().unwrap_or(""),
+                    position,
+                    Some(buffer.content()),
+                );
+            }
+        }
+
+        client.picker.filter(WordIndicesIter::empty(), "");
+        client.picker.move_cursor(0);
+
+        if client.picker.len() > 0 {
+            client.mode.picker_state.on_client_keys = on_client_keys;
+            client.mode.picker_state.allow_marking = false;
+            Editor::enter_mode(ctx, client_handle, ModeKind::Picker);
+        } else {
+            ctx.editor
+                .status_bar
+                .write(MessageKind::Error)
+                .str("navigation history is empty");
+        }
+    }
+}
 
-            ctx.editor.enter_mode(ModeKind::default());
+pub mod markers {
+    use super::*;
+
+    use std::path::Path;
+
+    use crate::mode::normal;
+
+    pub fn enter_mode(ctx: &mut EditorContext, client_handle: ClientHandle) {
+        fn on_client_keys(
+            ctx: &mut EditorContext,
+            client_handle: ClientHandle,
+            _: &mut KeysIterator,
+            poll: ReadLinePoll,
+        ) -> Option<EditorFlow> {
+            match poll {
+                ReadLinePoll::Pending => return Some(EditorFlow::Continue),
+                ReadLinePoll::Submitted => (),
+                ReadLinePoll::Canceled => {
+                    Editor::enter_mode(ctx, client_handle, ModeKind::default());
+                    return Some(EditorFlow::Continue);
+                }
+            }
+
+            let picker = &ctx.clients.get(client_handle).picker;
+            let key = match picker.current_entry(&ctx.editor.word_database) {
+                Some((_, entry)) => RegisterKey::from_str(parse_entry_label(entry)),
+                None => None,
+            };
+            Editor::enter_mode(ctx, client_handle, ModeKind::default());
+            if let Some(key) = key {
+                normal::go_to_marker(ctx, client_handle, key);
+            }
             Some(EditorFlow::Continue)
         }
 
-        ctx.editor.read_line.set_prompt("buffer:");
-        ctx.editor.picker.clear();
+        let client = ctx.clients.get_mut(client_handle);
+        client.read_line.set_prompt("marker:");
+        client.picker.clear();
 
-        for path in ctx.editor.buffers.iter().filter_map(|b| b.path.to_str()) {
-            ctx.editor.picker.add_custom_entry(path);
+        let mut file_content = BufferContent::new();
+        for c in 'a'..='z' {
+            let key = match RegisterKey::from_char(c) {
+                Some(key) => key,
+                None => continue,
+            };
+            let (path, position) = match parse_marker(ctx.editor.registers.get(key)) {
+                Some(marker) => marker,
+                None => continue,
+            };
+
+            let content = match ctx
+                .editor
+                .buffers
+                .find_with_path(&ctx.editor.current_directory, Path::new(path))
+            {
+                Some(handle) => Some(ctx.editor.buffers.get(handle).content()),
+                None => match File::open(ctx.editor.current_directory.join(path)) {
+                    Ok(file) => match file_content.read(&mut io::BufReader::new(file)) {
+                        Ok(()) => Some(&file_content),
+                        Err(_) => None,
+                    },
+                    Err(_) => None,
+                },
+            };
+            add_location_entry(
+                &mut client.picker,
+                format_args!("{}", c),
+                path,
+                position,
+                content,
+            );
         }
 
-        ctx.editor.picker.filter(WordIndicesIter::empty(), "");
-        ctx.editor.picker.move_cursor(0);
+        client.picker.filter(WordIndicesIter::empty(), "");
+        client.picker.move_cursor(0);
 
-        if ctx.editor.picker.len() > 0 {
-            ctx.editor.mode.picker_state.on_client_keys = on_client_keys;
-            ctx.editor.enter_mode(ModeKind::Picker);
+        if client.picker.len() > 0 {
+            client.mode.picker_state.on_client_keys = on_client_keys;
+            client.mode.picker_state.allow_marking = false;
+            Editor::enter_mode(ctx, client_handle, ModeKind::Picker);
         } else {
             ctx.editor
                 .status_bar
                 .write(MessageKind::Error)
-                .str("no buffer opened");
+                .str("no marker saved");
         }
     }
 }
@@ -237,7 +477,12 @@ pub mod opened_buffers {
 pub mod custom {
     use super::*;
 
-    pub fn enter_mode(ctx: &mut EditorContext, continuation: &str, prompt: &str) {
+    pub fn enter_mode(
+        ctx: &mut EditorContext,
+        client_handle: ClientHandle,
+        continuation: &str,
+        prompt: &str,
+    ) {
         fn on_client_keys(
             ctx: &mut EditorContext,
             client_handle: ClientHandle,
@@ -247,34 +492,52 @@ pub mod custom {
             match poll {
                 ReadLinePoll::Pending => (),
                 ReadLinePoll::Submitted => {
-                    if ctx.editor.picker.cursor().is_none() {
-                        ctx.editor.enter_mode(ModeKind::default());
+                    let client = ctx.clients.get(client_handle);
+                    let marked_len = client.picker.marked_len();
+                    if marked_len == 0 && client.picker.cursor().is_none() {
+                        Editor::enter_mode(ctx, client_handle, ModeKind::default());
                         return Some(EditorFlow::Continue);
                     }
 
-                    let continuation = &ctx.editor.mode.picker_state.continuation;
+                    let continuation = &client.mode.picker_state.continuation;
                     let continuation = ctx.editor.string_pool.acquire_with(continuation);
-                    let result = CommandManager::eval(ctx, Some(client_handle), &continuation);
-                    let flow = CommandManager::unwrap_eval_result(
-                        ctx,
-                        result,
-                        &continuation,
-                        Some("picker-continuation"),
-                    );
+                    let mut flow = EditorFlow::Continue;
+                    for i in 0..marked_len.max(1) {
+                        if marked_len > 0 {
+                            let client = ctx.clients.get_mut(client_handle);
+                            client.picker.set_marked_cursor(Some(i));
+                        }
+
+                        let result = CommandManager::eval(ctx, Some(client_handle), &continuation);
+                        flow = CommandManager::unwrap_eval_result(
+                            ctx,
+                            result,
+                            &continuation,
+                            Some("picker-continuation"),
+                        );
+                        if !matches!(flow, EditorFlow::Continue)
+                            || ctx.clients.get(client_handle).picker.marked_len() != marked_len
+                        {
+                            break;
+                        }
+                    }
                     ctx.editor.string_pool.release(continuation);
-                    ctx.editor.enter_mode(ModeKind::default());
+                    Editor::enter_mode(ctx, client_handle, ModeKind::default());
                     return Some(flow);
                 }
-                ReadLinePoll::Canceled => ctx.editor.enter_mode(ModeKind::default()),
+                ReadLinePoll::Canceled => {
+                    Editor::enter_mode(ctx, client_handle, ModeKind::default())
+                }
             }
             Some(EditorFlow::Continue)
         }
 
-        ctx.editor.read_line.set_prompt(prompt);
-        let state = &mut ctx.editor.mode.picker_state;
+        let client = ctx.clients.get_mut(client_handle);
+        client.read_line.set_prompt(prompt);
+        let state = &mut client.mode.picker_state;
         state.on_client_keys = on_client_keys;
         state.continuation.clear();
         state.continuation.push_str(continuation);
-        ctx.editor.enter_mode(ModeKind::Picker);
+        Editor::enter_mode(ctx, client_handle, ModeKind::Picker);
     }
 }
@@ -1,4 +1,5 @@
 use crate::{
+    buffer::SurroundPair,
     buffer_position::BufferPositionIndex,
     buffer_view::CursorMovementKind,
     client::ClientHandle,
@@ -17,7 +18,9 @@ pub struct State {
     previous_cursors: Vec<Cursor>,
     previous_main_cursor_index: usize,
     movement_kind: CursorMovementKind,
+    history_index: usize,
     continuation: String,
+    surround_pair: Option<SurroundPair>,
 }
 
 impl Default for State {
@@ -27,19 +30,23 @@ impl Default for State {
             previous_cursors: Vec::new(),
             previous_main_cursor_index: 0,
             movement_kind: CursorMovementKind::PositionAndAnchor,
+            history_index: 0,
             continuation: String::new(),
+            surround_pair: None,
         }
     }
 }
 
 impl ModeState for State {
-    fn on_enter(editor: &mut Editor) {
-        editor.read_line.input_mut().clear();
+    fn on_enter(ctx: &mut EditorContext, client_handle: ClientHandle) {
+        let client = ctx.clients.get_mut(client_handle);
+        client.read_line.input_mut().clear();
     }
 
-    fn on_exit(editor: &mut Editor) {
-        editor.mode.plugin_handle = None;
-        editor.read_line.input_mut().clear();
+    fn on_exit(ctx: &mut EditorContext, client_handle: ClientHandle) {
+        let client = ctx.clients.get_mut(client_handle);
+        client.mode.plugin_handle = None;
+        client.read_line.input_mut().clear();
     }
 
     fn on_keys(
@@ -47,13 +54,14 @@ impl ModeState for State {
         client_handle: ClientHandle,
         keys: &mut KeysIterator,
     ) -> Option<EditorFlow> {
-        let poll = ctx.editor.read_line.poll(
+        let client = ctx.clients.get_mut(client_handle);
+        let poll = client.read_line.poll(
             &mut ctx.platform,
             &mut ctx.editor.string_pool,
             &ctx.editor.buffered_keys,
             keys,
         );
-        let f = ctx.editor.mode.read_line_state.on_client_keys;
+        let f = client.mode.read_line_state.on_client_keys;
         f(ctx, client_handle, keys, poll)
     }
 }
@@ -61,7 +69,10 @@ impl ModeState for State {
 pub mod search {
     use super::*;
 
-    use crate::editor_utils::SEARCH_REGISTER;
+    use crate::{
+        editor_utils::SEARCH_REGISTER,
+        platform::{Key, KeyCode},
+    };
 
     pub fn enter_mode(
         ctx: &mut EditorContext,
@@ -71,11 +82,42 @@ pub mod search {
         fn on_client_keys(
             ctx: &mut EditorContext,
             client_handle: ClientHandle,
-            _: &mut KeysIterator,
+            keys: &mut KeysIterator,
             poll: ReadLinePoll,
         ) -> Option<EditorFlow> {
             match poll {
                 ReadLinePoll::Pending => {
+                    keys.index = keys.index.saturating_sub(1);
+                    let client = ctx.clients.get_mut(client_handle);
+                    let state = &mut client.mode.read_line_state;
+                    let history_len = ctx.editor.search_history.len();
+                    match keys.next(&ctx.editor.buffered_keys) {
+                        Key {
+                            code: KeyCode::Char('n' | 'j'),
+                            shift: false,
+                            control: true,
+                            alt: false,
+                        } => {
+                            state.history_index = history_len.min(state.history_index + 1);
+                            let entry = ctx.editor.search_history.entry(state.history_index);
+                            let input = client.read_line.input_mut();
+                            input.clear();
+                            input.push_str(entry);
+                        }
+                        Key {
+                            code: KeyCode::Char('p' | 'k'),
+                            shift: false,
+                            control: true,
+                            alt: false,
+                        } => {
+                            state.history_index = state.history_index.saturating_sub(1);
+                            let entry = ctx.editor.search_history.entry(state.history_index);
+                            let input = client.read_line.input_mut();
+                            input.clear();
+                            input.push_str(entry);
+                        }
+                        _ => (),
+                    }
                     update_search(ctx, client_handle);
                 }
                 ReadLinePoll::Submitted => {
@@ -88,7 +130,7 @@ pub mod search {
                         if search_ranges.is_empty() {
                             restore_saved_position(ctx, client_handle);
                         } else {
-                            let state = &ctx.editor.mode.read_line_state;
+                            let state = &client.mode.read_line_state;
                             let cursor_position = buffer_view.cursors.main_cursor().position;
 
                             {
@@ -118,7 +160,7 @@ pub mod search {
                                 });
                             }
 
-                            ctx.editor.mode.normal_state.search_index = match search_ranges
+                            client.mode.normal_state.search_index = match search_ranges
                                 .binary_search_by_key(&cursor_position, |r| r.from)
                             {
                                 Ok(i) => i,
@@ -127,14 +169,16 @@ pub mod search {
                         }
                     }
 
+                    let input = ctx.clients.get(client_handle).read_line.input();
+                    ctx.editor.search_history.add(input);
                     let register = ctx.editor.registers.get_mut(SEARCH_REGISTER);
                     register.clear();
-                    register.push_str(ctx.editor.read_line.input());
-                    ctx.editor.enter_mode(ModeKind::default());
+                    register.push_str(input);
+                    Editor::enter_mode(ctx, client_handle, ModeKind::default());
                 }
                 ReadLinePoll::Canceled => {
                     restore_saved_position(ctx, client_handle);
-                    ctx.editor.enter_mode(ModeKind::default());
+                    Editor::enter_mode(ctx, client_handle, ModeKind::default());
                 }
             }
 
@@ -142,16 +186,20 @@ pub mod search {
         }
 
         save_current_position(ctx, client_handle);
-        ctx.editor.read_line.set_prompt("search:");
+        let client = ctx.clients.get_mut(client_handle);
+        client.read_line.set_prompt("search:");
+        let state = &mut client.mode.read_line_state;
+        state.movement_kind = movement_kind;
+        state.history_index = ctx.editor.search_history.len();
+        state.on_client_keys = on_client_keys;
         update_search(ctx, client_handle);
 
-        ctx.editor.mode.read_line_state.movement_kind = movement_kind;
-        ctx.editor.mode.read_line_state.on_client_keys = on_client_keys;
-        ctx.editor.enter_mode(ModeKind::ReadLine);
+        Editor::enter_mode(ctx, client_handle, ModeKind::ReadLine);
     }
 
     fn update_search(ctx: &mut EditorContext, client_handle: ClientHandle) {
-        let handle = match ctx.clients.get_mut(client_handle).buffer_view_handle() {
+        let client = ctx.clients.get(client_handle);
+        let handle = match client.buffer_view_handle() {
             Some(handle) => handle,
             None => return,
         };
@@ -161,7 +209,7 @@ pub mod search {
         let _ = ctx
             .editor
             .aux_pattern
-            .compile_searcher(&ctx.editor.read_line.input());
+            .compile_searcher(client.read_line.input());
         buffer.set_search(&ctx.editor.aux_pattern);
         let search_ranges = buffer.search_ranges();
 
@@ -191,7 +239,7 @@ pub mod search {
             }
         }
 
-        if let CursorMovementKind::PositionAndAnchor = ctx.editor.mode.normal_state.movement_kind {
+        if let CursorMovementKind::PositionAndAnchor = client.mode.normal_state.movement_kind {
             main_cursor.anchor = main_cursor.position;
         }
     }
@@ -207,9 +255,9 @@ fn on_submitted(
         ReadLinePoll::Pending => (),
         ReadLinePoll::Submitted => {
             proc(ctx, client_handle);
-            ctx.editor.enter_mode(ModeKind::default());
+            Editor::enter_mode(ctx, client_handle, ModeKind::default());
         }
-        ReadLinePoll::Canceled => ctx.editor.enter_mode(ModeKind::default()),
+        ReadLinePoll::Canceled => Editor::enter_mode(ctx, client_handle, ModeKind::default()),
     }
 }
 
@@ -221,26 +269,28 @@ pub mod filter_cursors {
         editor_utils::SEARCH_REGISTER,
     };
 
-    pub fn enter_filter_mode(ctx: &mut EditorContext) {
-        ctx.editor.read_line.set_prompt("filter:");
-        ctx.editor.mode.read_line_state.on_client_keys = |ctx, client_handle, _, poll| {
+    pub fn enter_filter_mode(ctx: &mut EditorContext, client_handle: ClientHandle) {
+        let client = ctx.clients.get_mut(client_handle);
+        client.read_line.set_prompt("filter:");
+        client.mode.read_line_state.on_client_keys = |ctx, client_handle, _, poll| {
             on_submitted(ctx, client_handle, poll, |ctx, client_handle| {
                 on_event_impl(ctx, client_handle, true);
             });
             Some(EditorFlow::Continue)
         };
-        ctx.editor.enter_mode(ModeKind::ReadLine);
+        Editor::enter_mode(ctx, client_handle, ModeKind::ReadLine);
     }
 
-    pub fn enter_except_mode(ctx: &mut EditorContext) {
-        ctx.editor.read_line.set_prompt("except:");
-        ctx.editor.mode.read_line_state.on_client_keys = |ctx, client_handle, _, poll| {
+    pub fn enter_except_mode(ctx: &mut EditorContext, client_handle: ClientHandle) {
+        let client = ctx.clients.get_mut(client_handle);
+        client.read_line.set_prompt("except:");
+        client.mode.read_line_state.on_client_keys = |ctx, client_handle, _, poll| {
             on_submitted(ctx, client_handle, poll, |ctx, client_handle| {
                 on_event_impl(ctx, client_handle, false);
             });
             Some(EditorFlow::Continue)
         };
-        ctx.editor.enter_mode(ModeKind::ReadLine);
+        Editor::enter_mode(ctx, client_handle, ModeKind::ReadLine);
     }
 
     fn on_event_impl(
@@ -282,7 +332,7 @@ pub mod filter_cursors {
             }
         }
 
-        let pattern = ctx.editor.read_line.input();
+        let pattern = ctx.clients.get(client_handle).read_line.input();
         let pattern = if pattern.is_empty() {
             ctx.editor.registers.get(SEARCH_REGISTER)
         } else {
@@ -336,7 +386,7 @@ pub mod split_cursors {
 
     use crate::{buffer_position::BufferPosition, cursor::Cursor, editor_utils::SEARCH_REGISTER};
 
-    pub fn enter_by_pattern_mode(ctx: &mut EditorContext) {
+    pub fn enter_by_pattern_mode(ctx: &mut EditorContext, client_handle: ClientHandle) {
         fn add_matches(
             cursors: &mut CursorCollectionMutGuard,
             line: &str,
@@ -354,17 +404,18 @@ pub mod split_cursors {
             }
         }
 
-        ctx.editor.read_line.set_prompt("split-by:");
-        ctx.editor.mode.read_line_state.on_client_keys = |ctx, client_handle, _, poll| {
+        let client = ctx.clients.get_mut(client_handle);
+        client.read_line.set_prompt("split-by:");
+        client.mode.read_line_state.on_client_keys = |ctx, client_handle, _, poll| {
             on_submitted(ctx, client_handle, poll, |ctx, client_handle| {
                 on_event_impl(ctx, client_handle, add_matches);
             });
             Some(EditorFlow::Continue)
         };
-        ctx.editor.enter_mode(ModeKind::ReadLine);
+        Editor::enter_mode(ctx, client_handle, ModeKind::ReadLine);
     }
 
-    pub fn enter_by_separators_mode(ctx: &mut EditorContext) {
+    pub fn enter_by_separators_mode(ctx: &mut EditorContext, client_handle: ClientHandle) {
         fn add_matches(
             cursors: &mut CursorCollectionMutGuard,
             line: &str,
@@ -400,14 +451,15 @@ pub mod split_cursors {
             }
         }
 
-        ctx.editor.read_line.set_prompt("split-on:");
-        ctx.editor.mode.read_line_state.on_client_keys = |ctx, client_handle, _, poll| {
+        let client = ctx.clients.get_mut(client_handle);
+        client.read_line.set_prompt("split-on:");
+        client.mode.read_line_state.on_client_keys = |ctx, client_handle, _, poll| {
             on_submitted(ctx, client_handle, poll, |ctx, client_handle| {
                 on_event_impl(ctx, client_handle, add_matches);
             });
             Some(EditorFlow::Continue)
         };
-        ctx.editor.enter_mode(ModeKind::ReadLine);
+        Editor::enter_mode(ctx, client_handle, ModeKind::ReadLine);
     }
 
     fn on_event_impl(
@@ -415,7 +467,7 @@ pub mod split_cursors {
         client_handle: ClientHandle,
         add_matches: fn(&mut CursorCollectionMutGuard, &str, &Pattern, BufferPosition),
     ) {
-        let pattern = ctx.editor.read_line.input();
+        let pattern = ctx.clients.get(client_handle).read_line.input();
         let pattern = if pattern.is_empty() {
             ctx.editor.registers.get(SEARCH_REGISTER)
         } else {
@@ -523,7 +575,8 @@ pub mod goto {
         ) -> Option<EditorFlow> {
             match poll {
                 ReadLinePoll::Pending => {
-                    let line_number: usize = match ctx.editor.read_line.input().parse() {
+                    let input = ctx.clients.get(client_handle).read_line.input();
+                    let line_number: usize = match input.parse() {
                         Ok(number) => number,
                         Err(_) => return Some(EditorFlow::Continue),
                     };
@@ -548,26 +601,34 @@ pub mod goto {
                         position,
                     });
                 }
-                ReadLinePoll::Submitted => ctx.editor.enter_mode(ModeKind::default()),
+                ReadLinePoll::Submitted => {
+                    Editor::enter_mode(ctx, client_handle, ModeKind::default())
+                }
                 ReadLinePoll::Canceled => {
                     restore_saved_position(ctx, client_handle);
-                    ctx.editor.enter_mode(ModeKind::default());
+                    Editor::enter_mode(ctx, client_handle, ModeKind::default());
                 }
             }
             Some(EditorFlow::Continue)
         }
 
         save_current_position(ctx, client_handle);
-        ctx.editor.read_line.set_prompt("goto-line:");
-        ctx.editor.mode.read_line_state.on_client_keys = on_client_keys;
-        ctx.editor.enter_mode(ModeKind::ReadLine);
+        let client = ctx.clients.get_mut(client_handle);
+        client.read_line.set_prompt("goto-line:");
+        client.mode.read_line_state.on_client_keys = on_client_keys;
+        Editor::enter_mode(ctx, client_handle, ModeKind::ReadLine);
     }
 }
 
 pub mod custom {
     use super::*;
 
-    pub fn enter_mode(ctx: &mut EditorContext, continuation: &str, prompt: &str) {
+    pub fn enter_mode(
+        ctx: &mut EditorContext,
+        client_handle: ClientHandle,
+        continuation: &str,
+        prompt: &str,
+    ) {
         fn on_client_keys(
             ctx: &mut EditorContext,
             client_handle: ClientHandle,
@@ -577,7 +638,8 @@ pub mod custom {
             match poll {
                 ReadLinePoll::Pending => (),
                 ReadLinePoll::Submitted => {
-                    let continuation = &ctx.editor.mode.read_line_state.continuation;
+                    let client = ctx.clients.get(client_handle);
+                    let continuation = &client.mode.read_line_state.continuation;
                     let continuation = ctx.editor.string_pool.acquire_with(continuation);
                     let result = CommandManager::eval(ctx, Some(client_handle), &continuation);
                     let flow = CommandManager::unwrap_eval_result(
@@ -587,20 +649,96 @@ pub mod custom {
                         Some("readline-continuation"),
                     );
                     ctx.editor.string_pool.release(continuation);
-                    ctx.editor.enter_mode(ModeKind::default());
+                    Editor::enter_mode(ctx, client_handle, ModeKind::default());
                     return Some(flow);
                 }
-                ReadLinePoll::Canceled => ctx.editor.enter_mode(ModeKind::default()),
+                ReadLinePoll::Canceled => {
+                    Editor::enter_mode(ctx, client_handle, ModeKind::default())
+                }
             }
             Some(EditorFlow::Continue)
         }
 
-        ctx.editor.read_line.set_prompt(prompt);
-        let state = &mut ctx.editor.mode.read_line_state;
+        let client = ctx.clients.get_mut(client_handle);
+        client.read_line.set_prompt(prompt);
+        let state = &mut client.mode.read_line_state;
         state.on_client_keys = on_client_keys;
         state.continuation.clear();
         state.continuation.push_str(continuation);
-        ctx.editor.enter_mode(ModeKind::ReadLine);
+        Editor::enter_mode(ctx, client_handle, ModeKind::ReadLine);
+    }
+}
+
+pub mod surround {
+    use super::*;
+
+    use std::fmt::Write;
+
+    // surrounds selections with a tag or, if `pair` is `Some`, replaces that surrounding pair with it
+    pub fn enter_tag_mode(
+        ctx: &mut EditorContext,
+        client_handle: ClientHandle,
+        pair: Option<SurroundPair>,
+    ) {
+        fn on_client_keys(
+            ctx: &mut EditorContext,
+            client_handle: ClientHandle,
+            _: &mut KeysIterator,
+            poll: ReadLinePoll,
+        ) -> Option<EditorFlow> {
+            match poll {
+                ReadLinePoll::Pending => (),
+                ReadLinePoll::Submitted => {
+                    let client = ctx.clients.get(client_handle);
+                    let tag = client.read_line.input().trim();
+                    let name = tag.split_whitespace().next().unwrap_or("");
+                    let handle = client.buffer_view_handle();
+                    if let (false, Some(handle)) = (name.is_empty(), handle) {
+                        let mut left = ctx.editor.string_pool.acquire();
+                        let mut right = ctx.editor.string_pool.acquire();
+                        let _ = write!(left, "<{}>", tag);
+                        let _ = write!(right, "</{}>", name);
+
+                        let buffer_view = ctx.editor.buffer_views.get(handle);
+                        let tag = (&left[..], &right[..]);
+                        match client.mode.read_line_state.surround_pair {
+                            Some(pair) => buffer_view.change_surround(
+                                &mut ctx.editor.buffers,
+                                &mut ctx.editor.word_database,
+                                pair,
+                                Some(tag),
+                                &mut ctx.editor.events,
+                            ),
+                            None => buffer_view.add_surround(
+                                &mut ctx.editor.buffers,
+                                &mut ctx.editor.word_database,
+                                tag,
+                                &mut ctx.editor.events,
+                            ),
+                        }
+                        ctx.editor
+                            .buffers
+                            .get_mut(buffer_view.buffer_handle)
+                            .commit_edits();
+
+                        ctx.editor.string_pool.release(left);
+                        ctx.editor.string_pool.release(right);
+                    }
+                    Editor::enter_mode(ctx, client_handle, ModeKind::default());
+                }
+                ReadLinePoll::Canceled => {
+                    Editor::enter_mode(ctx, client_handle, ModeKind::default())
+                }
+            }
+            Some(EditorFlow::Continue)
+        }
+
+        let client = ctx.clients.get_mut(client_handle);
+        client.read_line.set_prompt("tag:");
+        let state = &mut client.mode.read_line_state;
+        state.on_client_keys = on_client_keys;
+        state.surround_pair = pair;
+        Editor::enter_mode(ctx, client_handle, ModeKind::ReadLine);
     }
 }
 
@@ -610,7 +748,7 @@ fn save_current_position(ctx: &mut EditorContext, client_handle: ClientHandle) {
         None => return,
     };
     let buffer_view = ctx.editor.buffer_views.get(buffer_view_handle);
-    let state = &mut ctx.editor.mode.read_line_state;
+    let state = &mut ctx.clients.get_mut(client_handle).mode.read_line_state;
     state.previous_cursors.clear();
     for &cursor in &buffer_view.cursors[..] {
         state.previous_cursors.push(cursor);
@@ -627,7 +765,7 @@ fn restore_saved_position(ctx: &mut EditorContext, client_handle: ClientHandle)
     let mut cursors = buffer_view.cursors.mut_guard();
     cursors.clear();
 
-    let state = &ctx.editor.mode.read_line_state;
+    let state = &ctx.clients.get(client_handle).mode.read_line_state;
     for &cursor in &state.previous_cursors {
         cursors.add(cursor);
     }
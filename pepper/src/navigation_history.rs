@@ -33,6 +33,31 @@ impl NavigationHistory {
         self.on_previous_buffer = false;
     }
 
+    pub fn len(&self) -> usize {
+        self.snapshots.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.snapshots.is_empty()
+    }
+
+    pub fn snapshot(&self, index: usize) -> Option<(BufferHandle, BufferPosition)> {
+        let snapshot = self.snapshots.get(index)?;
+        Some((snapshot.buffer_handle, snapshot.position))
+    }
+
+    pub fn remove_snapshot(&mut self, index: usize) -> bool {
+        if index >= self.snapshots.len() {
+            return false;
+        }
+
+        self.snapshots.remove(index);
+        if self.current_snapshot_index > 0 && index <= self.current_snapshot_index as _ {
+            self.current_snapshot_index -= 1;
+        }
+        true
+    }
+
     pub fn save_snapshot(client: &mut Client, buffer_views: &BufferViewCollection) {
         let buffer_view_handle = match client.buffer_view_handle() {
             Some(handle) => handle,
@@ -95,6 +120,25 @@ impl NavigationHistory {
             }
         }
 
+        Self::move_to_current_snapshot(client, editor);
+    }
+
+    pub fn move_to_snapshot(client: &mut Client, editor: &mut Editor, index: usize) {
+        if index >= client.navigation_history.snapshots.len() {
+            return;
+        }
+
+        if client.navigation_history.current_snapshot_index
+            == client.navigation_history.snapshots.len() as _
+        {
+            Self::save_snapshot(client, &editor.buffer_views);
+        }
+
+        client.navigation_history.current_snapshot_index = index as _;
+        Self::move_to_current_snapshot(client, editor);
+    }
+
+    fn move_to_current_snapshot(client: &mut Client, editor: &mut Editor) {
         let snapshot = &client.navigation_history.snapshots
             [client.navigation_history.current_snapshot_index as usize];
 
@@ -172,13 +216,8 @@ impl NavigationHistory {
 
     pub fn remove_snapshots_with_buffer_handle(&mut self, buffer_handle: BufferHandle) {
         for i in (0..self.snapshots.len()).rev() {
-            let snapshot = self.snapshots[i].clone();
-            if snapshot.buffer_handle == buffer_handle {
-                self.snapshots.remove(i);
-
-                if self.current_snapshot_index > 0 && i <= self.current_snapshot_index as _ {
-                    self.current_snapshot_index -= 1;
-                }
+            if self.snapshots[i].buffer_handle == buffer_handle {
+                self.remove_snapshot(i);
             }
         }
     }
@@ -257,6 +296,35 @@ mod tests {
         assert_eq!(3, client.navigation_history.snapshots.len());
     }
 
+    #[test]
+    fn move_to_and_remove_snapshots() {
+        let (mut editor, mut client) = setup();
+
+        NavigationHistory::move_to_snapshot(&mut client, &mut editor, 0);
+        assert_eq!(0, client.navigation_history.current_snapshot_index);
+        assert_eq!(0, buffer_index(&client, &editor));
+        assert_eq!(3, client.navigation_history.len());
+
+        NavigationHistory::move_to_snapshot(&mut client, &mut editor, 3);
+        assert_eq!(0, buffer_index(&client, &editor));
+
+        NavigationHistory::move_in_history(&mut client, &mut editor, NavigationMovement::Forward);
+        assert_eq!(1, client.navigation_history.current_snapshot_index);
+        assert_eq!(1, buffer_index(&client, &editor));
+
+        assert!(client.navigation_history.remove_snapshot(0));
+        assert!(!client.navigation_history.remove_snapshot(2));
+        assert_eq!(2, client.navigation_history.len());
+        assert_eq!(0, client.navigation_history.current_snapshot_index);
+        assert_eq!(
+            Some(BufferHandle(2)),
+            client.navigation_history.snapshot(1).map(|(h, _)| h)
+        );
+
+        NavigationHistory::move_in_history(&mut client, &mut editor, NavigationMovement::Forward);
+        assert_eq!(2, buffer_index(&client, &editor));
+    }
+
     #[test]
     fn move_to_previous_buffer_three_times() {
         let (mut editor, mut client) = setup();
@@ -1,8 +1,16 @@
-use std::fmt;
-
-use crate::word_database::{WordDatabase, WordIndicesIter};
-
-#[derive(Clone, Copy)]
+use std::{fmt, fs::File, io, path::Path};
+
+use crate::{
+    buffer::{parse_path_and_position, BufferContent, BufferHandle},
+    buffer_position::{BufferPosition, BufferRange},
+    client::ClientHandle,
+    editor::EditorContext,
+    mode::ModeKind,
+    syntax::{HighlightResult, HighlightedBuffer, SyntaxCollection, SyntaxHandle},
+    word_database::{WordDatabase, WordIndicesIter},
+};
+
+#[derive(Clone, Copy, PartialEq, Eq)]
 pub enum EntrySource {
     Custom(usize),
     WordDatabase(usize),
@@ -19,6 +27,8 @@ pub struct Picker {
     custom_entries_len: usize,
     custom_entries_buffer: Vec<String>,
     filtered_entries: Vec<FilteredEntry>,
+    marked_entries: Vec<EntrySource>,
+    marked_cursor: Option<usize>,
 
     cursor: Option<usize>,
     scroll: usize,
@@ -80,10 +90,58 @@ impl Picker {
     pub fn clear(&mut self) {
         self.custom_entries_len = 0;
         self.filtered_entries.clear();
+        self.marked_entries.clear();
+        self.marked_cursor = None;
         self.cursor = None;
         self.scroll = 0;
     }
 
+    pub fn marked_len(&self) -> usize {
+        self.marked_entries.len()
+    }
+
+    pub fn is_marked(&self, index: usize) -> bool {
+        match self.filtered_entries.get(index) {
+            Some(entry) => self.marked_entries.contains(&entry.source),
+            None => false,
+        }
+    }
+
+    pub fn toggle_mark_current_entry(&mut self) {
+        let source = match self.cursor {
+            Some(cursor) => self.filtered_entries[cursor].source,
+            None => return,
+        };
+        match self.marked_entries.iter().position(|&s| s == source) {
+            Some(i) => {
+                self.marked_entries.remove(i);
+            }
+            None => self.marked_entries.push(source),
+        }
+    }
+
+    pub fn toggle_mark_filtered_entries(&mut self) {
+        let all_marked = self
+            .filtered_entries
+            .iter()
+            .all(|e| self.marked_entries.contains(&e.source));
+        if all_marked {
+            let filtered_entries = &self.filtered_entries;
+            self.marked_entries
+                .retain(|s| !filtered_entries.iter().any(|e| e.source == *s));
+        } else {
+            for entry in &self.filtered_entries {
+                if !self.marked_entries.contains(&entry.source) {
+                    self.marked_entries.push(entry.source);
+                }
+            }
+        }
+    }
+
+    pub fn set_marked_cursor(&mut self, index: Option<usize>) {
+        self.marked_cursor = index.filter(|&i| i < self.marked_entries.len());
+    }
+
     fn new_custom_entry(&mut self) -> &mut String {
         if self.custom_entries_len == self.custom_entries_buffer.len() {
             self.custom_entries_buffer.push(String::new());
@@ -94,6 +152,17 @@ impl Picker {
         entry
     }
 
+    pub fn custom_entries_len(&self) -> usize {
+        self.custom_entries_len
+    }
+
+    pub fn clear_custom_entries(&mut self) {
+        self.custom_entries_len = 0;
+        self.filtered_entries.clear();
+        self.marked_entries.clear();
+        self.marked_cursor = None;
+    }
+
     pub fn add_custom_entry(&mut self, name: &str) {
         let entry = self.new_custom_entry();
         entry.push_str(name);
@@ -121,14 +190,25 @@ impl Picker {
     }
 
     pub fn filter(&mut self, word_indices: WordIndicesIter, pattern: &str) {
+        self.filter_ranked(word_indices, pattern, |_, _| Some(0));
+    }
+
+    fn filter_ranked<F>(&mut self, word_indices: WordIndicesIter, pattern: &str, mut rank: F)
+    where
+        F: FnMut(usize, &str) -> Option<u32>,
+    {
         self.filtered_entries.clear();
 
         for (i, word) in word_indices {
+            let bonus = match rank(i, word) {
+                Some(bonus) => bonus,
+                None => continue,
+            };
             let score = self.fuzzy_matcher.score(word, pattern);
             if score != 0 {
                 self.filtered_entries.push(FilteredEntry {
                     source: EntrySource::WordDatabase(i),
-                    score,
+                    score: score + bonus,
                 });
             }
         }
@@ -149,19 +229,41 @@ impl Picker {
     }
 
     pub fn filter_completion(&mut self, word_indices: WordIndicesIter, pattern: &str) {
+        self.filter_completion_ranked(word_indices, pattern, |_, _| Some(0));
+    }
+
+    pub fn filter_completion_ranked<F>(
+        &mut self,
+        word_indices: WordIndicesIter,
+        pattern: &str,
+        rank: F,
+    ) where
+        F: FnMut(usize, &str) -> Option<u32>,
+    {
         if self.custom_entries_len == 0 {
-            self.filter(word_indices, pattern);
-            if self.cursor.is_none() {
-                self.cursor = Some(0);
-            }
-            if self.len() == 1 {
-                self.clear();
-            }
+            self.filter_words_and_custom_completion_ranked(word_indices, pattern, rank);
         } else {
             self.filter(WordIndicesIter::empty(), pattern);
         }
     }
 
+    pub fn filter_words_and_custom_completion_ranked<F>(
+        &mut self,
+        word_indices: WordIndicesIter,
+        pattern: &str,
+        rank: F,
+    ) where
+        F: FnMut(usize, &str) -> Option<u32>,
+    {
+        self.filter_ranked(word_indices, pattern, rank);
+        if self.cursor.is_none() {
+            self.cursor = Some(0);
+        }
+        if self.len() == 1 {
+            self.clear();
+        }
+    }
+
     fn filter_custom_entry(&mut self, index: usize, pattern: &str) -> bool {
         let entry = &self.custom_entries_buffer[index];
         let score = self.fuzzy_matcher.score(entry, pattern);
@@ -177,9 +279,11 @@ impl Picker {
     }
 
     pub fn current_entry<'a>(&'a self, words: &'a WordDatabase) -> Option<(EntrySource, &'a str)> {
-        let entry = &self.filtered_entries[self.cursor?];
-        let source = entry.source;
-        let entry = filtered_to_picker_entry(entry, &self.custom_entries_buffer, words);
+        let source = match self.marked_cursor {
+            Some(i) => self.marked_entries[i],
+            None => self.filtered_entries[self.cursor?].source,
+        };
+        let entry = source_to_picker_entry(source, &self.custom_entries_buffer, words);
         Some((source, entry))
     }
 
@@ -190,16 +294,16 @@ impl Picker {
         let custom_entries = &self.custom_entries_buffer[..];
         self.filtered_entries
             .iter()
-            .map(move |e| filtered_to_picker_entry(e, custom_entries, words))
+            .map(move |e| source_to_picker_entry(e.source, custom_entries, words))
     }
 }
 
-fn filtered_to_picker_entry<'a>(
-    entry: &FilteredEntry,
+fn source_to_picker_entry<'a>(
+    source: EntrySource,
     custom_entries: &'a [String],
     words: &'a WordDatabase,
 ) -> &'a str {
-    match entry.source {
+    match source {
         EntrySource::Custom(i) => &custom_entries[i],
         EntrySource::WordDatabase(i) => words.word_at(i),
     }
@@ -229,6 +333,185 @@ impl<'picker, 'pattern> Drop for AddCustomFilteredEntryGuard<'picker, 'pattern>
     }
 }
 
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum PickerPreviewSource {
+    None,
+    Buffer(BufferHandle),
+    Content,
+}
+
+pub struct PickerPreview {
+    entry: String,
+    source: PickerPreviewSource,
+    content: BufferContent,
+    highlighted: HighlightedBuffer,
+    line_index: usize,
+}
+
+impl PickerPreview {
+    pub fn source(&self) -> PickerPreviewSource {
+        self.source
+    }
+
+    pub fn line_index(&self) -> usize {
+        self.line_index
+    }
+
+    pub fn content(&self) -> &BufferContent {
+        &self.content
+    }
+
+    pub fn highlighted(&self) -> &HighlightedBuffer {
+        &self.highlighted
+    }
+
+    pub fn height(&self, max_height: u8) -> usize {
+        match self.source {
+            PickerPreviewSource::None => 0,
+            _ => max_height as _,
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.entry.clear();
+        self.source = PickerPreviewSource::None;
+        self.line_index = 0;
+    }
+
+    fn read_file(
+        &mut self,
+        syntaxes: &SyntaxCollection,
+        path: &Path,
+        line_index: usize,
+        height: u8,
+    ) -> bool {
+        let file = match File::open(path) {
+            Ok(file) => file,
+            Err(_) => return false,
+        };
+        if !self.read_window(&mut io::BufReader::new(file), line_index, height) {
+            return false;
+        }
+
+        let syntax_handle = path
+            .to_str()
+            .and_then(|p| syntaxes.find_handle_by_path(p))
+            .unwrap_or_default();
+        self.highlight(syntaxes, syntax_handle);
+        self.source = PickerPreviewSource::Content;
+        true
+    }
+
+    // reads every line up to the end of the window displayed around `line_index`
+    // so they can be highlighted from the start of the file
+    // and stops reading as soon as they're read or as an invalid utf8 line is found
+    fn read_window<R>(&mut self, read: &mut R, line_index: usize, height: u8) -> bool
+    where
+        R: io::BufRead,
+    {
+        let first_line_index = line_index.saturating_sub(height as usize / 2);
+        let last_line_index = first_line_index + height.max(1) as usize;
+
+        let mut text = String::new();
+        let mut line = Vec::new();
+        for _ in 0..last_line_index {
+            line.clear();
+            match read.read_until(b'\n', &mut line) {
+                Ok(0) => break,
+                Ok(_) => (),
+                Err(_) => return false,
+            }
+            let line = match std::str::from_utf8(&line) {
+                Ok(line) => line,
+                Err(_) => return false,
+            };
+            text.push_str(line);
+        }
+
+        if self.content.read(&mut text.as_bytes()).is_err() {
+            return false;
+        }
+        self.line_index = line_index;
+        true
+    }
+
+    fn highlight(&mut self, syntaxes: &SyntaxCollection, syntax_handle: SyntaxHandle) {
+        self.highlighted.clear();
+        self.highlighted.insert_range(BufferRange::between(
+            BufferPosition::zero(),
+            BufferPosition::line_col((self.content.lines().len() - 1) as _, 0),
+        ));
+        let syntax = syntaxes.get(syntax_handle);
+        while let HighlightResult::Pending = self
+            .highlighted
+            .highlight_dirty_lines(syntax, &self.content)
+        {}
+    }
+
+    pub(crate) fn update(ctx: &mut EditorContext, client_handle: ClientHandle) {
+        let client = ctx.clients.get_mut(client_handle);
+        if client.mode.kind() != ModeKind::Picker || ctx.editor.config.picker_preview_height == 0 {
+            client.picker_preview.clear();
+            return;
+        }
+
+        let entry = match client.picker.current_entry(&ctx.editor.word_database) {
+            Some((_, entry)) => entry,
+            None => {
+                client.picker_preview.clear();
+                return;
+            }
+        };
+
+        let preview = &mut client.picker_preview;
+        if preview.entry == entry {
+            return;
+        }
+        preview.clear();
+        preview.entry.push_str(entry);
+
+        let entry = ctx
+            .editor
+            .string_pool
+            .acquire_with(&client.picker_preview.entry);
+        let (path, position) = parse_path_and_position(&entry);
+        let line_index = position.map(|p| p.line_index as usize).unwrap_or(0);
+
+        if !path.is_empty() {
+            let path = Path::new(path);
+            let preview = &mut client.picker_preview;
+            if let Some(buffer_handle) = ctx
+                .editor
+                .buffers
+                .find_with_path(&ctx.editor.current_directory, path)
+            {
+                preview.source = PickerPreviewSource::Buffer(buffer_handle);
+                preview.line_index = line_index;
+            } else {
+                let path = ctx.editor.current_directory.join(path);
+                if path.is_file() {
+                    let height = ctx.editor.config.picker_preview_height;
+                    preview.read_file(&ctx.editor.syntaxes, &path, line_index, height);
+                }
+            }
+        }
+
+        ctx.editor.string_pool.release(entry);
+    }
+}
+
+impl Default for PickerPreview {
+    fn default() -> Self {
+        Self {
+            entry: String::new(),
+            source: PickerPreviewSource::None,
+            content: BufferContent::new(),
+            highlighted: HighlightedBuffer::new(),
+            line_index: 0,
+        }
+    }
+}
+
 const FIRST_CHAR_SCORE: u32 = 1;
 const WORD_BOUNDARY_MATCH_SCORE: u32 = 2;
 const CONSECUTIVE_MATCH_SCORE: u32 = 3;
@@ -313,6 +596,8 @@ impl FuzzyMatcher {
 mod tests {
     use super::*;
 
+    use crate::syntax::TokenKind;
+
     #[test]
     fn fuzzy_matcher_test() {
         let mut fuzzy_matcher = FuzzyMatcher::default();
@@ -379,4 +664,102 @@ mod tests {
             fuzzy_matcher.score(&big_repetitive_text, &big_repetitive_text),
         );
     }
+
+    #[test]
+    fn picker_marked_entries() {
+        let words = WordDatabase::new();
+        let mut picker = Picker::default();
+        picker.add_custom_entry("file-a");
+        picker.add_custom_entry("file-b");
+        picker.add_custom_entry("other");
+        picker.filter(WordIndicesIter::empty(), "");
+        picker.move_cursor(0);
+
+        assert_eq!(0, picker.marked_len());
+        picker.toggle_mark_current_entry();
+        assert_eq!(1, picker.marked_len());
+        assert!(picker.is_marked(0));
+        picker.toggle_mark_current_entry();
+        assert_eq!(0, picker.marked_len());
+
+        picker.filter(WordIndicesIter::empty(), "file");
+        picker.toggle_mark_filtered_entries();
+        assert_eq!(2, picker.marked_len());
+
+        picker.filter(WordIndicesIter::empty(), "");
+        picker.toggle_mark_filtered_entries();
+        assert_eq!(3, picker.marked_len());
+        picker.toggle_mark_filtered_entries();
+        assert_eq!(0, picker.marked_len());
+
+        picker.filter(WordIndicesIter::empty(), "other");
+        picker.move_cursor(0);
+        picker.toggle_mark_current_entry();
+        picker.filter(WordIndicesIter::empty(), "");
+        picker.set_marked_cursor(Some(0));
+        assert_eq!("other", picker.current_entry(&words).unwrap().1);
+        picker.set_marked_cursor(None);
+        assert_eq!("file-a", picker.current_entry(&words).unwrap().1);
+    }
+
+    fn set_preview_text(
+        preview: &mut PickerPreview,
+        syntaxes: &SyntaxCollection,
+        text: &str,
+        line_index: usize,
+        height: u8,
+    ) {
+        assert!(preview.read_window(&mut text.as_bytes(), line_index, height));
+        preview.highlight(syntaxes, SyntaxHandle::default());
+        preview.source = PickerPreviewSource::Content;
+    }
+
+    #[test]
+    fn picker_preview_window() {
+        fn lines(preview: &PickerPreview) -> Vec<&str> {
+            preview
+                .content()
+                .lines()
+                .iter()
+                .map(|l| l.as_str())
+                .collect()
+        }
+
+        let syntaxes = SyntaxCollection::new();
+        let mut preview = PickerPreview::default();
+        let text = "0\n1\n2\n3\n4\n5\n6\n7\n8\n9\n";
+
+        set_preview_text(&mut preview, &syntaxes, text, 5, 4);
+        assert!(matches!(preview.source(), PickerPreviewSource::Content));
+        assert_eq!(["0", "1", "2", "3", "4", "5", "6"], &lines(&preview)[..]);
+        assert_eq!(5, preview.line_index());
+
+        set_preview_text(&mut preview, &syntaxes, text, 1, 4);
+        assert_eq!(["0", "1", "2", "3"], &lines(&preview)[..]);
+        assert_eq!(1, preview.line_index());
+
+        set_preview_text(&mut preview, &syntaxes, text, 9, 4);
+        assert_eq!(10, lines(&preview).len());
+        assert_eq!(9, preview.line_index());
+
+        preview.clear();
+        assert!(!preview.read_window(&mut &b"a\n\xff\xfe\nb\n"[..], 0, 4));
+        assert!(preview.read_window(&mut &b"a\nb\n\xff\xfe\n"[..], 0, 2));
+        assert_eq!(["a", "b"], &lines(&preview)[..]);
+    }
+
+    #[test]
+    fn picker_preview_highlights_from_file_start() {
+        let mut syntaxes = SyntaxCollection::new();
+        syntaxes
+            .get_current()
+            .set_rule(TokenKind::Comment, "/*{!(*/).$}")
+            .unwrap();
+
+        let mut preview = PickerPreview::default();
+        set_preview_text(&mut preview, &syntaxes, "/*\n1\n2\n3\n4\n5\n*/\n", 4, 2);
+        let tokens = preview.highlighted().line_tokens(4);
+        assert_eq!(1, tokens.len());
+        assert_eq!(TokenKind::Comment, tokens[0].kind);
+    }
 }
@@ -99,7 +99,7 @@ pub enum PlatformRequest {
 pub enum ProcessTag {
     Ignored,
     Buffer(u32),
-    PickerEntries,
+    PickerEntries(ClientHandle),
     Plugin {
         plugin_handle: PluginHandle,
         id: u32,
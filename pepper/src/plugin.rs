@@ -35,6 +35,8 @@ pub struct Plugin {
         keys: &mut KeysIterator,
     ) -> Option<EditorFlow>,
     pub on_completion: fn(PluginHandle, &mut EditorContext, &CompletionContext) -> bool,
+    pub on_text_object:
+        fn(PluginHandle, &mut EditorContext, &TextObjectContext) -> Option<BufferRange>,
 }
 impl Default for Plugin {
     fn default() -> Self {
@@ -49,6 +51,7 @@ impl Default for Plugin {
 
             on_keys: |_, _, _, _| Some(EditorFlow::Continue),
             on_completion: |_, _, _| false,
+            on_text_object: |_, _, _| None,
         }
     }
 }
@@ -61,9 +64,18 @@ pub struct CompletionContext {
     pub completion_requested: bool,
 }
 
+pub struct TextObjectContext {
+    pub client_handle: ClientHandle,
+    pub buffer_handle: BufferHandle,
+    pub key: char,
+    pub cursor_position: BufferPosition,
+    pub around: bool,
+}
+
 #[derive(Default)]
 pub struct PluginCollection {
     plugins: Vec<Plugin>,
+    text_objects: Vec<(char, PluginHandle)>,
 }
 impl PluginCollection {
     pub(crate) fn add(ctx: &mut EditorContext, definition: PluginDefinition) {
@@ -89,6 +101,19 @@ impl PluginCollection {
         }
     }
 
+    // makes `a<key>` and `A<key>` in normal mode call the plugin's `on_text_object`
+    pub fn register_text_object(&mut self, handle: PluginHandle, key: char) {
+        match self.text_objects.iter_mut().find(|(k, _)| *k == key) {
+            Some((_, h)) => *h = handle,
+            None => self.text_objects.push((key, handle)),
+        }
+    }
+
+    pub(crate) fn find_text_object(&self, key: char) -> Option<PluginHandle> {
+        let (_, handle) = self.text_objects.iter().find(|(k, _)| *k == key)?;
+        Some(*handle)
+    }
+
     pub(crate) fn get(&self, handle: PluginHandle) -> &Plugin {
         &self.plugins[handle.0 as usize]
     }
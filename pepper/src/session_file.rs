@@ -0,0 +1,458 @@
+use std::{
+    env, fmt, fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    buffer::BufferProperties,
+    buffer_position::{BufferPosition, BufferPositionIndex},
+    buffer_view::BufferViewHandle,
+    client::ClientHandle,
+    cursor::Cursor,
+    editor::{Editor, EditorContext},
+    serialization::{DeserializeError, Deserializer, Serialize, Serializer},
+};
+
+const SESSION_FILE_VERSION: u8 = 1;
+
+pub enum SessionError {
+    CouldNotWriteFile,
+    CouldNotReadFile,
+    InvalidFile,
+    InvalidCurrentDirectory,
+}
+impl fmt::Display for SessionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::CouldNotWriteFile => f.write_str("could not write session file"),
+            Self::CouldNotReadFile => f.write_str("could not read session file"),
+            Self::InvalidFile => f.write_str("invalid session file"),
+            Self::InvalidCurrentDirectory => f.write_str("invalid current directory"),
+        }
+    }
+}
+
+pub fn session_path(editor: &Editor, name: &str) -> PathBuf {
+    Path::new(editor.config.session_directory.as_str()).join(name)
+}
+
+struct SessionBuffer<'a> {
+    path: &'a str,
+    properties: BufferProperties,
+}
+
+struct SessionBufferView {
+    client_handle: ClientHandle,
+    buffer_index: u32,
+    main_cursor_index: u32,
+    cursors: Vec<Cursor>,
+}
+
+struct SessionClient {
+    handle: ClientHandle,
+    buffer_view_index: u32,
+    scroll: BufferPositionIndex,
+}
+
+#[derive(Default)]
+struct Session<'a> {
+    current_directory: &'a str,
+    buffers: Vec<SessionBuffer<'a>>,
+    buffer_views: Vec<SessionBufferView>,
+    clients: Vec<SessionClient>,
+}
+
+impl<'de> Session<'de> {
+    fn is_valid(&self) -> bool {
+        let buffers_len = self.buffers.len() as u32;
+        let buffer_views_len = self.buffer_views.len() as u32;
+        self.buffer_views
+            .iter()
+            .all(|v| v.buffer_index < buffers_len && !v.cursors.is_empty())
+            && self
+                .clients
+                .iter()
+                .all(|c| c.buffer_view_index < buffer_views_len)
+    }
+}
+
+fn serialize_position<S>(position: BufferPosition, serializer: &mut S)
+where
+    S: Serializer,
+{
+    position.line_index.serialize(serializer);
+    position.column_byte_index.serialize(serializer);
+}
+
+fn deserialize_position<'de, D>(deserializer: &mut D) -> Result<BufferPosition, DeserializeError>
+where
+    D: Deserializer<'de>,
+{
+    let line_index = Serialize::deserialize(deserializer)?;
+    let column_byte_index = Serialize::deserialize(deserializer)?;
+    Ok(BufferPosition {
+        line_index,
+        column_byte_index,
+    })
+}
+
+fn serialize_properties<S>(properties: BufferProperties, serializer: &mut S)
+where
+    S: Serializer,
+{
+    let mut flags = 0u8;
+    if properties.history_enabled {
+        flags |= 1 << 0;
+    }
+    if properties.saving_enabled {
+        flags |= 1 << 1;
+    }
+    if properties.is_file {
+        flags |= 1 << 2;
+    }
+    if properties.word_database_enabled {
+        flags |= 1 << 3;
+    }
+    flags.serialize(serializer);
+}
+
+fn deserialize_properties<'de, D>(
+    deserializer: &mut D,
+) -> Result<BufferProperties, DeserializeError>
+where
+    D: Deserializer<'de>,
+{
+    let flags = u8::deserialize(deserializer)?;
+    Ok(BufferProperties {
+        history_enabled: flags & (1 << 0) != 0,
+        saving_enabled: flags & (1 << 1) != 0,
+        is_file: flags & (1 << 2) != 0,
+        word_database_enabled: flags & (1 << 3) != 0,
+    })
+}
+
+impl<'de> Serialize<'de> for Session<'de> {
+    fn serialize<S>(&self, serializer: &mut S)
+    where
+        S: Serializer,
+    {
+        SESSION_FILE_VERSION.serialize(serializer);
+        self.current_directory.serialize(serializer);
+
+        (self.buffers.len() as u32).serialize(serializer);
+        for buffer in &self.buffers {
+            buffer.path.serialize(serializer);
+            serialize_properties(buffer.properties, serializer);
+        }
+
+        (self.buffer_views.len() as u32).serialize(serializer);
+        for buffer_view in &self.buffer_views {
+            buffer_view.client_handle.serialize(serializer);
+            buffer_view.buffer_index.serialize(serializer);
+            buffer_view.main_cursor_index.serialize(serializer);
+            (buffer_view.cursors.len() as u32).serialize(serializer);
+            for cursor in &buffer_view.cursors {
+                serialize_position(cursor.anchor, serializer);
+                serialize_position(cursor.position, serializer);
+            }
+        }
+
+        (self.clients.len() as u32).serialize(serializer);
+        for client in &self.clients {
+            client.handle.serialize(serializer);
+            client.buffer_view_index.serialize(serializer);
+            client.scroll.serialize(serializer);
+        }
+    }
+
+    fn deserialize<D>(deserializer: &mut D) -> Result<Self, DeserializeError>
+    where
+        D: Deserializer<'de>,
+    {
+        if u8::deserialize(deserializer)? != SESSION_FILE_VERSION {
+            return Err(DeserializeError::InvalidData);
+        }
+
+        let mut session = Session {
+            current_directory: Serialize::deserialize(deserializer)?,
+            ..Default::default()
+        };
+
+        let len = u32::deserialize(deserializer)?;
+        for _ in 0..len {
+            let path = Serialize::deserialize(deserializer)?;
+            let properties = deserialize_properties(deserializer)?;
+            session.buffers.push(SessionBuffer { path, properties });
+        }
+
+        let len = u32::deserialize(deserializer)?;
+        for _ in 0..len {
+            let client_handle = Serialize::deserialize(deserializer)?;
+            let buffer_index = Serialize::deserialize(deserializer)?;
+            let main_cursor_index = Serialize::deserialize(deserializer)?;
+            let mut cursors = Vec::new();
+            let cursors_len = u32::deserialize(deserializer)?;
+            for _ in 0..cursors_len {
+                let anchor = deserialize_position(deserializer)?;
+                let position = deserialize_position(deserializer)?;
+                cursors.push(Cursor { anchor, position });
+            }
+            session.buffer_views.push(SessionBufferView {
+                client_handle,
+                buffer_index,
+                main_cursor_index,
+                cursors,
+            });
+        }
+
+        let len = u32::deserialize(deserializer)?;
+        for _ in 0..len {
+            let handle = Serialize::deserialize(deserializer)?;
+            let buffer_view_index = Serialize::deserialize(deserializer)?;
+            let scroll = Serialize::deserialize(deserializer)?;
+            session.clients.push(SessionClient {
+                handle,
+                buffer_view_index,
+                scroll,
+            });
+        }
+
+        Ok(session)
+    }
+}
+
+fn session_from_context(ctx: &EditorContext) -> Session<'_> {
+    let mut session = Session {
+        current_directory: ctx.editor.current_directory.to_str().unwrap_or(""),
+        ..Default::default()
+    };
+
+    let mut buffer_handles = Vec::new();
+    let mut buffer_view_handles = Vec::new();
+    for buffer_view in ctx.editor.buffer_views.iter() {
+        let buffer = ctx.editor.buffers.get(buffer_view.buffer_handle);
+        let path = match buffer.path.to_str() {
+            Some(path) if buffer.properties.is_file && !path.is_empty() => path,
+            _ => continue,
+        };
+
+        let buffer_index = match buffer_handles.iter().position(|&h| h == buffer.handle()) {
+            Some(index) => index,
+            None => {
+                buffer_handles.push(buffer.handle());
+                session.buffers.push(SessionBuffer {
+                    path,
+                    properties: buffer.properties,
+                });
+                buffer_handles.len() - 1
+            }
+        };
+
+        buffer_view_handles.push(buffer_view.handle());
+        session.buffer_views.push(SessionBufferView {
+            client_handle: buffer_view.client_handle,
+            buffer_index: buffer_index as _,
+            main_cursor_index: buffer_view.cursors.main_cursor_index() as _,
+            cursors: buffer_view.cursors[..].to_vec(),
+        });
+    }
+
+    for client in ctx.clients.iter() {
+        let buffer_view_index = match client
+            .buffer_view_handle()
+            .and_then(|h| buffer_view_handles.iter().position(|&v| v == h))
+        {
+            Some(index) => index,
+            None => continue,
+        };
+        session.clients.push(SessionClient {
+            handle: client.handle(),
+            buffer_view_index: buffer_view_index as _,
+            scroll: client.scroll,
+        });
+    }
+
+    session
+}
+
+pub fn save(ctx: &EditorContext, path: &Path) -> Result<(), SessionError> {
+    let mut bytes = Vec::new();
+    session_from_context(ctx).serialize(&mut bytes);
+
+    if let Some(parent) = path.parent() {
+        let _ = fs::create_dir_all(parent);
+    }
+    fs::write(path, &bytes).map_err(|_| SessionError::CouldNotWriteFile)
+}
+
+pub fn load(ctx: &mut EditorContext, path: &Path) -> Result<(), SessionError> {
+    let bytes = fs::read(path).map_err(|_| SessionError::CouldNotReadFile)?;
+    let mut deserializer = &bytes[..];
+    let session = match Session::deserialize(&mut deserializer) {
+        Ok(session) if session.is_valid() => session,
+        _ => return Err(SessionError::InvalidFile),
+    };
+
+    if !session.current_directory.is_empty() {
+        let current_directory = PathBuf::from(session.current_directory);
+        if env::set_current_dir(&current_directory).is_err() {
+            return Err(SessionError::InvalidCurrentDirectory);
+        }
+        ctx.editor.current_directory = current_directory;
+    }
+
+    for buffer in ctx.editor.buffers.iter() {
+        ctx.editor
+            .buffers
+            .defer_remove(buffer.handle(), &mut ctx.editor.events);
+    }
+    ctx.trigger_event_handlers();
+
+    let mut buffer_view_handles: Vec<Option<BufferViewHandle>> = Vec::new();
+    for buffer_view in &session.buffer_views {
+        let buffer = &session.buffers[buffer_view.buffer_index as usize];
+        let handle = ctx.editor.buffer_view_handle_from_path(
+            buffer_view.client_handle,
+            Path::new(buffer.path),
+            buffer.properties,
+            true,
+        );
+        let handle = match handle {
+            Ok(handle) => handle,
+            Err(_) => {
+                buffer_view_handles.push(None);
+                continue;
+            }
+        };
+        buffer_view_handles.push(Some(handle));
+
+        let view = ctx.editor.buffer_views.get_mut(handle);
+        let content = ctx.editor.buffers.get(view.buffer_handle).content();
+        let mut cursors = view.cursors.mut_guard();
+        cursors.clear();
+        for cursor in &buffer_view.cursors {
+            cursors.add(Cursor {
+                anchor: content.saturate_position(cursor.anchor),
+                position: content.saturate_position(cursor.position),
+            });
+        }
+        let main_cursor_index = buffer_view.main_cursor_index as usize;
+        cursors.set_main_cursor_index(main_cursor_index.min(buffer_view.cursors.len() - 1));
+    }
+
+    for client in &session.clients {
+        if let Some(handle) = buffer_view_handles[client.buffer_view_index as usize] {
+            ctx.clients.restore_client_view(
+                client.handle,
+                handle,
+                client.scroll,
+                &ctx.editor.buffer_views,
+            );
+        }
+    }
+
+    ctx.trigger_event_handlers();
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn session_round_trip() {
+        let cursor = |anchor: (u32, u32), position: (u32, u32)| Cursor {
+            anchor: BufferPosition::line_col(anchor.0, anchor.1),
+            position: BufferPosition::line_col(position.0, position.1),
+        };
+
+        let session = Session {
+            current_directory: "/home/user/project",
+            buffers: vec![
+                SessionBuffer {
+                    path: "src/main.rs",
+                    properties: BufferProperties::text(),
+                },
+                SessionBuffer {
+                    path: "notes.txt",
+                    properties: BufferProperties::scratch(),
+                },
+            ],
+            buffer_views: vec![
+                SessionBufferView {
+                    client_handle: ClientHandle(0),
+                    buffer_index: 0,
+                    main_cursor_index: 1,
+                    cursors: vec![cursor((0, 0), (0, 4)), cursor((3, 2), (3, 2))],
+                },
+                SessionBufferView {
+                    client_handle: ClientHandle(1),
+                    buffer_index: 1,
+                    main_cursor_index: 0,
+                    cursors: vec![cursor((7, 1), (5, 0))],
+                },
+            ],
+            clients: vec![
+                SessionClient {
+                    handle: ClientHandle(0),
+                    buffer_view_index: 0,
+                    scroll: 2,
+                },
+                SessionClient {
+                    handle: ClientHandle(1),
+                    buffer_view_index: 1,
+                    scroll: 0,
+                },
+            ],
+        };
+        assert!(session.is_valid());
+
+        let mut bytes = Vec::new();
+        session.serialize(&mut bytes);
+
+        let mut deserializer = &bytes[..];
+        let restored = match Session::deserialize(&mut deserializer) {
+            Ok(session) => session,
+            Err(_) => panic!("could not deserialize session"),
+        };
+        assert!(deserializer.is_empty());
+        assert!(restored.is_valid());
+
+        assert_eq!("/home/user/project", restored.current_directory);
+        assert_eq!(2, restored.buffers.len());
+        assert_eq!("src/main.rs", restored.buffers[0].path);
+        assert!(restored.buffers[0].properties.saving_enabled);
+        assert_eq!("notes.txt", restored.buffers[1].path);
+        assert!(!restored.buffers[1].properties.saving_enabled);
+        assert!(restored.buffers[1].properties.is_file);
+
+        assert_eq!(2, restored.buffer_views.len());
+        let view = &restored.buffer_views[0];
+        assert!(view.client_handle == ClientHandle(0));
+        assert_eq!(1, view.main_cursor_index);
+        assert_eq!(2, view.cursors.len());
+        assert_eq!(BufferPosition::line_col(0, 4), view.cursors[0].position);
+        assert_eq!(BufferPosition::line_col(3, 2), view.cursors[1].anchor);
+        let view = &restored.buffer_views[1];
+        assert!(view.client_handle == ClientHandle(1));
+        assert_eq!(1, view.buffer_index);
+        assert_eq!(BufferPosition::line_col(7, 1), view.cursors[0].anchor);
+
+        assert_eq!(2, restored.clients.len());
+        assert_eq!(2, restored.clients[0].scroll);
+        assert_eq!(1, restored.clients[1].buffer_view_index);
+
+        let mut deserializer = &bytes[..bytes.len() - 1];
+        assert!(Session::deserialize(&mut deserializer).is_err());
+
+        let invalid = Session {
+            clients: vec![SessionClient {
+                handle: ClientHandle(0),
+                buffer_view_index: 0,
+                scroll: 0,
+            }],
+            ..Default::default()
+        };
+        assert!(!invalid.is_valid());
+    }
+}
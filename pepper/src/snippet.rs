@@ -0,0 +1,300 @@
+use crate::{
+    editor_utils::hash_bytes,
+    glob::{Glob, InvalidGlobError},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SnippetTabstop {
+    pub index: u32,
+    pub from: usize,
+    pub to: usize,
+}
+
+struct Snippet {
+    trigger: String,
+    body: String,
+}
+
+struct SnippetGroup {
+    glob_hash: u64,
+    glob: Glob,
+    snippets: Vec<Snippet>,
+}
+
+#[derive(Default)]
+pub struct SnippetCollection {
+    groups: Vec<SnippetGroup>,
+}
+
+impl SnippetCollection {
+    pub fn add(&mut self, glob: &str, trigger: &str, body: &str) -> Result<(), InvalidGlobError> {
+        let glob_hash = hash_bytes(glob.as_bytes());
+        let group = match self.groups.iter().position(|g| g.glob_hash == glob_hash) {
+            Some(i) => &mut self.groups[i],
+            None => {
+                let mut group = SnippetGroup {
+                    glob_hash,
+                    glob: Glob::default(),
+                    snippets: Vec::new(),
+                };
+                group.glob.compile(glob)?;
+                self.groups.push(group);
+                let last_index = self.groups.len() - 1;
+                &mut self.groups[last_index]
+            }
+        };
+
+        match group.snippets.iter_mut().find(|s| s.trigger == trigger) {
+            Some(snippet) => {
+                snippet.body.clear();
+                snippet.body.push_str(body);
+            }
+            None => group.snippets.push(Snippet {
+                trigger: trigger.into(),
+                body: body.into(),
+            }),
+        }
+
+        Ok(())
+    }
+
+    pub fn find(&self, path: &str, trigger: &str) -> Option<&str> {
+        self.groups
+            .iter()
+            .filter(|g| g.glob.matches(path))
+            .flat_map(|g| g.snippets.iter())
+            .find(|s| s.trigger == trigger)
+            .map(|s| s.body.as_str())
+    }
+
+    pub fn triggers<'a>(&'a self, path: &'a str) -> impl 'a + Iterator<Item = &'a str> {
+        self.groups
+            .iter()
+            .filter(move |g| g.glob.matches(path))
+            .flat_map(|g| g.snippets.iter())
+            .map(|s| s.trigger.as_str())
+    }
+}
+
+struct SnippetParser<'a> {
+    indentation: &'a str,
+    tab_text: &'a str,
+    text: &'a mut String,
+    tabstops: &'a mut Vec<SnippetTabstop>,
+}
+
+impl<'a> SnippetParser<'a> {
+    fn parse_text<'body>(&mut self, mut body: &'body str, nested: bool) -> &'body str {
+        loop {
+            let mut chars = body.chars();
+            match chars.next() {
+                None => return body,
+                Some('}') if nested => return chars.as_str(),
+                Some('\\') => match chars.clone().next() {
+                    Some(c @ ('$' | '}' | '\\')) => {
+                        chars.next();
+                        self.text.push(c);
+                    }
+                    _ => self.text.push('\\'),
+                },
+                Some('\n') => {
+                    self.text.push('\n');
+                    self.text.push_str(self.indentation);
+                }
+                Some('\t') => self.text.push_str(self.tab_text),
+                Some('$') => {
+                    body = self.parse_dollar(chars.as_str());
+                    continue;
+                }
+                Some(c) => self.text.push(c),
+            }
+            body = chars.as_str();
+        }
+    }
+
+    fn parse_dollar<'body>(&mut self, body: &'body str) -> &'body str {
+        fn variable_name_len(text: &str) -> usize {
+            text.bytes()
+                .take_while(|b| b.is_ascii_alphanumeric() || *b == b'_')
+                .count()
+        }
+
+        fn tabstop_index_len(text: &str) -> usize {
+            text.bytes().take_while(u8::is_ascii_digit).count()
+        }
+
+        let index_len = tabstop_index_len(body);
+        if index_len > 0 {
+            let index = body[..index_len].parse().unwrap_or(0);
+            self.add_mirror_tabstop(index);
+            return &body[index_len..];
+        }
+
+        let rest = match body.strip_prefix('{') {
+            Some(rest) => rest,
+            None => {
+                let name_len = variable_name_len(body);
+                if name_len == 0 {
+                    self.text.push('$');
+                }
+                return &body[name_len..];
+            }
+        };
+
+        let index_len = tabstop_index_len(rest);
+        let name_len = if index_len > 0 {
+            index_len
+        } else {
+            variable_name_len(rest)
+        };
+        if name_len == 0 {
+            self.text.push('$');
+            return body;
+        }
+
+        let (name, rest) = rest.split_at(name_len);
+        let index = if index_len > 0 {
+            Some(name.parse().unwrap_or(0))
+        } else {
+            None
+        };
+
+        let from = self.text.len();
+        let rest = if let Some(rest) = rest.strip_prefix(':') {
+            self.parse_text(rest, true)
+        } else if let Some(rest) = rest.strip_prefix('|') {
+            let choices_len = rest.find("|}").unwrap_or(rest.len());
+            let (choices, rest) = rest.split_at(choices_len);
+            let first_choice = choices.split(',').next().unwrap_or("");
+            self.text.push_str(first_choice);
+            rest.get(2..).unwrap_or("")
+        } else {
+            rest.strip_prefix('}').unwrap_or(rest)
+        };
+
+        if let Some(index) = index {
+            if from == self.text.len() {
+                self.add_mirror_tabstop(index);
+            } else {
+                self.tabstops.push(SnippetTabstop {
+                    index,
+                    from,
+                    to: self.text.len(),
+                });
+            }
+        }
+
+        rest
+    }
+
+    fn add_mirror_tabstop(&mut self, index: u32) {
+        let from = self.text.len();
+        if let Some(mirrored) = self.tabstops.iter().find(|t| t.index == index) {
+            let mirrored_range = mirrored.from..mirrored.to;
+            self.text.extend_from_within(mirrored_range);
+        }
+        self.tabstops.push(SnippetTabstop {
+            index,
+            from,
+            to: self.text.len(),
+        });
+    }
+}
+
+pub fn parse_snippet(
+    body: &str,
+    indentation: &str,
+    tab_text: &str,
+    text: &mut String,
+    tabstops: &mut Vec<SnippetTabstop>,
+) {
+    let mut parser = SnippetParser {
+        indentation,
+        tab_text,
+        text,
+        tabstops,
+    };
+    parser.parse_text(body, false);
+
+    if !tabstops.iter().any(|t| t.index == 0) {
+        let len = text.len();
+        tabstops.push(SnippetTabstop {
+            index: 0,
+            from: len,
+            to: len,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(body: &str) -> (String, Vec<SnippetTabstop>) {
+        let mut text = String::new();
+        let mut tabstops = Vec::new();
+        parse_snippet(body, "  ", "\t", &mut text, &mut tabstops);
+        (text, tabstops)
+    }
+
+    fn tabstop_texts<'a>(text: &'a str, tabstops: &[SnippetTabstop]) -> Vec<(u32, &'a str)> {
+        tabstops
+            .iter()
+            .map(|t| (t.index, &text[t.from..t.to]))
+            .collect()
+    }
+
+    #[test]
+    fn parse_plain_snippet() {
+        let (text, tabstops) = parse("plain \\$ text");
+        assert_eq!("plain $ text", text);
+        assert_eq!(vec![(0, "")], tabstop_texts(&text, &tabstops));
+        assert_eq!(text.len(), tabstops[0].from);
+
+        let (text, _) = parse("a $ b $");
+        assert_eq!("a $ b $", text);
+    }
+
+    #[test]
+    fn parse_tabstops_and_placeholders() {
+        let (text, tabstops) = parse("fn ${1:name}($2) {\n\t$0\n}");
+        assert_eq!("fn name() {\n  \t\n  }", text);
+        assert_eq!(
+            vec![(1, "name"), (2, ""), (0, "")],
+            tabstop_texts(&text, &tabstops)
+        );
+        assert_eq!(15, tabstops[2].from);
+    }
+
+    #[test]
+    fn parse_nested_and_mirrored_placeholders() {
+        let (text, tabstops) = parse("${1:a ${2:b}} = $1;");
+        assert_eq!("a b = a b;", text);
+        assert_eq!(
+            vec![(2, "b"), (1, "a b"), (1, "a b"), (0, "")],
+            tabstop_texts(&text, &tabstops)
+        );
+    }
+
+    #[test]
+    fn parse_choices_and_variables() {
+        let (text, tabstops) = parse("${1|first,second|} $TM_FILENAME ${VAR:default}\\}");
+        assert_eq!("first  default}", text);
+        assert_eq!(vec![(1, "first"), (0, "")], tabstop_texts(&text, &tabstops));
+    }
+
+    #[test]
+    fn snippet_collection() {
+        let mut snippets = SnippetCollection::default();
+        snippets.add("**/*.rs", "fn", "fn $1() {}").unwrap();
+        snippets.add("**/*.rs", "fn", "fn $1() {\n}").unwrap();
+        snippets.add("**/*.md", "h1", "# $0").unwrap();
+
+        assert_eq!(Some("fn $1() {\n}"), snippets.find("src/main.rs", "fn"));
+        assert_eq!(None, snippets.find("src/main.rs", "h1"));
+        assert_eq!(
+            vec!["h1"],
+            snippets.triggers("README.md").collect::<Vec<_>>()
+        );
+    }
+}
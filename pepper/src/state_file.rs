@@ -0,0 +1,225 @@
+use std::{fs, path::Path};
+
+use crate::{
+    config::PersistedState,
+    editor::Editor,
+    editor_utils::{MessageKind, RegisterKey},
+    serialization::{DeserializeError, Serialize},
+};
+
+const STATE_FILE_VERSION: u8 = 1;
+
+const REGISTER_TAG: u8 = 0;
+const COMMAND_HISTORY_TAG: u8 = 1;
+const SEARCH_HISTORY_TAG: u8 = 2;
+const RECENT_FILE_TAG: u8 = 3;
+const MARKER_REGISTER_TAG: u8 = 4;
+
+pub fn load(editor: &mut Editor) {
+    let path = editor.config.state_file.clone();
+    if path.as_str().is_empty() {
+        return;
+    }
+
+    let bytes = match fs::read(path.as_str()) {
+        Ok(bytes) => bytes,
+        Err(_) => return,
+    };
+    let parts = editor.config.state_persist;
+    if deserialize_state(editor, parts, &bytes).is_err() {
+        editor
+            .status_bar
+            .write(MessageKind::Error)
+            .fmt(format_args!("could not load state file '{}'", path));
+    }
+}
+
+pub fn save(editor: &Editor) {
+    let path = Path::new(editor.config.state_file.as_str());
+    if path.as_os_str().is_empty() {
+        return;
+    }
+
+    let mut bytes = Vec::new();
+    serialize_state(editor, editor.config.state_persist, &mut bytes);
+
+    if let Some(parent) = path.parent() {
+        let _ = fs::create_dir_all(parent);
+    }
+    let _ = fs::write(path, &bytes);
+}
+
+fn serialize_state(editor: &Editor, parts: PersistedState, bytes: &mut Vec<u8>) {
+    STATE_FILE_VERSION.serialize(bytes);
+
+    if parts.contains(PersistedState::REGISTERS) {
+        for key in b'a'..=b'z' {
+            let key = match RegisterKey::from_char(key as _) {
+                Some(key) => key,
+                None => continue,
+            };
+            let register = editor.registers.get(key);
+            if !register.is_empty() {
+                let tag = if editor.registers.is_marker(key) {
+                    MARKER_REGISTER_TAG
+                } else {
+                    REGISTER_TAG
+                };
+                tag.serialize(bytes);
+                key.as_u8().serialize(bytes);
+                register.serialize(bytes);
+            }
+        }
+    }
+
+    let histories = [
+        (
+            PersistedState::COMMAND_HISTORY,
+            COMMAND_HISTORY_TAG,
+            &editor.commands.history,
+        ),
+        (
+            PersistedState::SEARCH_HISTORY,
+            SEARCH_HISTORY_TAG,
+            &editor.search_history,
+        ),
+        (
+            PersistedState::RECENT_FILES,
+            RECENT_FILE_TAG,
+            &editor.recent_files,
+        ),
+    ];
+    for (part, tag, history) in histories {
+        if parts.contains(part) {
+            for entry in history.entries() {
+                tag.serialize(bytes);
+                entry.serialize(bytes);
+            }
+        }
+    }
+}
+
+fn deserialize_state(
+    editor: &mut Editor,
+    parts: PersistedState,
+    mut bytes: &[u8],
+) -> Result<(), DeserializeError> {
+    if u8::deserialize(&mut bytes)? != STATE_FILE_VERSION {
+        return Err(DeserializeError::InvalidData);
+    }
+
+    while !bytes.is_empty() {
+        match u8::deserialize(&mut bytes)? {
+            tag @ (REGISTER_TAG | MARKER_REGISTER_TAG) => {
+                let key = u8::deserialize(&mut bytes)?;
+                let key = RegisterKey::from_char(key as _).ok_or(DeserializeError::InvalidData)?;
+                let content = <&str>::deserialize(&mut bytes)?;
+                if parts.contains(PersistedState::REGISTERS) {
+                    let register = if tag == MARKER_REGISTER_TAG {
+                        editor.registers.get_marker_mut(key)
+                    } else {
+                        editor.registers.get_mut(key)
+                    };
+                    register.clear();
+                    register.push_str(content);
+                }
+            }
+            COMMAND_HISTORY_TAG => {
+                let entry = <&str>::deserialize(&mut bytes)?;
+                if parts.contains(PersistedState::COMMAND_HISTORY) {
+                    editor.commands.add_to_history(entry);
+                }
+            }
+            SEARCH_HISTORY_TAG => {
+                let entry = <&str>::deserialize(&mut bytes)?;
+                if parts.contains(PersistedState::SEARCH_HISTORY) {
+                    editor.search_history.add(entry);
+                }
+            }
+            RECENT_FILE_TAG => {
+                let entry = <&str>::deserialize(&mut bytes)?;
+                if parts.contains(PersistedState::RECENT_FILES) {
+                    editor.recent_files.remove(entry);
+                    editor.recent_files.add(entry);
+                }
+            }
+            _ => return Err(DeserializeError::InvalidData),
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::path::PathBuf;
+
+    use crate::editor_utils::SEARCH_REGISTER;
+
+    #[test]
+    fn state_round_trip() {
+        let mut editor = Editor::new(PathBuf::new());
+        editor.registers.get_mut(SEARCH_REGISTER).push_str("needle");
+        editor
+            .registers
+            .get_marker_mut(RegisterKey::from_char('m').unwrap())
+            .push_str("src/main.rs:3,4");
+        editor
+            .registers
+            .get_mut(RegisterKey::from_char('n').unwrap())
+            .push_str("src/lib.rs:1");
+        editor.commands.add_to_history("open file.txt");
+        editor.commands.add_to_history("save");
+        editor.search_history.add("first\nline");
+        editor.recent_files.add("/home/user/file.txt");
+
+        let mut bytes = Vec::new();
+        serialize_state(&editor, PersistedState::ALL, &mut bytes);
+
+        let mut restored = Editor::new(PathBuf::new());
+        assert!(deserialize_state(&mut restored, PersistedState::ALL, &bytes).is_ok());
+        assert_eq!("needle", restored.registers.get(SEARCH_REGISTER));
+        assert_eq!(
+            "src/main.rs:3,4",
+            restored.registers.get(RegisterKey::from_char('m').unwrap())
+        );
+        assert!(restored
+            .registers
+            .is_marker(RegisterKey::from_char('m').unwrap()));
+        assert!(!restored
+            .registers
+            .is_marker(RegisterKey::from_char('n').unwrap()));
+        assert_eq!(
+            "",
+            restored.registers.get(RegisterKey::from_char('b').unwrap())
+        );
+        assert!(restored
+            .commands
+            .history
+            .entries()
+            .eq(["open file.txt", "save"]));
+        assert!(restored.search_history.entries().eq(["first\nline"]));
+        assert!(restored.recent_files.entries().eq(["/home/user/file.txt"]));
+
+        let mut restored = Editor::new(PathBuf::new());
+        assert!(deserialize_state(&mut restored, PersistedState::RECENT_FILES, &bytes).is_ok());
+        assert_eq!("", restored.registers.get(SEARCH_REGISTER));
+        assert!(restored.commands.history.is_empty());
+        assert!(restored.search_history.is_empty());
+        assert_eq!(1, restored.recent_files.len());
+
+        let mut bytes = Vec::new();
+        serialize_state(&editor, PersistedState::SEARCH_HISTORY, &mut bytes);
+        let mut restored = Editor::new(PathBuf::new());
+        assert!(deserialize_state(&mut restored, PersistedState::ALL, &bytes).is_ok());
+        assert_eq!("", restored.registers.get(SEARCH_REGISTER));
+        assert!(restored.recent_files.is_empty());
+        assert_eq!(1, restored.search_history.len());
+
+        let mut restored = Editor::new(PathBuf::new());
+        assert!(deserialize_state(&mut restored, PersistedState::ALL, &bytes[..4]).is_err());
+        assert!(deserialize_state(&mut restored, PersistedState::ALL, &[0]).is_err());
+    }
+}
@@ -78,6 +78,13 @@ pub struct Syntax {
     glob_hash: u64,
     glob: Glob,
     rules: [Pattern; 7],
+    auto_pairs: String,
+    indent_pattern: Pattern,
+    dedent_pattern: Pattern,
+    function_pattern: Pattern,
+    line_comment: String,
+    block_comment_begin: String,
+    block_comment_end: String,
 }
 
 impl Syntax {
@@ -96,6 +103,13 @@ impl Syntax {
                 Pattern::new(),
                 text_pattern,
             ],
+            auto_pairs: String::new(),
+            indent_pattern: Pattern::new(),
+            dedent_pattern: Pattern::new(),
+            function_pattern: Pattern::new(),
+            line_comment: String::new(),
+            block_comment_begin: String::new(),
+            block_comment_end: String::new(),
         }
     }
 
@@ -103,6 +117,13 @@ impl Syntax {
         for r in &mut self.rules {
             r.clear();
         }
+        self.auto_pairs.clear();
+        self.indent_pattern.clear();
+        self.dedent_pattern.clear();
+        self.function_pattern.clear();
+        self.line_comment.clear();
+        self.block_comment_begin.clear();
+        self.block_comment_end.clear();
     }
 
     fn set_glob(&mut self, glob: &str, glob_hash: u64) -> Result<(), InvalidGlobError> {
@@ -114,6 +135,89 @@ impl Syntax {
         self.rules[kind as usize].compile(pattern)
     }
 
+    pub fn set_auto_pairs(&mut self, pairs: &str) -> bool {
+        if pairs.chars().count() % 2 == 1 {
+            return false;
+        }
+        self.auto_pairs.clear();
+        self.auto_pairs.push_str(pairs);
+        true
+    }
+
+    pub fn auto_pairs(&self) -> impl '_ + Iterator<Item = (char, char)> {
+        let mut chars = self.auto_pairs.chars();
+        std::iter::from_fn(move || Some((chars.next()?, chars.next()?)))
+    }
+
+    pub fn set_indent_rule(&mut self, pattern: &str) -> Result<(), PatternError> {
+        self.indent_pattern.compile(pattern)
+    }
+
+    pub fn set_dedent_rule(&mut self, pattern: &str) -> Result<(), PatternError> {
+        self.dedent_pattern.compile(pattern)
+    }
+
+    pub fn set_function_rule(&mut self, pattern: &str) -> Result<(), PatternError> {
+        self.function_pattern.compile(pattern)
+    }
+
+    // the line is matched once from the start so the last match must end at the line end
+    pub fn increases_indentation(&self, line: &str) -> bool {
+        let line = line.trim_end();
+        let anchor = self.indent_pattern.search_anchor();
+        match self.indent_pattern.match_indices(line, anchor).last() {
+            Some(range) => range.end == line.len(),
+            None => false,
+        }
+    }
+
+    pub fn decreases_indentation(&self, line: &str) -> bool {
+        let line = line.trim_start();
+        matches!(self.dedent_pattern.matches(line, 0), MatchResult::Ok(_))
+    }
+
+    pub fn is_dedent_trigger(&self, line: &str) -> bool {
+        let line = line.trim_start();
+        !line.is_empty() && self.dedent_pattern.matches(line, 0) == MatchResult::Ok(line.len())
+    }
+
+    pub fn is_function_start(&self, line: &str) -> bool {
+        let line = line.trim_start();
+        matches!(self.function_pattern.matches(line, 0), MatchResult::Ok(_))
+    }
+
+    pub fn set_line_comment(&mut self, delimiter: &str) {
+        self.line_comment.clear();
+        self.line_comment.push_str(delimiter.trim());
+    }
+
+    pub fn line_comment(&self) -> &str {
+        &self.line_comment
+    }
+
+    // `delimiters` is the begin and end delimiters separated by whitespace or empty to unset them
+    pub fn set_block_comment(&mut self, delimiters: &str) -> bool {
+        let mut parts = delimiters.split_whitespace();
+        let (begin, end) = match (parts.next(), parts.next(), parts.next()) {
+            (None, _, _) => ("", ""),
+            (Some(begin), Some(end), None) => (begin, end),
+            _ => return false,
+        };
+        self.block_comment_begin.clear();
+        self.block_comment_begin.push_str(begin);
+        self.block_comment_end.clear();
+        self.block_comment_end.push_str(end);
+        true
+    }
+
+    pub fn block_comment(&self) -> Option<(&str, &str)> {
+        if self.block_comment_begin.is_empty() {
+            None
+        } else {
+            Some((&self.block_comment_begin, &self.block_comment_end))
+        }
+    }
+
     fn parse_line(
         &self,
         line: &str,
@@ -502,6 +606,89 @@ mod tests {
         assert_token("  ", TokenKind::Text, line, &tokens[5]);
     }
 
+    #[test]
+    fn syntax_auto_pairs() {
+        let mut syntax = Syntax::new();
+        assert_eq!(0, syntax.auto_pairs().count());
+
+        assert!(syntax.set_auto_pairs("<>«»"));
+        assert_eq!(
+            vec![('<', '>'), ('«', '»')],
+            syntax.auto_pairs().collect::<Vec<_>>()
+        );
+
+        assert!(!syntax.set_auto_pairs("()["));
+        assert_eq!(2, syntax.auto_pairs().count());
+
+        assert!(syntax.set_auto_pairs(""));
+        assert_eq!(0, syntax.auto_pairs().count());
+    }
+
+    #[test]
+    fn syntax_indentation_rules() {
+        let mut syntax = Syntax::new();
+        assert!(!syntax.increases_indentation("fn main() {"));
+        assert!(!syntax.decreases_indentation("}"));
+
+        syntax.set_indent_rule("%{|%(|%[").unwrap();
+        syntax.set_dedent_rule("%}|%)|%]|end%b").unwrap();
+
+        assert!(syntax.increases_indentation("fn main() {  "));
+        assert!(syntax.increases_indentation("call("));
+        assert!(!syntax.increases_indentation("{ x }"));
+        assert!(syntax.increases_indentation("a[0] = {"));
+        assert!(!syntax.increases_indentation(""));
+
+        syntax.set_indent_rule(":").unwrap();
+        assert!(syntax.increases_indentation("if a: # b:"));
+        assert!(!syntax.increases_indentation("a: b"));
+
+        assert!(syntax.decreases_indentation("    }"));
+        assert!(syntax.decreases_indentation("  ) + 1"));
+        assert!(syntax.decreases_indentation("end"));
+        assert!(!syntax.decreases_indentation("endpoint"));
+
+        assert!(syntax.is_dedent_trigger("    }"));
+        assert!(!syntax.is_dedent_trigger("    })"));
+        assert!(!syntax.is_dedent_trigger("    "));
+    }
+
+    #[test]
+    fn syntax_function_rule() {
+        let mut syntax = Syntax::new();
+        assert!(!syntax.is_function_start("fn main() {"));
+
+        syntax.set_function_rule("{!(%bfn%b)%w_%(%) }").unwrap();
+        assert!(syntax.is_function_start("fn main() {"));
+        assert!(syntax.is_function_start("    pub(crate) async fn f() {"));
+        assert!(!syntax.is_function_start("let f = my_fn(x);"));
+        assert!(!syntax.is_function_start("buffn x"));
+        assert!(!syntax.is_function_start("// fn"));
+    }
+
+    #[test]
+    fn syntax_comment_delimiters() {
+        let mut syntax = Syntax::new();
+        assert_eq!("", syntax.line_comment());
+        assert_eq!(None, syntax.block_comment());
+
+        syntax.set_line_comment(" // ");
+        assert_eq!("//", syntax.line_comment());
+
+        assert!(syntax.set_block_comment("/* */"));
+        assert_eq!(Some(("/*", "*/")), syntax.block_comment());
+
+        assert!(!syntax.set_block_comment("/*"));
+        assert!(!syntax.set_block_comment("/* */ x"));
+        assert_eq!(Some(("/*", "*/")), syntax.block_comment());
+
+        assert!(syntax.set_block_comment(""));
+        assert_eq!(None, syntax.block_comment());
+
+        syntax.clear_rules();
+        assert_eq!("", syntax.line_comment());
+    }
+
     #[test]
     fn simple_syntax() {
         let mut syntax = Syntax::new();
@@ -1,12 +1,15 @@
 use std::{io, iter};
 
 use crate::{
-    buffer::CharDisplayDistances,
-    buffer_position::{BufferPosition, BufferPositionIndex, BufferRange},
-    buffer_view::{BufferViewHandle, CursorMovementKind},
+    buffer::{char_display_len, BufferHandle, CharDisplayDistances},
+    buffer_position::{BufferPosition, BufferRange},
+    buffer_view::{BufferView, BufferViewHandle, CursorMovementKind},
+    client::{Client, ClientHandle},
+    cursor::Cursor,
     editor::Editor,
     editor_utils::StatusBarDisplay,
     mode::ModeKind,
+    picker::PickerPreviewSource,
     syntax::{Token, TokenKind},
     theme::Color,
 };
@@ -65,17 +68,51 @@ pub fn set_not_underlined(buf: &mut Vec<u8>) {
 pub struct RenderContext<'a> {
     pub editor: &'a Editor,
     pub status_bar_display: &'a StatusBarDisplay<'a, 'a>,
-    pub viewport_size: (u16, u16),
-    pub scroll: BufferPositionIndex,
+    pub client: &'a Client,
     pub has_focus: bool,
+    // the buffer view each client is currently displaying, indexed by client handle
+    pub client_buffer_view_handles: &'a [Option<BufferViewHandle>],
+}
+
+// buffer views of the other clients that are currently displaying `buffer_handle`
+fn other_viewers<'a>(
+    ctx: &'a RenderContext,
+    buffer_handle: BufferHandle,
+) -> impl 'a + Iterator<Item = (ClientHandle, &'a BufferView)> {
+    ctx.client_buffer_view_handles
+        .iter()
+        .enumerate()
+        .filter(move |&(i, _)| i != ctx.client.handle().0 as usize)
+        .filter_map(move |(i, handle)| {
+            let buffer_view = ctx.editor.buffer_views.get((*handle)?);
+            if buffer_view.buffer_handle == buffer_handle {
+                Some((ClientHandle(i as _), buffer_view))
+            } else {
+                None
+            }
+        })
 }
 
 pub fn draw(ctx: &RenderContext, buffer_view_handle: Option<BufferViewHandle>, buf: &mut Vec<u8>) {
     draw_buffer_view(ctx, buffer_view_handle, buf);
+    draw_picker_preview(ctx, buf);
     draw_picker(ctx, buf);
     draw_statusbar(ctx, buffer_view_handle, buf);
 }
 
+fn picker_height(ctx: &RenderContext) -> usize {
+    let entries_height = ctx
+        .client
+        .picker
+        .len()
+        .min(ctx.editor.config.picker_max_height as _);
+    let preview_height = ctx
+        .client
+        .picker_preview
+        .height(ctx.editor.config.picker_preview_height);
+    entries_height + preview_height
+}
+
 fn draw_empty_view(ctx: &RenderContext, buf: &mut Vec<u8>) {
     move_cursor_to(buf, 0, 0);
     buf.extend_from_slice(RESET_STYLE_CODE);
@@ -92,22 +129,13 @@ fn draw_empty_view(ctx: &RenderContext, buf: &mut Vec<u8>) {
         "or `:quit<enter>` to quit",
     ];
 
-    let width = ctx.viewport_size.0 as usize;
-    let height = ctx.viewport_size.1.saturating_sub(1) as usize;
+    let width = ctx.client.viewport_size.0 as usize;
+    let height = ctx.client.viewport_size.1.saturating_sub(1) as usize;
 
     let margin_top = (height.saturating_sub(message_lines.len())) / 2;
     let margin_bottom = height - margin_top - message_lines.len();
 
-    let margin_bottom = if ctx.has_focus {
-        let picker_height = ctx
-            .editor
-            .picker
-            .len()
-            .min(ctx.editor.config.picker_max_height as _);
-        margin_bottom.saturating_sub(picker_height)
-    } else {
-        margin_bottom
-    };
+    let margin_bottom = margin_bottom.saturating_sub(picker_height(ctx));
 
     let mut visual_empty = [0; 4];
     let visual_empty = ctx
@@ -159,25 +187,17 @@ fn draw_buffer_view(
     let cursors = &buffer_view.cursors[..];
     let active_line_index = buffer_view.cursors.main_cursor().position.line_index as usize;
 
-    let tab_size = ctx.editor.config.tab_size.get();
-
-    let draw_width = ctx.viewport_size.0 as usize;
-    let draw_height = ctx.viewport_size.1.saturating_sub(1);
-    let draw_height = if ctx.has_focus {
-        let picker_height = ctx
-            .editor
-            .picker
-            .len()
-            .min(ctx.editor.config.picker_max_height as _);
-        draw_height.saturating_sub(picker_height as _)
-    } else {
-        draw_height
-    };
+    let config = buffer.config(&ctx.editor.config, &ctx.editor.scoped_configs);
+    let tab_size = config.tab_size.get();
+
+    let draw_width = ctx.client.viewport_size.0 as usize;
+    let draw_height = ctx.client.viewport_size.1.saturating_sub(1);
+    let draw_height = draw_height.saturating_sub(picker_height(ctx) as _);
 
     let cursor_color = if ctx.has_focus {
-        match ctx.editor.mode.kind() {
+        match ctx.client.mode.kind() {
             ModeKind::Insert => ctx.editor.theme.insert_cursor,
-            _ => match ctx.editor.mode.normal_state.movement_kind {
+            _ => match ctx.client.mode.normal_state.movement_kind {
                 CursorMovementKind::PositionAndAnchor => ctx.editor.theme.normal_cursor,
                 CursorMovementKind::PositionOnly => ctx.editor.theme.select_cursor,
             },
@@ -188,6 +208,12 @@ fn draw_buffer_view(
 
     let cursors_end_index = cursors.len().saturating_sub(1);
 
+    let mut other_cursors: Vec<Cursor> = Vec::new();
+    for (_, other_buffer_view) in other_viewers(ctx, buffer_view.buffer_handle) {
+        other_cursors.extend_from_slice(&other_buffer_view.cursors[..]);
+    }
+    other_cursors.sort_unstable_by_key(|c| c.to_range().from);
+
     let buffer_content = buffer.content();
     let highlighted_buffer = buffer.highlighted();
     let search_ranges = buffer.search_ranges();
@@ -197,7 +223,7 @@ fn draw_buffer_view(
     let lints_end_index = lints.len().saturating_sub(1);
 
     let mut scroll_offset = BufferPosition::zero();
-    let mut scroll_padding_top = ctx.scroll as usize;
+    let mut scroll_padding_top = ctx.client.scroll as usize;
     for (line_index, display_len) in buffer_content.line_display_lens().iter().enumerate() {
         scroll_offset.line_index = line_index as _;
 
@@ -237,6 +263,11 @@ fn draw_buffer_view(
         }
     }
 
+    let mut current_other_cursor_index = other_cursors
+        .iter()
+        .position(|c| scroll_offset <= c.to_range().to)
+        .unwrap_or(other_cursors.len());
+
     let mut current_search_range_index = search_ranges.len();
     let mut current_search_range = BufferRange::zero();
     for (i, &range) in search_ranges.iter().enumerate() {
@@ -264,33 +295,25 @@ fn draw_buffer_view(
     let mut char_buf = [0; std::mem::size_of::<char>()];
 
     let mut visual_empty = [0; 4];
-    let visual_empty = ctx
-        .editor
-        .config
+    let visual_empty = config
         .visual_empty
         .encode_utf8(&mut visual_empty)
         .as_bytes();
 
     let mut visual_space = [0; 4];
-    let visual_space = ctx
-        .editor
-        .config
+    let visual_space = config
         .visual_space
         .encode_utf8(&mut visual_space)
         .as_bytes();
 
     let mut visual_tab_first = [0; 4];
-    let visual_tab_first = ctx
-        .editor
-        .config
+    let visual_tab_first = config
         .visual_tab_first
         .encode_utf8(&mut visual_tab_first)
         .as_bytes();
 
     let mut visual_tab_repeat = [0; 4];
-    let visual_tab_repeat = ctx
-        .editor
-        .config
+    let visual_tab_repeat = config
         .visual_tab_repeat
         .encode_utf8(&mut visual_tab_repeat)
         .as_bytes();
@@ -308,6 +331,8 @@ fn draw_buffer_view(
             Selection(TokenKind),
             Highlight,
             Cursor,
+            OtherSelection,
+            OtherCursor,
         }
 
         if lines_drawn_count == draw_height {
@@ -349,16 +374,7 @@ fn draw_buffer_view(
                 last_line_token.kind
             };
 
-            let text_color = match token_kind {
-                TokenKind::Keyword => ctx.editor.theme.token_keyword,
-                TokenKind::Type => ctx.editor.theme.token_type,
-                TokenKind::Symbol => ctx.editor.theme.token_symbol,
-                TokenKind::Literal => ctx.editor.theme.token_literal,
-                TokenKind::String => ctx.editor.theme.token_string,
-                TokenKind::Comment => ctx.editor.theme.token_comment,
-                TokenKind::Text => ctx.editor.theme.token_text,
-                TokenKind::Whitespace => ctx.editor.theme.token_whitespace,
-            };
+            let text_color = token_color(ctx, token_kind);
 
             while current_cursor_index < cursors_end_index
                 && current_cursor_range.to < char_position
@@ -371,6 +387,22 @@ fn draw_buffer_view(
             let inside_cursor_range = current_cursor_range.from <= char_position
                 && char_position < current_cursor_range.to;
 
+            while current_other_cursor_index < other_cursors.len()
+                && other_cursors[current_other_cursor_index].to_range().to < char_position
+            {
+                current_other_cursor_index += 1;
+            }
+            let mut on_other_cursor = false;
+            let mut inside_other_cursor_range = false;
+            for cursor in &other_cursors[current_other_cursor_index..] {
+                let range = cursor.to_range();
+                if char_position < range.from {
+                    break;
+                }
+                on_other_cursor |= cursor.position == char_position;
+                inside_other_cursor_range |= char_position < range.to;
+            }
+
             while current_search_range.to <= char_position
                 && current_search_range_index < search_ranges_end_index
             {
@@ -408,6 +440,18 @@ fn draw_buffer_view(
                     set_background_color(buf, text_color);
                     set_foreground_color(buf, background_color);
                 }
+            } else if on_other_cursor {
+                if draw_state != DrawState::OtherCursor {
+                    draw_state = DrawState::OtherCursor;
+                    set_background_color(buf, ctx.editor.theme.inactive_cursor);
+                    set_foreground_color(buf, text_color);
+                }
+            } else if inside_other_cursor_range {
+                if draw_state != DrawState::OtherSelection {
+                    draw_state = DrawState::OtherSelection;
+                    set_background_color(buf, background_color);
+                    set_foreground_color(buf, ctx.editor.theme.inactive_cursor);
+                }
             } else if inside_search_range {
                 if draw_state != DrawState::Highlight {
                     draw_state = DrawState::Highlight;
@@ -446,8 +490,8 @@ fn draw_buffer_view(
                 }
             }
 
-            if x > ctx.viewport_size.0 as _ {
-                x -= ctx.viewport_size.0 as usize;
+            if x > ctx.client.viewport_size.0 as _ {
+                x -= ctx.client.viewport_size.0 as usize;
                 lines_drawn_count += 1;
                 if lines_drawn_count > draw_height {
                     lines_drawn_count = draw_height;
@@ -461,7 +505,7 @@ fn draw_buffer_view(
         scroll_offset.column_byte_index = 0;
         set_background_color(buf, background_color);
 
-        if x < ctx.viewport_size.0 as _ {
+        if x < ctx.client.viewport_size.0 as _ {
             clear_until_new_line(buf);
         }
 
@@ -479,17 +523,139 @@ fn draw_buffer_view(
     }
 }
 
-fn draw_picker(ctx: &RenderContext, buf: &mut Vec<u8>) {
-    if !ctx.has_focus {
+fn draw_picker_preview(ctx: &RenderContext, buf: &mut Vec<u8>) {
+    let preview = &ctx.client.picker_preview;
+    let height = preview.height(ctx.editor.config.picker_preview_height);
+    if height == 0 {
         return;
     }
 
-    let cursor = ctx.editor.picker.cursor().unwrap_or(usize::MAX - 1);
-    let scroll = ctx.editor.picker.scroll();
+    let (content, highlighted, tab_size) = match preview.source() {
+        PickerPreviewSource::None => return,
+        PickerPreviewSource::Buffer(handle) => match ctx.editor.buffers.try_get(handle) {
+            Some(buffer) => {
+                let config = buffer.config(&ctx.editor.config, &ctx.editor.scoped_configs);
+                (buffer.content(), buffer.highlighted(), config.tab_size)
+            }
+            None => (
+                preview.content(),
+                preview.highlighted(),
+                ctx.editor.config.tab_size,
+            ),
+        },
+        PickerPreviewSource::Content => (
+            preview.content(),
+            preview.highlighted(),
+            ctx.editor.config.tab_size,
+        ),
+    };
 
-    let width = ctx.viewport_size.0 as _;
-    let height = ctx
+    let tab_size = tab_size.get() as usize;
+    let width = ctx.client.viewport_size.0 as usize;
+    let lines = content.lines();
+    let focused_line_index = preview.line_index().min(lines.len() - 1);
+    let first_line_index = focused_line_index.saturating_sub(height / 2);
+
+    let mut visual_empty = [0; 4];
+    let visual_empty = ctx
         .editor
+        .config
+        .visual_empty
+        .encode_utf8(&mut visual_empty)
+        .as_bytes();
+
+    let mut char_buf = [0; std::mem::size_of::<char>()];
+
+    for line_index in first_line_index..first_line_index + height {
+        let background_color = if line_index == focused_line_index {
+            ctx.editor.theme.active_line_background
+        } else {
+            ctx.editor.theme.background
+        };
+        set_background_color(buf, background_color);
+
+        let line = match lines.get(line_index) {
+            Some(line) => line.as_str(),
+            None => {
+                set_foreground_color(buf, ctx.editor.theme.token_whitespace);
+                buf.extend_from_slice(visual_empty);
+                clear_until_new_line(buf);
+                move_cursor_to_next_line(buf);
+                continue;
+            }
+        };
+
+        let mut x = 0;
+        let mut current_token_kind = None;
+        let mut line_tokens = highlighted.line_tokens(line_index).iter();
+        let mut last_line_token = Token::default();
+
+        for (char_index, c) in line.char_indices() {
+            let token_kind = if c.is_ascii_whitespace() {
+                TokenKind::Whitespace
+            } else {
+                if !last_line_token.contains(char_index as _) {
+                    for token in &mut line_tokens {
+                        if token.contains(char_index as _) {
+                            last_line_token = token.clone();
+                            break;
+                        }
+                    }
+                }
+                last_line_token.kind
+            };
+
+            if current_token_kind != Some(token_kind) {
+                current_token_kind = Some(token_kind);
+                set_foreground_color(buf, token_color(ctx, token_kind));
+            }
+
+            let char_width = match c {
+                '\t' => tab_size,
+                _ => char_display_len(c) as _,
+            };
+            if x + char_width > width {
+                break;
+            }
+            x += char_width;
+
+            match c {
+                '\t' => {
+                    for _ in 0..tab_size {
+                        buf.push(b' ');
+                    }
+                }
+                _ => buf.extend_from_slice(c.encode_utf8(&mut char_buf).as_bytes()),
+            }
+        }
+
+        if x < width {
+            clear_until_new_line(buf);
+        }
+        move_cursor_to_next_line(buf);
+    }
+}
+
+fn token_color(ctx: &RenderContext, kind: TokenKind) -> Color {
+    match kind {
+        TokenKind::Keyword => ctx.editor.theme.token_keyword,
+        TokenKind::Type => ctx.editor.theme.token_type,
+        TokenKind::Symbol => ctx.editor.theme.token_symbol,
+        TokenKind::Literal => ctx.editor.theme.token_literal,
+        TokenKind::String => ctx.editor.theme.token_string,
+        TokenKind::Comment => ctx.editor.theme.token_comment,
+        TokenKind::Text => ctx.editor.theme.token_text,
+        TokenKind::Whitespace => ctx.editor.theme.token_whitespace,
+    }
+}
+
+fn draw_picker(ctx: &RenderContext, buf: &mut Vec<u8>) {
+    let cursor = ctx.client.picker.cursor().unwrap_or(usize::MAX - 1);
+    let scroll = ctx.client.picker.scroll();
+
+    let width = ctx.client.viewport_size.0 as _;
+    let height = ctx
+        .client
         .picker
         .len()
         .min(ctx.editor.config.picker_max_height as _);
@@ -497,12 +663,13 @@ fn draw_picker(ctx: &RenderContext, buf: &mut Vec<u8>) {
     let background_normal_color = ctx.editor.theme.statusbar_inactive_background;
     let background_selected_color = ctx.editor.theme.statusbar_active_background;
     let foreground_color = ctx.editor.theme.token_text;
+    let foreground_marked_color = ctx.editor.theme.highlight;
 
     set_background_color(buf, background_normal_color);
     set_foreground_color(buf, foreground_color);
 
     for (i, entry) in ctx
-        .editor
+        .client
         .picker
         .entries(&ctx.editor.word_database)
         .enumerate()
@@ -515,6 +682,11 @@ fn draw_picker(ctx: &RenderContext, buf: &mut Vec<u8>) {
             set_background_color(buf, background_normal_color);
         }
 
+        let is_marked = ctx.client.picker.is_marked(i);
+        if is_marked {
+            set_foreground_color(buf, foreground_marked_color);
+        }
+
         let mut x = 0;
 
         fn print_char(buf: &mut Vec<u8>, x: &mut usize, c: char) {
@@ -545,6 +717,10 @@ fn draw_picker(ctx: &RenderContext, buf: &mut Vec<u8>) {
         }
         x = 0;
 
+        if is_marked {
+            set_foreground_color(buf, foreground_color);
+        }
+
         if x < width {
             clear_until_new_line(buf);
         }
@@ -561,11 +737,13 @@ fn draw_statusbar(
     let needs_save;
     let main_cursor_position;
     let search_ranges;
+    let buffer_handle;
 
     match buffer_view_handle {
         Some(handle) => {
             let buffer_view = ctx.editor.buffer_views.get(handle);
             let buffer = ctx.editor.buffers.get(buffer_view.buffer_handle);
+            buffer_handle = Some(buffer.handle());
 
             view_name = buffer.path.to_str().unwrap_or("");
             needs_save = buffer.needs_save();
@@ -577,6 +755,7 @@ fn draw_statusbar(
             needs_save = false;
             main_cursor_position = BufferPosition::zero();
             search_ranges = &[];
+            buffer_handle = None;
         }
     }
 
@@ -594,83 +773,80 @@ fn draw_statusbar(
     }
     set_foreground_color(buf, foreground_color);
 
-    let x = if ctx.has_focus {
-        let message_is_empty = ctx.status_bar_display.lines.is_empty();
-        match ctx.editor.mode.kind() {
-            ModeKind::Normal if message_is_empty => match ctx.editor.recording_macro {
-                Some(key) => {
-                    let text = b"recording macro ";
-                    let key = key.as_u8();
-                    buf.extend_from_slice(text);
-                    buf.push(key);
-                    Some(text.len() + 1)
+    let message_is_empty = ctx.status_bar_display.lines.is_empty();
+    let x = match ctx.client.mode.kind() {
+        ModeKind::Command | ModeKind::Picker | ModeKind::ReadLine => {
+            let read_line = &ctx.client.read_line;
+
+            set_background_color(buf, background_innactive_color);
+            set_foreground_color(buf, foreground_color);
+            buf.extend_from_slice(read_line.prompt().as_bytes());
+            set_background_color(buf, background_active_color);
+            set_foreground_color(buf, foreground_color);
+            buf.extend_from_slice(read_line.input().as_bytes());
+            set_background_color(buf, cursor_color);
+            buf.push(b' ');
+            set_background_color(buf, background_active_color);
+            None
+        }
+        _ if !ctx.has_focus => Some(0),
+        ModeKind::Normal if message_is_empty => match ctx.editor.recording_macro {
+            Some(key) => {
+                let text = b"recording macro ";
+                let key = key.as_u8();
+                buf.extend_from_slice(text);
+                buf.push(key);
+                Some(text.len() + 1)
+            }
+            None => match search_ranges {
+                [] => Some(0),
+                _ => {
+                    let previous_len = buf.len();
+                    let search_index = ctx.client.mode.normal_state.search_index + 1;
+                    let _ = write!(buf, " [{}/{}]", search_index, search_ranges.len());
+                    Some(buf.len() - previous_len)
                 }
-                None => match search_ranges {
-                    [] => Some(0),
-                    _ => {
-                        let previous_len = buf.len();
-                        let search_index = ctx.editor.mode.normal_state.search_index + 1;
-                        let _ = write!(buf, " [{}/{}]", search_index, search_ranges.len());
-                        Some(buf.len() - previous_len)
-                    }
-                },
             },
-            ModeKind::Insert if message_is_empty => {
-                let text = b"-- INSERT --";
-                buf.extend_from_slice(text);
-                Some(text.len())
+        },
+        ModeKind::Insert if message_is_empty => {
+            let text = b"-- INSERT --";
+            buf.extend_from_slice(text);
+            Some(text.len())
+        }
+        _ => {
+            let line_count =
+                ctx.status_bar_display.lines.len() + ctx.status_bar_display.prefix_is_line as usize;
+            if line_count > 1 {
+                move_cursor_up(buf, (line_count - 1) as _);
             }
-            ModeKind::Command | ModeKind::Picker | ModeKind::ReadLine => {
-                let read_line = &ctx.editor.read_line;
 
+            let prefix = ctx.status_bar_display.prefix.as_bytes();
+            if !prefix.is_empty() {
                 set_background_color(buf, background_innactive_color);
                 set_foreground_color(buf, foreground_color);
-                buf.extend_from_slice(read_line.prompt().as_bytes());
+                buf.extend_from_slice(prefix);
+
+                if ctx.status_bar_display.prefix_is_line {
+                    clear_until_new_line(buf);
+                    move_cursor_to_next_line(buf);
+                }
+
                 set_background_color(buf, background_active_color);
                 set_foreground_color(buf, foreground_color);
-                buf.extend_from_slice(read_line.input().as_bytes());
-                set_background_color(buf, cursor_color);
-                buf.push(b' ');
-                set_background_color(buf, background_active_color);
-                None
             }
-            _ => {
-                let line_count = ctx.status_bar_display.lines.len()
-                    + ctx.status_bar_display.prefix_is_line as usize;
-                if line_count > 1 {
-                    move_cursor_up(buf, (line_count - 1) as _);
-                }
-
-                let prefix = ctx.status_bar_display.prefix.as_bytes();
-                if !prefix.is_empty() {
-                    set_background_color(buf, background_innactive_color);
-                    set_foreground_color(buf, foreground_color);
-                    buf.extend_from_slice(prefix);
-
-                    if ctx.status_bar_display.prefix_is_line {
-                        clear_until_new_line(buf);
-                        move_cursor_to_next_line(buf);
-                    }
-
-                    set_background_color(buf, background_active_color);
-                    set_foreground_color(buf, foreground_color);
-                }
 
-                if let Some((first, rest)) = ctx.status_bar_display.lines.split_first() {
-                    buf.extend_from_slice(first.as_bytes());
+            if let Some((first, rest)) = ctx.status_bar_display.lines.split_first() {
+                buf.extend_from_slice(first.as_bytes());
+                clear_until_new_line(buf);
+                for &line in rest {
+                    move_cursor_to_next_line(buf);
+                    buf.extend_from_slice(line.as_bytes());
                     clear_until_new_line(buf);
-                    for &line in rest {
-                        move_cursor_to_next_line(buf);
-                        buf.extend_from_slice(line.as_bytes());
-                        clear_until_new_line(buf);
-                    }
                 }
-
-                None
             }
+
+            None
         }
-    } else {
-        Some(0)
     };
 
     if let Some(x) = x {
@@ -681,17 +857,17 @@ fn draw_statusbar(
             }
         }
 
-        let available_width = ctx.viewport_size.0 as usize - x;
+        let available_width = ctx.client.viewport_size.0 as usize - x;
         let half_available_width = available_width / 2;
 
         let status_start_index = buf.len();
 
         if ctx.has_focus {
-            let param_count = ctx.editor.mode.normal_state.count;
-            if param_count > 0 && matches!(ctx.editor.mode.kind(), ModeKind::Normal) {
+            let param_count = ctx.client.mode.normal_state.count;
+            if param_count > 0 && matches!(ctx.client.mode.kind(), ModeKind::Normal) {
                 let _ = write!(buf, "{}", param_count);
             }
-            for key in ctx.editor.buffered_keys.as_slice() {
+            for key in ctx.client.buffered_keys.as_slice() {
                 let _ = write!(buf, "{}", key);
             }
             buf.push(b' ');
@@ -712,6 +888,16 @@ fn draw_statusbar(
             let column_number = main_cursor_position.column_byte_index + 1;
             let _ = write!(buf, ":{},{}", line_number, column_number);
         }
+        match buffer_handle {
+            Some(buffer_handle) if ctx.editor.config.status_bar_show_viewers => {
+                let mut separator = " viewers:";
+                for (client_handle, _) in other_viewers(ctx, buffer_handle) {
+                    let _ = write!(buf, "{}{}", separator, client_handle.0);
+                    separator = ",";
+                }
+            }
+            _ => (),
+        }
         buf.push(b' ');
 
         let status = match std::str::from_utf8(&buf[status_start_index..]) {
@@ -79,6 +79,15 @@ impl<'a> DoubleEndedIterator for WordIter<'a> {
 struct Word {
     text: String,
     count: usize,
+    accepted_count: u32,
+    last_accepted_tick: u32,
+}
+
+#[derive(Clone, Copy)]
+pub struct WordUsage {
+    pub count: usize,
+    pub accepted_count: u32,
+    pub ticks_since_accepted: Option<u32>,
 }
 
 #[derive(PartialEq, Eq)]
@@ -148,6 +157,7 @@ pub struct WordDatabase {
     words: Vec<Word>,
     free_indices: Vec<usize>,
     hash_to_index: HashMap<WordHash, usize, WordHasher>,
+    accept_tick: u32,
 }
 
 impl WordDatabase {
@@ -156,6 +166,7 @@ impl WordDatabase {
             words: Vec::with_capacity(512),
             free_indices: Vec::new(),
             hash_to_index: HashMap::with_hasher(WordHasher(0)),
+            accept_tick: 0,
         }
     }
 
@@ -173,12 +184,16 @@ impl WordDatabase {
                     w.text.clear();
                     w.text.push_str(word);
                     w.count = 1;
+                    w.accepted_count = 0;
+                    w.last_accepted_tick = 0;
                 }
                 None => {
                     entry.insert(self.words.len());
                     self.words.push(Word {
                         text: word.into(),
                         count: 1,
+                        accepted_count: 0,
+                        last_accepted_tick: 0,
                     });
                 }
             },
@@ -202,6 +217,35 @@ impl WordDatabase {
         &self.words[index].text
     }
 
+    pub fn index_of(&self, word: &str) -> Option<usize> {
+        self.hash_to_index.get(&WordHash::new(word)).copied()
+    }
+
+    pub fn accept(&mut self, index: usize) {
+        let word = &mut self.words[index];
+        if word.count == 0 {
+            return;
+        }
+
+        self.accept_tick = self.accept_tick.wrapping_add(1);
+        word.accepted_count = word.accepted_count.saturating_add(1);
+        word.last_accepted_tick = self.accept_tick;
+    }
+
+    pub fn usage(&self, index: usize) -> WordUsage {
+        let word = &self.words[index];
+        let ticks_since_accepted = if word.accepted_count > 0 {
+            Some(self.accept_tick.wrapping_sub(word.last_accepted_tick))
+        } else {
+            None
+        };
+        WordUsage {
+            count: word.count,
+            accepted_count: word.accepted_count,
+            ticks_since_accepted,
+        }
+    }
+
     pub fn word_indices(&self) -> WordIndicesIter {
         WordIndicesIter {
             words: &self.words,
@@ -293,4 +337,33 @@ mod tests {
         words.remove("first");
         assert_eq!(1, unique_word_count(&words));
     }
+
+    #[test]
+    fn word_database_usage() {
+        let mut words = WordDatabase::new();
+        words.add("first");
+        words.add("second");
+        words.add("second");
+
+        let first = words.index_of("first").unwrap();
+        let second = words.index_of("second").unwrap();
+        assert_eq!(None, words.index_of("third"));
+
+        assert_eq!(1, words.usage(first).count);
+        assert_eq!(2, words.usage(second).count);
+        assert_eq!(None, words.usage(first).ticks_since_accepted);
+
+        words.accept(first);
+        words.accept(second);
+        assert_eq!(Some(1), words.usage(first).ticks_since_accepted);
+        assert_eq!(Some(0), words.usage(second).ticks_since_accepted);
+        assert_eq!(1, words.usage(second).accepted_count);
+
+        words.remove("first");
+        words.add("third");
+        let third = words.index_of("third").unwrap();
+        assert_eq!(first, third);
+        assert_eq!(0, words.usage(third).accepted_count);
+        assert_eq!(None, words.usage(third).ticks_since_accepted);
+    }
 }
@@ -1,14 +1,14 @@
 use std::{
     fmt,
     fs::File,
-    io,
+    io, iter,
     ops::Range,
     path::{Path, PathBuf},
     str::FromStr,
 };
 
 use pepper::{
-    buffer::{BufferHandle, BufferProperties},
+    buffer::{BufferHandle, BufferProperties, IndentationStyle},
     buffer_position::{BufferPosition, BufferRange},
     buffer_view::BufferViewHandle,
     client,
@@ -388,6 +388,7 @@ pub(crate) enum RequestState {
         context_len: usize,
     },
     Rename {
+        client_handle: client::ClientHandle,
         buffer_handle: BufferHandle,
         buffer_position: BufferPosition,
     },
@@ -395,15 +396,20 @@ pub(crate) enum RequestState {
         buffer_handle: BufferHandle,
         buffer_position: BufferPosition,
     },
-    CodeAction,
+    CodeAction {
+        client_handle: client::ClientHandle,
+    },
     FinishCodeAction,
     DocumentSymbols {
+        client_handle: client::ClientHandle,
         buffer_view_handle: BufferViewHandle,
     },
     FinishDocumentSymbols {
         buffer_view_handle: BufferViewHandle,
     },
-    WorkspaceSymbols,
+    WorkspaceSymbols {
+        client_handle: client::ClientHandle,
+    },
     FinishWorkspaceSymbols,
     Formatting {
         buffer_handle: BufferHandle,
@@ -684,6 +690,7 @@ impl Client {
         plugin_handle: PluginHandle,
         buffer_handle: BufferHandle,
         buffer_position: BufferPosition,
+        client_handle: client::ClientHandle,
     ) {
         if !self.server_capabilities.rename_provider.on || !self.request_state.is_idle() {
             return;
@@ -705,6 +712,7 @@ impl Client {
 
         if self.server_capabilities.rename_provider.prepare_provider {
             self.request_state = RequestState::Rename {
+                client_handle,
                 buffer_handle,
                 buffer_position,
             };
@@ -715,11 +723,16 @@ impl Client {
                 buffer_position,
             };
 
-            read_line::enter_rename_mode(ctx, plugin_handle, "", self);
+            read_line::enter_rename_mode(ctx, plugin_handle, client_handle, "", self);
         }
     }
 
-    pub(crate) fn finish_rename(&mut self, editor: &Editor, platform: &mut Platform) {
+    pub(crate) fn finish_rename(
+        &mut self,
+        editor: &Editor,
+        platform: &mut Platform,
+        new_name: &str,
+    ) {
         let (buffer_handle, buffer_position) = match self.request_state {
             RequestState::FinishRename {
                 buffer_handle,
@@ -737,7 +750,7 @@ impl Client {
         let buffer = editor.buffers.get(buffer_handle);
         let text_document = util::text_document_with_id(&self.root, &buffer.path, &mut self.json);
         let position = DocumentPosition::from_buffer_position(buffer_position);
-        let new_name = self.json.create_string(editor.read_line.input());
+        let new_name = self.json.create_string(new_name);
 
         let mut params = JsonObject::default();
         params.set("textDocument".into(), text_document.into(), &mut self.json);
@@ -758,6 +771,7 @@ impl Client {
         plugin_handle: PluginHandle,
         buffer_handle: BufferHandle,
         range: BufferRange,
+        client_handle: client::ClientHandle,
     ) {
         if !self.server_capabilities.code_action_provider.0 || !self.request_state.is_idle() {
             return;
@@ -806,11 +820,11 @@ impl Client {
         );
         params.set("context".into(), context.into(), &mut self.json);
 
-        self.request_state = RequestState::CodeAction;
+        self.request_state = RequestState::CodeAction { client_handle };
         self.request(platform, "textDocument/codeAction", params);
     }
 
-    pub(crate) fn finish_code_action(&mut self, editor: &mut Editor, index: usize) {
+    pub(crate) fn finish_code_action(&mut self, editor: &mut Editor, indices: &[usize]) {
         match self.request_state {
             RequestState::FinishCodeAction => (),
             _ => return,
@@ -825,14 +839,16 @@ impl Client {
             Ok(actions) => actions,
             Err(_) => return,
         };
-        if let Some(edit) = code_actions
+        for (i, edit) in code_actions
             .elements(&self.json)
             .filter_map(|a| DocumentCodeAction::from_json(a, &self.json).ok())
             .filter(|a| !a.disabled)
             .map(|a| a.edit)
-            .nth(index)
+            .enumerate()
         {
-            edit.apply(editor, &mut self.temp_edits, &self.root, &self.json);
+            if indices.contains(&i) {
+                edit.apply(editor, &mut self.temp_edits, &self.root, &self.json);
+            }
         }
     }
 
@@ -841,6 +857,7 @@ impl Client {
         editor: &Editor,
         platform: &mut Platform,
         buffer_view_handle: BufferViewHandle,
+        client_handle: client::ClientHandle,
     ) {
         if !self.server_capabilities.document_symbol_provider.0 || !self.request_state.is_idle() {
             return;
@@ -855,7 +872,10 @@ impl Client {
         let mut params = JsonObject::default();
         params.set("textDocument".into(), text_document.into(), &mut self.json);
 
-        self.request_state = RequestState::DocumentSymbols { buffer_view_handle };
+        self.request_state = RequestState::DocumentSymbols {
+            client_handle,
+            buffer_view_handle,
+        };
         self.request(platform, "textDocument/documentSymbol", params);
     }
 
@@ -864,7 +884,7 @@ impl Client {
         editor: &mut Editor,
         clients: &mut client::ClientManager,
         client_handle: client::ClientHandle,
-        index: usize,
+        indices: &[usize],
     ) {
         let buffer_view_handle = match self.request_state {
             RequestState::FinishDocumentSymbols { buffer_view_handle } => buffer_view_handle,
@@ -905,21 +925,37 @@ impl Client {
             Err(index)
         }
 
-        if let Ok(position) = find_symbol_position(symbols, &self.json, index) {
-            NavigationHistory::save_snapshot(clients.get_mut(client_handle), &editor.buffer_views);
+        let mut positions = indices.iter().filter_map(|&i| {
+            find_symbol_position(symbols.clone(), &self.json, i)
+                .ok()
+                .map(DocumentPosition::into_buffer_position)
+        });
+        let main_position = match positions.next() {
+            Some(position) => position,
+            None => return,
+        };
 
-            let buffer_view = editor.buffer_views.get_mut(buffer_view_handle);
-            let position = position.into_buffer_position();
-            let mut cursors = buffer_view.cursors.mut_guard();
-            cursors.clear();
+        NavigationHistory::save_snapshot(clients.get_mut(client_handle), &editor.buffer_views);
+
+        let buffer_view = editor.buffer_views.get_mut(buffer_view_handle);
+        let mut cursors = buffer_view.cursors.mut_guard();
+        cursors.clear();
+        for position in iter::once(main_position).chain(positions) {
             cursors.add(Cursor {
                 anchor: position,
                 position,
             });
         }
+        cursors.set_main_cursor_near_position(main_position);
     }
 
-    pub fn workspace_symbols(&mut self, editor: &Editor, platform: &mut Platform, query: &str) {
+    pub fn workspace_symbols(
+        &mut self,
+        editor: &Editor,
+        platform: &mut Platform,
+        query: &str,
+        client_handle: client::ClientHandle,
+    ) {
         if !self.server_capabilities.workspace_symbol_provider.0 || !self.request_state.is_idle() {
             return;
         }
@@ -930,7 +966,7 @@ impl Client {
         let mut params = JsonObject::default();
         params.set("query".into(), query.into(), &mut self.json);
 
-        self.request_state = RequestState::WorkspaceSymbols;
+        self.request_state = RequestState::WorkspaceSymbols { client_handle };
         self.request(platform, "workspace/symbol", params);
     }
 
@@ -939,7 +975,7 @@ impl Client {
         editor: &mut Editor,
         clients: &mut client::ClientManager,
         client_handle: client::ClientHandle,
-        index: usize,
+        indices: &[usize],
     ) {
         self.request_state = RequestState::Idle;
         if !self.server_capabilities.workspace_symbol_provider.0 {
@@ -951,14 +987,18 @@ impl Client {
             Ok(symbols) => symbols,
             Err(_) => return,
         };
-        if let Some(symbol) = symbols
+        for (i, symbol) in symbols
             .elements(&self.json)
             .filter_map(|s| DocumentSymbolInformation::from_json(s, &self.json).ok())
-            .nth(index)
+            .enumerate()
         {
+            if !indices.contains(&i) {
+                continue;
+            }
+
             let path = match Uri::parse(&self.root, symbol.uri.as_str(&self.json)) {
                 Ok(Uri::Path(path)) => path,
-                Err(_) => return,
+                Err(_) => continue,
             };
 
             match editor.buffer_view_handle_from_path(
@@ -1001,17 +1041,19 @@ impl Client {
 
         util::send_pending_did_change(self, editor, platform);
 
-        let buffer_path = &editor.buffers.get(buffer_handle).path;
-        let text_document = util::text_document_with_id(&self.root, buffer_path, &mut self.json);
+        let buffer = editor.buffers.get(buffer_handle);
+        let config = buffer.config(&editor.config, &editor.scoped_configs);
+        let indentation_style = buffer.indentation_style(&config, &editor.scoped_configs);
+        let text_document = util::text_document_with_id(&self.root, &buffer.path, &mut self.json);
         let mut options = JsonObject::default();
         options.set(
             "tabSize".into(),
-            JsonValue::Integer(editor.config.tab_size.get() as _),
+            JsonValue::Integer(indentation_style.width(config.tab_size.get()) as _),
             &mut self.json,
         );
         options.set(
             "insertSpaces".into(),
-            (!editor.config.indent_with_tabs).into(),
+            (indentation_style != IndentationStyle::Tabs).into(),
             &mut self.json,
         );
         options.set("trimTrailingWhitespace".into(), true.into(), &mut self.json);
@@ -691,11 +691,12 @@ pub(crate) fn on_response(
             Ok(())
         }
         "textDocument/prepareRename" => {
-            let (buffer_handle, buffer_position) = match client.request_state {
+            let (client_handle, buffer_handle, buffer_position) = match client.request_state {
                 RequestState::Rename {
+                    client_handle,
                     buffer_handle,
                     buffer_position,
-                } => (buffer_handle, buffer_position),
+                } => (client_handle, buffer_handle, buffer_position),
                 _ => return Ok(()),
             };
             client.request_state = RequestState::Idle;
@@ -744,7 +745,7 @@ pub(crate) fn on_response(
                 }
             }
 
-            read_line::enter_rename_mode(ctx, plugin_handle, &input, client);
+            read_line::enter_rename_mode(ctx, plugin_handle, client_handle, &input, client);
 
             ctx.editor.string_pool.release(input);
 
@@ -773,8 +774,8 @@ pub(crate) fn on_response(
             Ok(())
         }
         "textDocument/codeAction" => {
-            match client.request_state {
-                RequestState::CodeAction => (),
+            let client_handle = match client.request_state {
+                RequestState::CodeAction { client_handle } => client_handle,
                 _ => return Ok(()),
             };
             client.request_state = RequestState::Idle;
@@ -783,19 +784,18 @@ pub(crate) fn on_response(
                 _ => return Ok(()),
             };
 
-            ctx.editor.picker.clear();
+            let picker = &mut ctx.clients.get_mut(client_handle).picker;
+            picker.clear();
             for action in actions
                 .clone()
                 .elements(&client.json)
                 .filter_map(|a| DocumentCodeAction::from_json(a, &client.json).ok())
                 .filter(|a| !a.disabled)
             {
-                ctx.editor
-                    .picker
-                    .add_custom_entry(action.title.as_str(&client.json));
+                picker.add_custom_entry(action.title.as_str(&client.json));
             }
 
-            picker::enter_code_action_mode(ctx, plugin_handle, client);
+            picker::enter_code_action_mode(ctx, plugin_handle, client_handle, client);
 
             client.request_state = RequestState::FinishCodeAction;
             client.request_raw_json.clear();
@@ -806,8 +806,11 @@ pub(crate) fn on_response(
             Ok(())
         }
         "textDocument/documentSymbol" => {
-            let buffer_view_handle = match client.request_state {
-                RequestState::DocumentSymbols { buffer_view_handle } => buffer_view_handle,
+            let (client_handle, buffer_view_handle) = match client.request_state {
+                RequestState::DocumentSymbols {
+                    client_handle,
+                    buffer_view_handle,
+                } => (client_handle, buffer_view_handle),
                 _ => return Ok(()),
             };
             client.request_state = RequestState::Idle;
@@ -843,10 +846,11 @@ pub(crate) fn on_response(
                 }
             }
 
-            ctx.editor.picker.clear();
-            add_symbols(&mut ctx.editor.picker, 0, symbols.clone(), &client.json);
+            let picker = &mut ctx.clients.get_mut(client_handle).picker;
+            picker.clear();
+            add_symbols(picker, 0, symbols.clone(), &client.json);
 
-            picker::enter_document_symbol_mode(ctx, plugin_handle, client);
+            picker::enter_document_symbol_mode(ctx, plugin_handle, client_handle, client);
 
             client.request_state = RequestState::FinishDocumentSymbols { buffer_view_handle };
             client.request_raw_json.clear();
@@ -857,8 +861,8 @@ pub(crate) fn on_response(
             Ok(())
         }
         "workspace/symbol" => {
-            match client.request_state {
-                RequestState::WorkspaceSymbols => (),
+            let client_handle = match client.request_state {
+                RequestState::WorkspaceSymbols { client_handle } => client_handle,
                 _ => return Ok(()),
             };
             client.request_state = RequestState::Idle;
@@ -867,7 +871,8 @@ pub(crate) fn on_response(
                 _ => return Ok(()),
             };
 
-            ctx.editor.picker.clear();
+            let picker = &mut ctx.clients.get_mut(client_handle).picker;
+            picker.clear();
             for symbol in symbols
                 .clone()
                 .elements(&client.json)
@@ -877,15 +882,13 @@ pub(crate) fn on_response(
                 match symbol.container_name {
                     Some(container_name) => {
                         let container_name = container_name.as_str(&client.json);
-                        ctx.editor
-                            .picker
-                            .add_custom_entry_fmt(format_args!("{} ({})", name, container_name,));
+                        picker.add_custom_entry_fmt(format_args!("{} ({})", name, container_name,));
                     }
-                    None => ctx.editor.picker.add_custom_entry(name),
+                    None => picker.add_custom_entry(name),
                 }
             }
 
-            picker::enter_workspace_symbol_mode(ctx, plugin_handle, client);
+            picker::enter_workspace_symbol_mode(ctx, plugin_handle, client_handle, client);
 
             client.request_state = RequestState::FinishWorkspaceSymbols;
             client.request_raw_json.clear();
@@ -938,7 +941,7 @@ pub(crate) fn on_response(
             };
             client.request_state = RequestState::Idle;
 
-            if ctx.editor.mode.kind() != ModeKind::Insert {
+            if ctx.clients.get(client_handle).mode.kind() != ModeKind::Insert {
                 return Ok(());
             }
 
@@ -961,12 +964,21 @@ pub(crate) fn on_response(
                 _ => return Ok(()),
             };
 
-            ctx.editor.picker.clear();
+            let client_state = ctx.clients.get_mut(client_handle);
+            let picker = &mut client_state.picker;
+            let insert_state = &mut client_state.mode.insert_state;
+            picker.clear();
+            insert_state.clear_completion_snippets();
             for completion in completions.elements(&client.json) {
                 if let Ok(completion) = DocumentCompletionItem::from_json(completion, &client.json)
                 {
                     let text = completion.text.as_str(&client.json);
-                    ctx.editor.picker.add_custom_entry(text);
+                    if completion.is_snippet {
+                        insert_state.add_completion_snippet(picker.custom_entries_len(), text);
+                        picker.add_custom_entry(completion.label.as_str(&client.json));
+                    } else {
+                        picker.add_custom_entry(text);
+                    }
                 }
             }
 
@@ -977,9 +989,7 @@ pub(crate) fn on_response(
                 WordKind::Identifier => word.text,
                 _ => "",
             };
-            ctx.editor
-                .picker
-                .filter_completion(ctx.editor.word_database.word_indices(), filter);
+            picker.filter_completion(ctx.editor.word_database.word_indices(), filter);
 
             Ok(())
         }
@@ -1060,7 +1070,8 @@ fn goto_definition(
             Ok(())
         }
         DefinitionLocation::Many(locations) => {
-            ctx.editor.picker.clear();
+            let picker = &mut ctx.clients.get_mut(client_handle).picker;
+            picker.clear();
             for location in locations
                 .elements(&client.json)
                 .filter_map(|l| DocumentLocation::from_json(l, &client.json).ok())
@@ -1075,7 +1086,7 @@ fn goto_definition(
                 };
 
                 let position = location.range.start.into_buffer_position();
-                ctx.editor.picker.add_custom_entry_fmt(format_args!(
+                picker.add_custom_entry_fmt(format_args!(
                     "{}:{},{}",
                     path,
                     position.line_index + 1,
@@ -1083,7 +1094,7 @@ fn goto_definition(
                 ));
             }
 
-            picker::enter_definition_mode(ctx, plugin_handle, client);
+            picker::enter_definition_mode(ctx, plugin_handle, client_handle, client);
             Ok(())
         }
         DefinitionLocation::Invalid => Ok(()),
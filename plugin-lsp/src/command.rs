@@ -217,42 +217,52 @@ pub fn register_commands(commands: &mut CommandManager, plugin_handle: PluginHan
     r("lsp-rename", &[], |ctx, io| {
         io.args.assert_empty()?;
 
+        let client_handle = io.client_handle()?;
         let (buffer_handle, cursor) = current_buffer_and_main_cursor(ctx, io)?;
         let plugin_handle = io.plugin_handle();
 
         access(ctx, io, Some(buffer_handle), |ctx, client| {
-            let op = client.rename(ctx, plugin_handle, buffer_handle, cursor.position);
-            Ok(op)
+            client.rename(
+                ctx,
+                plugin_handle,
+                buffer_handle,
+                cursor.position,
+                client_handle,
+            );
+            Ok(())
         })
     });
 
     r("lsp-code-action", &[], |ctx, io| {
         io.args.assert_empty()?;
 
+        let client_handle = io.client_handle()?;
         let (buffer_handle, cursor) = current_buffer_and_main_cursor(ctx, io)?;
         let plugin_handle = io.plugin_handle();
 
         access(ctx, io, Some(buffer_handle), |ctx, client| {
-            let op = client.code_action(
-                &mut ctx.editor,
+            client.code_action(
+                &ctx.editor,
                 &mut ctx.platform,
                 plugin_handle,
                 buffer_handle,
                 cursor.to_range(),
+                client_handle,
             );
-            Ok(op)
+            Ok(())
         })
     });
 
     r("lsp-document-symbols", &[], |ctx, io| {
         io.args.assert_empty()?;
 
+        let client_handle = io.client_handle()?;
         let view_handle = io.current_buffer_view_handle(ctx)?;
         let buffer_handle = ctx.editor.buffer_views.get(view_handle).buffer_handle;
 
         access(ctx, io, Some(buffer_handle), |ctx, client| {
-            let op = client.document_symbols(&mut ctx.editor, &mut ctx.platform, view_handle);
-            Ok(op)
+            client.document_symbols(&ctx.editor, &mut ctx.platform, view_handle, client_handle);
+            Ok(())
         })
     });
 
@@ -260,10 +270,11 @@ pub fn register_commands(commands: &mut CommandManager, plugin_handle: PluginHan
         let query = io.args.try_next().unwrap_or("");
         io.args.assert_empty()?;
 
+        let client_handle = io.client_handle()?;
         let buffer_handle = io.current_buffer_handle(ctx).ok();
         access(ctx, io, buffer_handle, |ctx, client| {
-            let op = client.workspace_symbols(&mut ctx.editor, &mut ctx.platform, query);
-            Ok(op)
+            client.workspace_symbols(&ctx.editor, &mut ctx.platform, query, client_handle);
+            Ok(())
         })
     });
 
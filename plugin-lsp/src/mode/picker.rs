@@ -5,19 +5,34 @@ use pepper::{
     buffer_position::BufferPosition,
     client::ClientHandle,
     cursor::Cursor,
-    editor::{EditorContext, EditorFlow, KeysIterator},
+    editor::{Editor, EditorContext, EditorFlow, KeysIterator},
     editor_utils::{MessageKind, ReadLinePoll},
     mode::ModeKind,
-    picker::EntrySource,
+    picker::{EntrySource, Picker},
     plugin::PluginHandle,
-    word_database::WordIndicesIter,
+    word_database::{WordDatabase, WordIndicesIter},
 };
 
 use crate::{client::Client, LspPlugin};
 
+fn selected_entry_indices(picker: &mut Picker, word_database: &WordDatabase) -> Vec<usize> {
+    let mut indices = Vec::new();
+    let marked_len = picker.marked_len();
+    for i in 0..marked_len.max(1) {
+        if marked_len > 0 {
+            picker.set_marked_cursor(Some(i));
+        }
+        if let Some((EntrySource::Custom(i), _)) = picker.current_entry(word_database) {
+            indices.push(i);
+        }
+    }
+    indices
+}
+
 pub fn enter_definition_mode(
     ctx: &mut EditorContext,
     plugin_handle: PluginHandle,
+    client_handle: ClientHandle,
     client: &Client,
 ) {
     fn on_client_keys(
@@ -29,8 +44,17 @@ pub fn enter_definition_mode(
         match poll {
             ReadLinePoll::Pending => Some(EditorFlow::Continue),
             ReadLinePoll::Submitted => {
-                if let Some((_, entry)) = ctx.editor.picker.current_entry(&ctx.editor.word_database)
-                {
+                let marked_len = ctx.clients.get(client_handle).picker.marked_len();
+                for i in 0..marked_len.max(1) {
+                    let picker = &mut ctx.clients.get_mut(client_handle).picker;
+                    if marked_len > 0 {
+                        picker.set_marked_cursor(Some(i));
+                    }
+
+                    let entry = match picker.current_entry(&ctx.editor.word_database) {
+                        Some((_, entry)) => entry,
+                        None => break,
+                    };
                     let (path, position) = parse_path_and_position(entry);
                     let position = match position {
                         Some(position) => position,
@@ -71,24 +95,25 @@ pub fn enter_definition_mode(
                     }
                     ctx.editor.string_pool.release(path);
                 }
-                ctx.editor.enter_mode(ModeKind::default());
+                Editor::enter_mode(ctx, client_handle, ModeKind::default());
                 Some(EditorFlow::Continue)
             }
             ReadLinePoll::Canceled => {
-                ctx.editor.enter_mode(ModeKind::default());
+                Editor::enter_mode(ctx, client_handle, ModeKind::default());
                 Some(EditorFlow::Continue)
             }
         }
     }
 
-    ctx.editor.read_line.set_prompt("definition:");
-    ctx.editor.picker.filter(WordIndicesIter::empty(), "");
-    ctx.editor.picker.move_cursor(0);
+    let client_state = ctx.clients.get_mut(client_handle);
+    client_state.read_line.set_prompt("definition:");
+    client_state.picker.filter(WordIndicesIter::empty(), "");
+    client_state.picker.move_cursor(0);
 
-    if ctx.editor.picker.len() > 0 {
-        ctx.editor.mode.plugin_handle = Some(plugin_handle);
-        ctx.editor.mode.picker_state.on_client_keys = on_client_keys;
-        ctx.editor.enter_mode(ModeKind::Picker);
+    if client_state.picker.len() > 0 {
+        client_state.mode.plugin_handle = Some(plugin_handle);
+        client_state.mode.picker_state.on_client_keys = on_client_keys;
+        Editor::enter_mode(ctx, client_handle, ModeKind::Picker);
 
         let lsp = ctx.plugins.get_as::<LspPlugin>(plugin_handle);
         lsp.current_client_handle = Some(client.handle());
@@ -98,38 +123,38 @@ pub fn enter_definition_mode(
 pub fn enter_code_action_mode(
     ctx: &mut EditorContext,
     plugin_handle: PluginHandle,
+    client_handle: ClientHandle,
     client: &mut Client,
 ) {
     fn on_client_keys(
         ctx: &mut EditorContext,
-        _: ClientHandle,
+        client_handle: ClientHandle,
         _: &mut KeysIterator,
         poll: ReadLinePoll,
     ) -> Option<EditorFlow> {
         match poll {
             ReadLinePoll::Pending => Some(EditorFlow::Continue),
             ReadLinePoll::Submitted => {
-                if let Some(handle) = ctx.editor.mode.plugin_handle {
+                if let Some(handle) = ctx.clients.get(client_handle).mode.plugin_handle {
                     let lsp = ctx.plugins.get_as::<LspPlugin>(handle);
                     if let Some(client) = lsp
                         .current_client_handle
                         .take()
                         .and_then(|h| lsp.get_mut(h))
                     {
-                        let index = match ctx.editor.picker.current_entry(&ctx.editor.word_database)
-                        {
-                            Some((EntrySource::Custom(i), _)) => i,
-                            _ => 0,
-                        };
-                        client.finish_code_action(&mut ctx.editor, index);
+                        let indices = selected_entry_indices(
+                            &mut ctx.clients.get_mut(client_handle).picker,
+                            &ctx.editor.word_database,
+                        );
+                        client.finish_code_action(&mut ctx.editor, &indices);
                     }
                 }
 
-                ctx.editor.enter_mode(ModeKind::default());
+                Editor::enter_mode(ctx, client_handle, ModeKind::default());
                 Some(EditorFlow::Continue)
             }
             ReadLinePoll::Canceled => {
-                if let Some(handle) = ctx.editor.mode.plugin_handle {
+                if let Some(handle) = ctx.clients.get(client_handle).mode.plugin_handle {
                     let lsp = ctx.plugins.get_as::<LspPlugin>(handle);
                     if let Some(client) = lsp
                         .current_client_handle
@@ -140,20 +165,21 @@ pub fn enter_code_action_mode(
                     }
                 }
 
-                ctx.editor.enter_mode(ModeKind::default());
+                Editor::enter_mode(ctx, client_handle, ModeKind::default());
                 Some(EditorFlow::Continue)
             }
         }
     }
 
-    ctx.editor.read_line.set_prompt("code action:");
-    ctx.editor.picker.filter(WordIndicesIter::empty(), "");
-    ctx.editor.picker.move_cursor(0);
+    let client_state = ctx.clients.get_mut(client_handle);
+    client_state.read_line.set_prompt("code action:");
+    client_state.picker.filter(WordIndicesIter::empty(), "");
+    client_state.picker.move_cursor(0);
 
-    if ctx.editor.picker.len() > 0 {
-        ctx.editor.mode.plugin_handle = Some(plugin_handle);
-        ctx.editor.mode.picker_state.on_client_keys = on_client_keys;
-        ctx.editor.enter_mode(ModeKind::Picker);
+    if client_state.picker.len() > 0 {
+        client_state.mode.plugin_handle = Some(plugin_handle);
+        client_state.mode.picker_state.on_client_keys = on_client_keys;
+        Editor::enter_mode(ctx, client_handle, ModeKind::Picker);
 
         let lsp = ctx.plugins.get_as::<LspPlugin>(plugin_handle);
         lsp.current_client_handle = Some(client.handle());
@@ -165,6 +191,7 @@ pub fn enter_code_action_mode(
 pub fn enter_document_symbol_mode(
     ctx: &mut EditorContext,
     plugin_handle: PluginHandle,
+    client_handle: ClientHandle,
     client: &mut Client,
 ) {
     fn on_client_keys(
@@ -176,32 +203,31 @@ pub fn enter_document_symbol_mode(
         match poll {
             ReadLinePoll::Pending => Some(EditorFlow::Continue),
             ReadLinePoll::Submitted => {
-                if let Some(handle) = ctx.editor.mode.plugin_handle {
+                if let Some(handle) = ctx.clients.get(client_handle).mode.plugin_handle {
                     let lsp = ctx.plugins.get_as::<LspPlugin>(handle);
                     if let Some(client) = lsp
                         .current_client_handle
                         .take()
                         .and_then(|h| lsp.get_mut(h))
                     {
-                        let index = match ctx.editor.picker.current_entry(&ctx.editor.word_database)
-                        {
-                            Some((EntrySource::Custom(i), _)) => i,
-                            _ => 0,
-                        };
+                        let indices = selected_entry_indices(
+                            &mut ctx.clients.get_mut(client_handle).picker,
+                            &ctx.editor.word_database,
+                        );
                         client.finish_document_symbols(
                             &mut ctx.editor,
                             &mut ctx.clients,
                             client_handle,
-                            index,
+                            &indices,
                         );
                     }
                 }
 
-                ctx.editor.enter_mode(ModeKind::default());
+                Editor::enter_mode(ctx, client_handle, ModeKind::default());
                 Some(EditorFlow::Continue)
             }
             ReadLinePoll::Canceled => {
-                if let Some(handle) = ctx.editor.mode.plugin_handle {
+                if let Some(handle) = ctx.clients.get(client_handle).mode.plugin_handle {
                     let lsp = ctx.plugins.get_as::<LspPlugin>(handle);
                     if let Some(client) = lsp
                         .current_client_handle
@@ -212,20 +238,21 @@ pub fn enter_document_symbol_mode(
                     }
                 }
 
-                ctx.editor.enter_mode(ModeKind::default());
+                Editor::enter_mode(ctx, client_handle, ModeKind::default());
                 Some(EditorFlow::Continue)
             }
         }
     }
 
-    ctx.editor.read_line.set_prompt("document symbol:");
-    ctx.editor.picker.filter(WordIndicesIter::empty(), "");
-    ctx.editor.picker.move_cursor(0);
+    let client_state = ctx.clients.get_mut(client_handle);
+    client_state.read_line.set_prompt("document symbol:");
+    client_state.picker.filter(WordIndicesIter::empty(), "");
+    client_state.picker.move_cursor(0);
 
-    if ctx.editor.picker.len() > 0 {
-        ctx.editor.mode.plugin_handle = Some(plugin_handle);
-        ctx.editor.mode.picker_state.on_client_keys = on_client_keys;
-        ctx.editor.enter_mode(ModeKind::Picker);
+    if client_state.picker.len() > 0 {
+        client_state.mode.plugin_handle = Some(plugin_handle);
+        client_state.mode.picker_state.on_client_keys = on_client_keys;
+        Editor::enter_mode(ctx, client_handle, ModeKind::Picker);
 
         let lsp = ctx.plugins.get_as::<LspPlugin>(plugin_handle);
         lsp.current_client_handle = Some(client.handle());
@@ -237,6 +264,7 @@ pub fn enter_document_symbol_mode(
 pub fn enter_workspace_symbol_mode(
     ctx: &mut EditorContext,
     plugin_handle: PluginHandle,
+    client_handle: ClientHandle,
     client: &mut Client,
 ) {
     fn on_client_keys(
@@ -248,32 +276,31 @@ pub fn enter_workspace_symbol_mode(
         match poll {
             ReadLinePoll::Pending => Some(EditorFlow::Continue),
             ReadLinePoll::Submitted => {
-                if let Some(handle) = ctx.editor.mode.plugin_handle {
+                if let Some(handle) = ctx.clients.get(client_handle).mode.plugin_handle {
                     let lsp = ctx.plugins.get_as::<LspPlugin>(handle);
                     if let Some(client) = lsp
                         .current_client_handle
                         .take()
                         .and_then(|h| lsp.get_mut(h))
                     {
-                        let index = match ctx.editor.picker.current_entry(&ctx.editor.word_database)
-                        {
-                            Some((EntrySource::Custom(i), _)) => i,
-                            _ => 0,
-                        };
+                        let indices = selected_entry_indices(
+                            &mut ctx.clients.get_mut(client_handle).picker,
+                            &ctx.editor.word_database,
+                        );
                         client.finish_workspace_symbols(
                             &mut ctx.editor,
                             &mut ctx.clients,
                             client_handle,
-                            index,
+                            &indices,
                         );
                     }
                 }
 
-                ctx.editor.enter_mode(ModeKind::default());
+                Editor::enter_mode(ctx, client_handle, ModeKind::default());
                 Some(EditorFlow::Continue)
             }
             ReadLinePoll::Canceled => {
-                if let Some(handle) = ctx.editor.mode.plugin_handle {
+                if let Some(handle) = ctx.clients.get(client_handle).mode.plugin_handle {
                     let lsp = ctx.plugins.get_as::<LspPlugin>(handle);
                     if let Some(client) = lsp
                         .current_client_handle
@@ -284,20 +311,21 @@ pub fn enter_workspace_symbol_mode(
                     }
                 }
 
-                ctx.editor.enter_mode(ModeKind::default());
+                Editor::enter_mode(ctx, client_handle, ModeKind::default());
                 Some(EditorFlow::Continue)
             }
         }
     }
 
-    ctx.editor.read_line.set_prompt("workspace symbol:");
-    ctx.editor.picker.filter(WordIndicesIter::empty(), "");
-    ctx.editor.picker.move_cursor(0);
+    let client_state = ctx.clients.get_mut(client_handle);
+    client_state.read_line.set_prompt("workspace symbol:");
+    client_state.picker.filter(WordIndicesIter::empty(), "");
+    client_state.picker.move_cursor(0);
 
-    if ctx.editor.picker.len() > 0 {
-        ctx.editor.mode.plugin_handle = Some(plugin_handle);
-        ctx.editor.mode.picker_state.on_client_keys = on_client_keys;
-        ctx.editor.enter_mode(ModeKind::Picker);
+    if client_state.picker.len() > 0 {
+        client_state.mode.plugin_handle = Some(plugin_handle);
+        client_state.mode.picker_state.on_client_keys = on_client_keys;
+        Editor::enter_mode(ctx, client_handle, ModeKind::Picker);
 
         let lsp = ctx.plugins.get_as::<LspPlugin>(plugin_handle);
         lsp.current_client_handle = Some(client.handle());
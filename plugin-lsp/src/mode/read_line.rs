@@ -1,6 +1,6 @@
 use pepper::{
     client::ClientHandle,
-    editor::{EditorContext, EditorFlow, KeysIterator},
+    editor::{Editor, EditorContext, EditorFlow, KeysIterator},
     editor_utils::ReadLinePoll,
     mode::ModeKind,
     plugin::PluginHandle,
@@ -11,34 +11,36 @@ use crate::{client::Client, LspPlugin};
 pub fn enter_rename_mode(
     ctx: &mut EditorContext,
     plugin_handle: PluginHandle,
+    client_handle: ClientHandle,
     placeholder: &str,
     client: &Client,
 ) {
     fn on_client_keys(
         ctx: &mut EditorContext,
-        _: ClientHandle,
+        client_handle: ClientHandle,
         _: &mut KeysIterator,
         poll: ReadLinePoll,
     ) -> Option<EditorFlow> {
         match poll {
             ReadLinePoll::Pending => Some(EditorFlow::Continue),
             ReadLinePoll::Submitted => {
-                if let Some(handle) = ctx.editor.mode.plugin_handle {
+                if let Some(handle) = ctx.clients.get(client_handle).mode.plugin_handle {
                     let lsp = ctx.plugins.get_as::<LspPlugin>(handle);
                     if let Some(client) = lsp
                         .current_client_handle
                         .take()
                         .and_then(|h| lsp.get_mut(h))
                     {
-                        client.finish_rename(&mut ctx.editor, &mut ctx.platform);
+                        let new_name = ctx.clients.get(client_handle).read_line.input();
+                        client.finish_rename(&ctx.editor, &mut ctx.platform, new_name);
                     }
                 }
 
-                ctx.editor.enter_mode(ModeKind::default());
+                Editor::enter_mode(ctx, client_handle, ModeKind::default());
                 Some(EditorFlow::Continue)
             }
             ReadLinePoll::Canceled => {
-                if let Some(handle) = ctx.editor.mode.plugin_handle {
+                if let Some(handle) = ctx.clients.get(client_handle).mode.plugin_handle {
                     let lsp = ctx.plugins.get_as::<LspPlugin>(handle);
                     if let Some(client) = lsp
                         .current_client_handle
@@ -49,18 +51,23 @@ pub fn enter_rename_mode(
                     }
                 }
 
-                ctx.editor.enter_mode(ModeKind::default());
+                Editor::enter_mode(ctx, client_handle, ModeKind::default());
                 Some(EditorFlow::Continue)
             }
         }
     }
 
-    ctx.editor.read_line.set_prompt("rename:");
+    let client_state = ctx.clients.get_mut(client_handle);
+    client_state.read_line.set_prompt("rename:");
 
-    ctx.editor.mode.plugin_handle = Some(plugin_handle);
-    ctx.editor.mode.read_line_state.on_client_keys = on_client_keys;
-    ctx.editor.enter_mode(ModeKind::ReadLine);
-    ctx.editor.read_line.input_mut().push_str(placeholder);
+    client_state.mode.plugin_handle = Some(plugin_handle);
+    client_state.mode.read_line_state.on_client_keys = on_client_keys;
+    Editor::enter_mode(ctx, client_handle, ModeKind::ReadLine);
+    ctx.clients
+        .get_mut(client_handle)
+        .read_line
+        .input_mut()
+        .push_str(placeholder);
 
     let lsp = ctx.plugins.get_as::<LspPlugin>(plugin_handle);
     lsp.current_client_handle = Some(client.handle());
@@ -10,6 +10,7 @@ use pepper::{
     buffer_position::{BufferPosition, BufferRange},
     editor::Editor,
     editor_utils::MessageKind,
+    editorconfig::load_buffer_format,
     glob::InvalidGlobError,
     platform::{Platform, PlatformProcessHandle, PlatformRequest},
 };
@@ -623,6 +624,7 @@ impl WorkspaceEdit {
                     buffer.properties = BufferProperties::scratch();
                     buffer.properties.saving_enabled = true;
                     buffer.set_path(path);
+                    load_buffer_format(&editor.current_directory, buffer);
                     let _ = buffer.read_from_file(&mut editor.word_database, &mut editor.events);
                     (true, buffer.handle())
                 }
@@ -635,10 +637,11 @@ impl WorkspaceEdit {
             TextEdit::apply_edits(editor, buffer_handle, temp_edits, text_edits, json);
 
             if is_temp {
-                let _ = editor
-                    .buffers
-                    .get_mut(buffer_handle)
-                    .write_to_file(None, &mut editor.events);
+                let _ = editor.buffers.get_mut(buffer_handle).write_to_file(
+                    &mut editor.word_database,
+                    None,
+                    &mut editor.events,
+                );
 
                 editor
                     .buffers
@@ -668,6 +671,7 @@ impl WorkspaceEdit {
                             buffer.properties = BufferProperties::scratch();
                             buffer.properties.saving_enabled = true;
                             buffer.set_path(path);
+                            load_buffer_format(&editor.current_directory, buffer);
                             let _ = buffer
                                 .read_from_file(&mut editor.word_database, &mut editor.events);
                             (true, buffer.handle())
@@ -677,10 +681,11 @@ impl WorkspaceEdit {
                     TextEdit::apply_edits(editor, buffer_handle, temp_edits, edit.edits, json);
 
                     if is_temp {
-                        let _ = editor
-                            .buffers
-                            .get_mut(buffer_handle)
-                            .write_to_file(None, &mut editor.events);
+                        let _ = editor.buffers.get_mut(buffer_handle).write_to_file(
+                            &mut editor.word_database,
+                            None,
+                            &mut editor.events,
+                        );
 
                         editor
                             .buffers
@@ -881,7 +886,9 @@ impl<'json> FromJson<'json> for DocumentSymbolInformation {
 
 #[derive(Default)]
 pub struct DocumentCompletionItem {
+    pub label: JsonString,
     pub text: JsonString,
+    pub is_snippet: bool,
 }
 impl<'json> FromJson<'json> for DocumentCompletionItem {
     fn from_json(value: JsonValue, json: &'json Json) -> Result<Self, JsonConvertError> {
@@ -890,16 +897,30 @@ impl<'json> FromJson<'json> for DocumentCompletionItem {
             _ => return Err(JsonConvertError),
         };
         let mut this = Self::default();
+        let mut edit_text = JsonString::default();
         for (key, value) in value.members(json) {
             match key {
-                "label" => this.text = JsonString::from_json(value, json)?,
-                "insertText" => {
-                    this.text = JsonString::from_json(value, json)?;
-                    break;
+                "label" => this.label = JsonString::from_json(value, json)?,
+                "insertText" => this.text = JsonString::from_json(value, json)?,
+                "insertTextFormat" => this.is_snippet = JsonInteger::from_json(value, json)? == 2,
+                // both `TextEdit` and `InsertReplaceEdit` have a `newText`
+                "textEdit" => {
+                    if let JsonValue::Object(edit) = value {
+                        for (key, value) in edit.members(json) {
+                            if key == "newText" {
+                                edit_text = JsonString::from_json(value, json)?;
+                            }
+                        }
+                    }
                 }
                 _ => (),
             }
         }
+        if !edit_text.as_str(json).is_empty() {
+            this.text = edit_text;
+        } else if this.text.as_str(json).is_empty() {
+            this.text = this.label.clone();
+        }
         Ok(this)
     }
 }
@@ -1190,4 +1211,34 @@ mod tests {
             assert_uri("c:/file.rs", "file:///c:/file.rs");
         }
     }
+
+    #[test]
+    fn parse_completion_item_text() {
+        fn completion_text(item: &str) -> String {
+            let mut json = Json::new();
+            let value = json.read(&mut item.as_bytes()).unwrap();
+            match DocumentCompletionItem::from_json(value, &json) {
+                Ok(item) => item.text.as_str(&json).into(),
+                Err(_) => panic!("could not parse completion item {}", item),
+            }
+        }
+
+        assert_eq!("label", completion_text(r#"{"label":"label"}"#));
+        assert_eq!(
+            "insert",
+            completion_text(r#"{"label":"label","insertText":"insert"}"#)
+        );
+        assert_eq!(
+            "edit($1)",
+            completion_text(
+                r#"{"label":"label","insertText":"insert","textEdit":{"range":{"start":{"line":0,"character":0},"end":{"line":0,"character":1}},"newText":"edit($1)"}}"#
+            )
+        );
+        assert_eq!(
+            "replace",
+            completion_text(
+                r#"{"label":"label","textEdit":{"newText":"replace","insert":{"start":{"line":0,"character":0},"end":{"line":0,"character":1}},"replace":{"start":{"line":0,"character":0},"end":{"line":0,"character":2}}}}"#
+            )
+        );
+    }
 }